@@ -21,6 +21,30 @@
         '';
         default = "127.0.0.1:8734";
       };
+      extraListenAddresses = lib.mkOption {
+        type = lib.types.listOf lib.types.str;
+        description = ''
+          Additional addresses to listen for HTTP requests, as `IP:PORT` or `unix:PATH` for a Unix
+          domain socket (served without TLS)
+        '';
+        default = [];
+        example = ["[fd7a:115c:a1e0::1]:8734" "unix:/run/zpool-status-exporter/metrics.sock"];
+      };
+      socketActivation = lib.mkOption {
+        type = lib.types.bool;
+        description = ''
+          If `true`, systemd listens on `listen_address` and `extraListenAddresses` (socket
+          activation), starting the service on the first request.
+        '';
+        default = false;
+      };
+      unixSocketMode = lib.mkOption {
+        type = lib.types.nullOr (lib.types.strMatching "[0-7]{3,4}");
+        description = ''
+          Permissions (octal) of the Unix domain socket files (default `660`)
+        '';
+        default = null;
+      };
       package = lib.mkOption {
         type = lib.types.package;
         default = pkgs.zpool-status-exporter;
@@ -48,13 +72,41 @@
         '';
         default = "zpool-status-exporter";
       };
+      dropPrivileges = lib.mkOption {
+        type = lib.types.bool;
+        description = ''
+          If `true`, starts the service as root and switches to `user` and `group` after binding
+          the listeners (e.g. for ports below 1024).
+        '';
+        default = false;
+      };
       basic_auth_keys_file = lib.mkOption {
         type = lib.types.nullOr lib.types.path;
         description = ''
-          Path to the file containing lines `user:pass` specifying allowed Basic authentication credentials
+          Path to the file containing lines `user:hash` specifying allowed Basic authentication credentials,
+          with bcrypt (as in the Prometheus `web-config.yml`) or argon2 hashes. Plaintext `user:pass` lines
+          are deprecated.
+
+          The file is re-read on reload (SIGHUP).
+        '';
+        default = null;
+      };
+      bearerTokenFile = lib.mkOption {
+        type = lib.types.nullOr lib.types.path;
+        description = ''
+          Path to the file containing allowed Bearer tokens, one per line.
+
+          The file is re-read on reload (SIGHUP).
         '';
         default = null;
       };
+      watchAuthFiles = lib.mkOption {
+        type = lib.types.bool;
+        description = ''
+          If `true`, also re-reads the `basic_auth_keys_file` and `bearerTokenFile` when modified.
+        '';
+        default = false;
+      };
       wants = lib.mkOption {
         type = lib.types.listOf unitNameType;
         description = ''
@@ -91,6 +143,135 @@
         '';
         default = 5;
       };
+      metricPrefix = lib.mkOption {
+        type = lib.types.strMatching "[a-zA-Z_:][a-zA-Z0-9_:]*";
+        description = ''
+          Prefix for all metric names.
+        '';
+        default = "zpool";
+      };
+      constLabels = lib.mkOption {
+        type = lib.types.attrsOf (lib.types.strMatching "[^,]*");
+        description = ''
+          Labels added to every metric, e.g. `{ host = "nas"; }`.
+        '';
+        default = {};
+        example = {
+          host = "nas";
+          site = "home";
+        };
+      };
+      failureResponse = lib.mkOption {
+        type = lib.types.enum ["up-zero" "unavailable"];
+        description = ''
+          Response when `zpool status` fails: `up-zero` for HTTP 200 with `zpool_up 0` (and the
+          last successful metrics), or `unavailable` for HTTP 503 with the error description.
+        '';
+        default = "up-zero";
+      };
+      pollInterval = lib.mkOption {
+        type = lib.types.nullOr lib.types.ints.positive;
+        description = ''
+          If set, runs `zpool status` in the background every N seconds, serving the latest results.
+        '';
+        default = null;
+      };
+      minInterval = lib.mkOption {
+        type = lib.types.nullOr lib.types.ints.positive;
+        description = ''
+          If set, reuses `zpool status` results for N seconds, sharing one run between concurrent
+          requests. Conflicts with `pollInterval`.
+        '';
+        default = null;
+      };
+      authenticateHealth = lib.mkOption {
+        type = lib.types.bool;
+        description = ''
+          If `true`, the `/-/healthy` and `/-/ready` endpoints require authentication (by default
+          public, like the root page).
+        '';
+        default = false;
+      };
+      readyMaxAge = lib.mkOption {
+        type = lib.types.nullOr lib.types.ints.positive;
+        description = ''
+          If set, `/-/ready` reports not ready when the last successful collection is older than N
+          seconds (useful with `pollInterval`).
+        '';
+        default = null;
+      };
+      watchdogSec = lib.mkOption {
+        type = lib.types.nullOr lib.types.ints.positive;
+        description = ''
+          If set, systemd restarts the service when the server sends no watchdog keepalive for N
+          seconds (e.g. when `zpool status` or the request workers hang).
+        '';
+        default = null;
+      };
+      zpoolPath = lib.mkOption {
+        type = lib.types.nullOr lib.types.str;
+        description = ''
+          Path of the `zpool` binary. By default, `zpool` is found in the service `PATH` (the
+          package of `boot.zfs.package`).
+        '';
+        default = null;
+      };
+      zpoolWrapper = lib.mkOption {
+        type = lib.types.listOf (lib.types.strMatching "[^[:space:]]+");
+        description = ''
+          Command and arguments prepended to the `zpool` command, e.g. to run it with additional
+          privileges when `user` cannot access `/dev/zfs`.
+        '';
+        default = [];
+        example = ["/run/wrappers/bin/sudo" "--non-interactive"];
+      };
+      zpoolTimeout = lib.mkOption {
+        type = lib.types.nullOr lib.types.ints.positive;
+        description = ''
+          If set, kills `zpool status` when it runs for more than N seconds (default 15).
+        '';
+        default = null;
+      };
+      tlsCertFile = lib.mkOption {
+        type = lib.types.nullOr lib.types.str;
+        description = ''
+          Path to the PEM certificate chain for serving HTTPS (reloaded when modified).
+          Requires `tlsKeyFile`.
+        '';
+        default = null;
+      };
+      tlsKeyFile = lib.mkOption {
+        type = lib.types.nullOr lib.types.str;
+        description = ''
+          Path to the PEM private key for serving HTTPS (reloaded when modified).
+        '';
+        default = null;
+      };
+      tlsClientCaFile = lib.mkOption {
+        type = lib.types.nullOr lib.types.str;
+        description = ''
+          Path to the PEM CA certificates for requiring and verifying client certificates (mTLS).
+        '';
+        default = null;
+      };
+      allowCidrs = lib.mkOption {
+        type = lib.types.listOf lib.types.str;
+        description = ''
+          Networks (`address/prefix-length`) allowed to connect, checked before authentication.
+
+          When empty, all clients are allowed.
+        '';
+        default = [];
+        example = ["192.0.2.0/24" "2001:db8::/32"];
+      };
+      trustedProxies = lib.mkOption {
+        type = lib.types.listOf lib.types.str;
+        description = ''
+          Networks of proxies trusted to report the client address in the `X-Forwarded-For` header,
+          for checking `allowCidrs`.
+        '';
+        default = [];
+      };
     };
     config = lib.mkIf cfg.enable {
       nixpkgs.overlays = [
@@ -110,18 +291,58 @@
           (cfg)
           user
           group
+          dropPrivileges
           listen_address
+          extraListenAddresses
+          socketActivation
+          unixSocketMode
           basic_auth_keys_file
+          bearerTokenFile
+          watchAuthFiles
           wants
           after
           binds_to
           exec_start_pre
           maxBindRetries
+          metricPrefix
+          constLabels
+          failureResponse
+          pollInterval
+          minInterval
+          authenticateHealth
+          readyMaxAge
+          watchdogSec
+          zpoolPath
+          zpoolWrapper
+          zpoolTimeout
+          tlsCertFile
+          tlsKeyFile
+          tlsClientCaFile
+          allowCidrs
+          trustedProxies
           ;
         zpool-status-exporter = cfg.package;
         zfs = config.boot.zfs.package;
       };
+      systemd.sockets.${name} = lib.mkIf cfg.socketActivation ((import ./systemd.nix).socket {
+        inherit name;
+        listenStreams =
+          builtins.map (lib.removePrefix "unix:") ([cfg.listen_address] ++ cfg.extraListenAddresses);
+        inherit (cfg) unixSocketMode;
+      });
       assertions = [
+        {
+          assertion = (cfg.tlsCertFile == null) == (cfg.tlsKeyFile == null);
+          message = "`tlsCertFile` and `tlsKeyFile` must be set together";
+        }
+        {
+          assertion = cfg.tlsClientCaFile == null || cfg.tlsCertFile != null;
+          message = "`tlsClientCaFile` requires `tlsCertFile` and `tlsKeyFile`";
+        }
+        {
+          assertion = cfg.trustedProxies == [] || cfg.allowCidrs != [];
+          message = "`trustedProxies` requires `allowCidrs`";
+        }
         {
           assertion = config.boot.zfs.enabled;
           message = ''
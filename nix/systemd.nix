@@ -57,14 +57,38 @@ in rec {
     zfs,
     name,
     listen_address,
+    extraListenAddresses ? [],
+    # listeners are passed by the `socket` unit (the addresses are not bound by the service)
+    socketActivation ? false,
+    unixSocketMode ? null,
     basic_auth_keys_file,
+    bearerTokenFile ? null,
+    watchAuthFiles ? false,
     user ? "zpool-status-exporter",
     group ? "zpool-status-exporter",
+    # start as root, switching to `user` and `group` after binding the listeners
+    dropPrivileges ? false,
     wants ? [],
     after ? [],
     binds_to ? [],
     exec_start_pre ? [],
     maxBindRetries ? 5,
+    metricPrefix ? "zpool",
+    constLabels ? {},
+    failureResponse ? "up-zero",
+    pollInterval ? null,
+    minInterval ? null,
+    authenticateHealth ? false,
+    readyMaxAge ? null,
+    watchdogSec ? null,
+    zpoolPath ? null,
+    zpoolWrapper ? [],
+    zpoolTimeout ? null,
+    tlsCertFile ? null,
+    tlsKeyFile ? null,
+    tlsClientCaFile ? null,
+    allowCidrs ? [],
+    trustedProxies ? [],
   }: {
     description = "${name} Web Server";
     serviceConfig =
@@ -74,18 +98,148 @@ in rec {
         # "main" is the default for Type="notify", but why not be explicit
         NotifyAccess = "main";
         ExecStart = "${zpool-status-exporter}/bin/zpool-status-exporter";
-        User = user;
-        Group = group;
+        # Re-reads the basic auth keys and bearer token files
+        ExecReload = "kill -HUP $MAINPID";
         ExecStartPre = exec_start_pre;
       }
-      // hardening;
-    wantedBy = ["default.target"];
+      // (
+        if dropPrivileges
+        then {}
+        else {
+          User = user;
+          Group = group;
+        }
+      )
+      # Binary sends `sd_notify` keepalives while the serve loop and `zpool status` make progress
+      // (
+        if watchdogSec == null
+        then {}
+        else {
+          WatchdogSec = watchdogSec;
+          Restart = "on-failure";
+        }
+      )
+      // hardening
+      // (
+        if dropPrivileges
+        then {CapabilityBoundingSet = ["CAP_SETUID" "CAP_SETGID" "CAP_CHOWN" "CAP_NET_BIND_SERVICE"];}
+        else {}
+      );
+    # with socket activation, started by the first request
+    wantedBy =
+      if socketActivation
+      then []
+      else ["default.target"];
     path = [zfs];
-    environment = {
-      LISTEN_ADDRESS = listen_address;
-      BASIC_AUTH_KEYS_FILE = basic_auth_keys_file;
-      MAX_BIND_RETRIES = toString maxBindRetries;
-    };
+    environment =
+      {
+        BASIC_AUTH_KEYS_FILE = basic_auth_keys_file;
+        MAX_BIND_RETRIES = toString maxBindRetries;
+        METRIC_PREFIX = metricPrefix;
+        FAILURE_RESPONSE = failureResponse;
+      }
+      // (
+        if dropPrivileges
+        then {
+          RUN_AS_USER = user;
+          RUN_AS_GROUP = group;
+        }
+        else {}
+      )
+      # NOTE: omit when empty, since an empty value is not a valid label
+      // (
+        if constLabels == {}
+        then {}
+        else {
+          CONST_LABEL = let
+            pairs = builtins.mapAttrs (name: value: "${name}=${value}") constLabels;
+          in
+            builtins.concatStringsSep "," (builtins.attrValues pairs);
+        }
+      )
+      // (
+        if bearerTokenFile == null
+        then {}
+        else {BEARER_TOKEN_FILE = bearerTokenFile;}
+      )
+      // (
+        if socketActivation
+        then {}
+        else {LISTEN_ADDRESS = listen_address;}
+      )
+      // (
+        if socketActivation || extraListenAddresses == []
+        then {}
+        else {LISTEN = builtins.concatStringsSep "," extraListenAddresses;}
+      )
+      // (
+        if unixSocketMode == null
+        then {}
+        else {UNIX_SOCKET_MODE = unixSocketMode;}
+      )
+      // (
+        if allowCidrs == []
+        then {}
+        else {ALLOW_CIDR = builtins.concatStringsSep "," allowCidrs;}
+      )
+      // (
+        if trustedProxies == []
+        then {}
+        else {TRUSTED_PROXY = builtins.concatStringsSep "," trustedProxies;}
+      )
+      // (
+        if watchAuthFiles
+        then {WATCH_AUTH_FILES = "true";}
+        else {}
+      )
+      // (
+        if authenticateHealth
+        then {AUTHENTICATE_HEALTH = "true";}
+        else {}
+      )
+      // (
+        if readyMaxAge == null
+        then {}
+        else {READY_MAX_AGE = toString readyMaxAge;}
+      )
+      // (
+        if zpoolPath == null
+        then {}
+        else {ZPOOL_PATH = zpoolPath;}
+      )
+      // (
+        if zpoolWrapper == []
+        then {}
+        else {ZPOOL_WRAPPER = builtins.concatStringsSep " " zpoolWrapper;}
+      )
+      // (
+        if zpoolTimeout == null
+        then {}
+        else {ZPOOL_TIMEOUT = toString zpoolTimeout;}
+      )
+      // (
+        if pollInterval == null
+        then {}
+        else {POLL_INTERVAL = toString pollInterval;}
+      )
+      // (
+        if minInterval == null
+        then {}
+        else {MIN_INTERVAL = toString minInterval;}
+      )
+      // (
+        if tlsCertFile == null
+        then {}
+        else {
+          TLS_CERT_FILE = tlsCertFile;
+          TLS_KEY_FILE = tlsKeyFile;
+        }
+      )
+      // (
+        if tlsClientCaFile == null
+        then {}
+        else {TLS_CLIENT_CA_FILE = tlsClientCaFile;}
+      );
     inherit
       wants
       after
@@ -93,6 +247,26 @@ in rec {
     bindsTo = binds_to;
   };
 
+  socket = {
+    name,
+    # `ListenStream` values, `IP:PORT` or the path of a Unix domain socket
+    listenStreams,
+    unixSocketMode ? null,
+  }: {
+    description = "${name} Web Server Socket";
+    wantedBy = ["sockets.target"];
+    inherit listenStreams;
+    socketConfig =
+      {
+        FileDescriptorName = "metrics";
+      }
+      // (
+        if unixSocketMode == null
+        then {}
+        else {SocketMode = unixSocketMode;}
+      );
+  };
+
   render_service = {
     pkgs,
     name,
@@ -171,13 +345,34 @@ in rec {
   }: let
     input_params = {
       listen_address = "127.0.0.1:4589739485";
+      extraListenAddresses = ["[fd7a:115c:a1e0::1]:4589739485" "unix:/run/zpool-status-exporter/metrics.sock"];
+      unixSocketMode = "660";
       basic_auth_keys_file = "/path/to/secrets/basic_auth_keys_file.txt";
+      bearerTokenFile = "/path/to/secrets/bearer_tokens.txt";
+      watchAuthFiles = true;
       user = "my-special-user";
       wants = ["wants-some-other.service" "wants-another.service"];
       after = ["after1.service" "after2.service"];
       binds_to = ["binds-to-1.device" "binds-to-2.device"];
       exec_start_pre = ["exec-start-pre.sh" "also-this.sh"];
       maxBindRetries = 10;
+      metricPrefix = "zfs";
+      constLabels = {
+        host = "my-host";
+        site = "my-site";
+      };
+      failureResponse = "unavailable";
+      pollInterval = 30;
+      authenticateHealth = true;
+      readyMaxAge = 120;
+      watchdogSec = 60;
+      zpoolWrapper = ["/run/wrappers/bin/sudo" "--non-interactive"];
+      zpoolTimeout = 30;
+      tlsCertFile = "/path/to/secrets/cert.pem";
+      tlsKeyFile = "/path/to/secrets/key.pem";
+      tlsClientCaFile = "/path/to/client-ca.pem";
+      allowCidrs = ["192.0.2.0/24" "2001:db8::/32"];
+      trustedProxies = ["127.0.0.1"];
     };
 
     # use `pkgs` and `nixosModules` to build a system, to examine systemd output
@@ -203,12 +398,31 @@ in rec {
                 (input_params)
                 user
                 listen_address
+                extraListenAddresses
+                unixSocketMode
                 basic_auth_keys_file
+                bearerTokenFile
+                watchAuthFiles
                 wants
                 after
                 binds_to
                 exec_start_pre
                 maxBindRetries
+                metricPrefix
+                constLabels
+                failureResponse
+                pollInterval
+                authenticateHealth
+                readyMaxAge
+                watchdogSec
+                zpoolPath
+                zpoolWrapper
+                zpoolTimeout
+                tlsCertFile
+                tlsKeyFile
+                tlsClientCaFile
+                allowCidrs
+                trustedProxies
                 ;
             };
           })
@@ -238,12 +452,31 @@ in rec {
             (input_params)
             user
             listen_address
+            extraListenAddresses
+            unixSocketMode
             basic_auth_keys_file
+            bearerTokenFile
+            watchAuthFiles
             wants
             after
             binds_to
             exec_start_pre
             maxBindRetries
+            metricPrefix
+            constLabels
+            failureResponse
+            pollInterval
+            authenticateHealth
+            readyMaxAge
+            watchdogSec
+            zpoolPath
+            zpoolWrapper
+            zpoolTimeout
+            tlsCertFile
+            tlsKeyFile
+            tlsClientCaFile
+            allowCidrs
+            trustedProxies
             ;
         };
       };
@@ -4,7 +4,9 @@
 #![allow(clippy::module_name_repetitions)]
 
 pub(crate) use file::Error as FileError;
-use std::sync::OnceLock;
+pub(crate) use reload::RulesFile;
+use secret::Secret;
+use std::{path::PathBuf, sync::OnceLock};
 
 static HEADER_AUTHORIZATION: OnceLock<tiny_http::HeaderField> = OnceLock::new();
 fn get_header_authorization() -> &'static tiny_http::HeaderField {
@@ -12,55 +14,148 @@ fn get_header_authorization() -> &'static tiny_http::HeaderField {
         .get_or_init(|| tiny_http::HeaderField::from_bytes("Authorization").expect("ascii"))
 }
 
-static HEADER_AUTHENTICATE: OnceLock<tiny_http::Header> = OnceLock::new();
 #[allow(clippy::missing_panics_doc)]
-pub(crate) fn get_header_www_authenticate() -> tiny_http::Header {
-    HEADER_AUTHENTICATE
-        .get_or_init(|| {
-            let field = tiny_http::HeaderField::from_bytes("WWW-Authenticate").expect("ascii");
-            let value = ascii::AsciiString::from_ascii("Basic").expect("ascii");
-            tiny_http::Header { field, value }
-        })
-        .clone()
+fn header_www_authenticate(value: &str) -> tiny_http::Header {
+    let field = tiny_http::HeaderField::from_bytes("WWW-Authenticate").expect("ascii");
+    let value = ascii::AsciiString::from_ascii(value).expect("ascii");
+    tiny_http::Header { field, value }
+}
+
+/// Authentication schemes to offer in the response to a request without (usable) credentials
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) struct Challenge {
+    basic: bool,
+    bearer: bool,
+}
+impl Challenge {
+    /// Returns the `WWW-Authenticate` headers
+    pub fn headers(self) -> Vec<tiny_http::Header> {
+        let Self { basic, bearer } = self;
+        [(basic, "Basic"), (bearer, "Bearer")]
+            .into_iter()
+            .filter(|&(enabled, _)| enabled)
+            .map(|(_, scheme)| header_www_authenticate(scheme))
+            .collect()
+    }
+    /// Returns the `WWW-Authenticate` header for a rejected bearer token
+    pub fn header_invalid_token() -> tiny_http::Header {
+        header_www_authenticate(r#"Bearer error="invalid_token""#)
+    }
 }
 
 /// Configuration for authentication rules
 pub(crate) struct AuthRules {
-    entries_sorted: Box<[String]>,
+    /// Basic authentication entries (empty if not configured)
+    entries: Box<[Entry]>,
+    /// Bearer tokens (empty if not configured)
+    tokens: Box<[String]>,
+}
+/// Files containing the authentication rules
+#[derive(Clone)]
+pub(crate) struct AuthFiles {
+    /// Lines of `user:secret` for basic authentication
+    pub basic_auth_keys: Option<PathBuf>,
+    /// Lines of bearer tokens
+    pub bearer_tokens: Option<PathBuf>,
+}
+/// Allowed credentials for a user
+struct Entry {
+    user: String,
+    secret: Secret,
 }
 mod file {
-    use super::AuthRules;
+    use super::{AuthFiles, AuthRules, Entry, Secret};
+    use std::path::Path;
 
     impl AuthRules {
-        /// Attempt to construct rules from a plaintext file
+        /// Attempt to construct rules from the files
         ///
         /// # Errors
-        /// Returns an error if the file IO fails
-        pub fn from_file(file: impl AsRef<std::path::Path>) -> Result<Self, Error> {
-            let file = file.as_ref();
-            let make_error = |kind| Error {
-                file: file.to_owned(),
-                kind,
-            };
+        /// Returns an error if the file IO fails, a line is invalid, or a file has no entries
+        pub fn from_files(files: &AuthFiles) -> Result<Self, Error> {
+            let AuthFiles {
+                basic_auth_keys,
+                bearer_tokens,
+            } = files;
+            let entries = basic_auth_keys
+                .as_deref()
+                .map(read_entries)
+                .transpose()?
+                .unwrap_or_default();
+            let tokens = bearer_tokens
+                .as_deref()
+                .map(read_tokens)
+                .transpose()?
+                .unwrap_or_default();
+            Ok(Self {
+                entries: entries.into_boxed_slice(),
+                tokens: tokens.into_boxed_slice(),
+            })
+        }
+        /// Returns the number of entries loaded from each of the [`AuthFiles`]
+        pub(super) fn counts(&self) -> [usize; 2] {
+            [self.entries.len(), self.tokens.len()]
+        }
+    }
+
+    /// Reads a file of `user:secret` lines
+    ///
+    /// The secret is a bcrypt hash (as in the Prometheus `web-config.yml`), an argon2 hash (PHC
+    /// string format), or the plaintext password (deprecated)
+    fn read_entries(file: &Path) -> Result<Vec<Entry>, Error> {
+        let make_error = |kind| Error {
+            file: file.to_owned(),
+            kind,
+        };
 
-            let content = std::fs::read_to_string(file)
-                .map_err(ErrorKind::IO)
-                .map_err(make_error)?;
-            let lines = content.lines().map(String::from);
-            Self::from_entries(lines)
-                .ok_or(ErrorKind::NoEntries)
-                .map_err(make_error)
+        let content = std::fs::read_to_string(file)
+            .map_err(ErrorKind::IO)
+            .map_err(make_error)?;
+        let entries = content
+            .lines()
+            .enumerate()
+            .filter(|(_, line)| !line.trim().is_empty())
+            .map(|(index, line)| {
+                let line_number = index + 1;
+                let (user, secret) = line
+                    .split_once(':')
+                    .ok_or(ErrorKind::MissingSeparator { line_number })?;
+                let secret = Secret::parse(secret)
+                    .map_err(|error| ErrorKind::InvalidHash { line_number, error })?;
+                Ok(Entry {
+                    user: user.to_owned(),
+                    secret,
+                })
+            })
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(make_error)?;
+        if entries.is_empty() {
+            Err(make_error(ErrorKind::NoEntries))
+        } else {
+            Ok(entries)
         }
-        /// Constructs rules from the specified entries
-        ///
-        /// Returns `None` if no entries are specified
-        pub fn from_entries(entries: impl Iterator<Item = String>) -> Option<Self> {
-            let entries_sorted = {
-                let mut entries: Vec<_> = entries.collect();
-                entries.sort();
-                entries.into_boxed_slice()
-            };
-            (!entries_sorted.is_empty()).then_some(Self { entries_sorted })
+    }
+
+    /// Reads a file of bearer tokens, one per line
+    fn read_tokens(file: &Path) -> Result<Vec<String>, Error> {
+        let make_error = |kind| Error {
+            file: file.to_owned(),
+            kind,
+        };
+
+        let content = std::fs::read_to_string(file)
+            .map_err(ErrorKind::IO)
+            .map_err(make_error)?;
+        let tokens: Vec<_> = content
+            .lines()
+            .map(str::trim)
+            .filter(|line| !line.is_empty())
+            .map(String::from)
+            .collect();
+        if tokens.is_empty() {
+            Err(make_error(ErrorKind::NoEntries))
+        } else {
+            Ok(tokens)
         }
     }
     #[derive(Debug)]
@@ -72,40 +167,258 @@ mod file {
     enum ErrorKind {
         IO(std::io::Error),
         NoEntries,
+        MissingSeparator {
+            line_number: usize,
+        },
+        InvalidHash {
+            line_number: usize,
+            error: super::secret::Error,
+        },
     }
     impl std::error::Error for Error {
         fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
             match &self.kind {
                 ErrorKind::IO(error) => Some(error),
-                ErrorKind::NoEntries => None,
+                ErrorKind::NoEntries | ErrorKind::MissingSeparator { .. } => None,
+                ErrorKind::InvalidHash { error, .. } => Some(error),
             }
         }
     }
     impl std::fmt::Display for Error {
         fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
             let Self { file, kind } = self;
-            let description = match kind {
-                ErrorKind::IO(_error) => "failed to read",
-                ErrorKind::NoEntries => "no entries in",
-            };
-            write!(f, "{description} file {}", file.display())
+            let file = file.display();
+            match kind {
+                ErrorKind::IO(_error) => write!(f, "failed to read file {file}"),
+                ErrorKind::NoEntries => write!(f, "no entries in file {file}"),
+                ErrorKind::MissingSeparator { line_number } => {
+                    write!(
+                        f,
+                        "missing \"user:\" prefix on line {line_number} of file {file}"
+                    )
+                }
+                ErrorKind::InvalidHash {
+                    line_number,
+                    error: _,
+                } => write!(
+                    f,
+                    "invalid password hash on line {line_number} of file {file}"
+                ),
+            }
+        }
+    }
+}
+
+mod reload {
+    use super::{AuthFiles, AuthRules, FileError};
+    use std::{
+        path::Path,
+        sync::{Arc, Mutex, MutexGuard, PoisonError},
+        time::SystemTime,
+    };
+
+    /// Authentication rules from files, reloaded on request (and when modified, if watching)
+    ///
+    /// The previous rules remain in effect if reloading fails
+    pub(crate) struct RulesFile {
+        files: AuthFiles,
+        watch: bool,
+        state: Mutex<Loaded>,
+    }
+    struct Loaded {
+        modified: [Option<SystemTime>; 2],
+        rules: Arc<AuthRules>,
+    }
+
+    impl RulesFile {
+        /// Loads the rules (initial load must succeed)
+        ///
+        /// # Errors
+        /// Returns an error if a file is invalid
+        pub fn load(files: AuthFiles, watch: bool) -> Result<Self, FileError> {
+            let modified = files.modified();
+            let rules = Arc::new(AuthRules::from_files(&files)?);
+            Ok(Self {
+                files,
+                watch,
+                state: Mutex::new(Loaded { modified, rules }),
+            })
+        }
+
+        /// Returns the current rules, reloading first if watching and a file was modified
+        pub fn current(&self) -> Arc<AuthRules> {
+            let mut state = self.lock();
+            if self.watch {
+                let modified = self.files.modified();
+                if modified != state.modified {
+                    self.reload_locked(&mut state, modified);
+                }
+            }
+            Arc::clone(&state.rules)
+        }
+
+        /// Re-reads the files
+        pub fn reload(&self) {
+            let modified = self.files.modified();
+            self.reload_locked(&mut self.lock(), modified);
+        }
+
+        fn reload_locked(&self, state: &mut Loaded, modified: [Option<SystemTime>; 2]) {
+            // retry only after the next modification (when watching)
+            state.modified = modified;
+            match AuthRules::from_files(&self.files) {
+                Ok(rules) => {
+                    for (file, count) in self.files.paths().into_iter().zip(rules.counts()) {
+                        if let Some(file) = file {
+                            let entry_plural = if count == 1 { "entry" } else { "entries" };
+                            println!(
+                                "Reloaded auth file {} with {count} {entry_plural}",
+                                file.display()
+                            );
+                        }
+                    }
+                    state.rules = Arc::new(rules);
+                }
+                Err(error) => {
+                    eprintln!("failed to reload auth file, keeping the previous rules: {error}");
+                }
+            }
+        }
+
+        fn lock(&self) -> MutexGuard<'_, Loaded> {
+            // rules remain valid even if a holder panicked
+            self.state.lock().unwrap_or_else(PoisonError::into_inner)
+        }
+    }
+
+    impl AuthFiles {
+        /// Returns the configured paths, in the order of [`AuthRules::counts`]
+        fn paths(&self) -> [Option<&Path>; 2] {
+            [
+                self.basic_auth_keys.as_deref(),
+                self.bearer_tokens.as_deref(),
+            ]
+        }
+        fn modified(&self) -> [Option<SystemTime>; 2] {
+            self.paths().map(|file| {
+                file.and_then(|file| {
+                    std::fs::metadata(file)
+                        .and_then(|meta| meta.modified())
+                        .ok()
+                })
+            })
+        }
+    }
+}
+
+mod secret {
+    use argon2::{PasswordHash, PasswordVerifier as _};
+    use subtle::ConstantTimeEq as _;
+
+    const BCRYPT_PREFIXES: [&str; 3] = ["$2a$", "$2b$", "$2y$"];
+    const ARGON2_PREFIX: &str = "$argon2";
+
+    /// Password of an entry, as specified in the file
+    pub(super) enum Secret {
+        Plaintext(String),
+        Bcrypt(String),
+        Argon2(String),
+    }
+    impl Secret {
+        /// Parses the secret, validating hashes (identified by the prefix)
+        pub fn parse(secret: &str) -> Result<Self, Error> {
+            // allow "user: hash" as in YAML
+            let hash = secret.trim();
+            if BCRYPT_PREFIXES
+                .iter()
+                .any(|prefix| hash.starts_with(prefix))
+            {
+                hash.parse::<bcrypt::HashParts>().map_err(Error::Bcrypt)?;
+                Ok(Self::Bcrypt(hash.to_owned()))
+            } else if hash.starts_with(ARGON2_PREFIX) {
+                PasswordHash::new(hash).map_err(Error::Argon2)?;
+                Ok(Self::Argon2(hash.to_owned()))
+            } else {
+                Ok(Self::Plaintext(secret.to_owned()))
+            }
+        }
+        pub fn is_plaintext(&self) -> bool {
+            matches!(self, Self::Plaintext(_))
+        }
+        /// Returns true if the password matches, in constant time (for the same secret)
+        pub fn verify(&self, password: &str) -> bool {
+            match self {
+                Self::Plaintext(secret) => secret.as_bytes().ct_eq(password.as_bytes()).into(),
+                Self::Bcrypt(hash) => bcrypt::verify(password, hash).unwrap_or(false),
+                Self::Argon2(hash) => PasswordHash::new(hash).is_ok_and(|hash| {
+                    argon2::Argon2::default()
+                        .verify_password(password.as_bytes(), &hash)
+                        .is_ok()
+                }),
+            }
+        }
+    }
+
+    #[derive(Debug)]
+    pub(crate) enum Error {
+        Bcrypt(bcrypt::BcryptError),
+        Argon2(argon2::password_hash::phc::Error),
+    }
+    impl std::error::Error for Error {
+        fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+            match self {
+                Self::Bcrypt(error) => Some(error),
+                Self::Argon2(error) => Some(error),
+            }
+        }
+    }
+    impl std::fmt::Display for Error {
+        fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+            match self {
+                Self::Bcrypt(_error) => write!(f, "invalid bcrypt hash"),
+                Self::Argon2(_error) => write!(f, "invalid argon2 hash"),
+            }
         }
     }
 }
 
 mod header {
-    use super::{AuthResult, AuthRules, DebugUserString, get_header_authorization};
+    use super::{AuthResult, AuthRules, Challenge, DebugUserString, get_header_authorization};
     use base64::Engine;
+    use subtle::ConstantTimeEq as _;
 
     impl AuthRules {
         /// Prints startup message(s) to stdout
-        pub fn print_start_message(&self) {
-            let count = self.entries_sorted.len();
-            let entry_plural = if count == 1 { "entry" } else { "entries" };
-            println!("Allow-list configured with {count} {entry_plural}");
-            println!(
-                "!!!!!! WARNING: HTTP transmits authentication in plaintext, use a HTTPS-proxy on the local machine!!!!!!!"
-            );
+        ///
+        /// Warns about plaintext credentials, unless `encrypted` (TLS)
+        pub fn print_start_message(&self, encrypted: bool) {
+            let plural = |count| if count == 1 { "entry" } else { "entries" };
+            let [count, token_count] = self.counts();
+            if count > 0 {
+                println!("Allow-list configured with {count} {}", plural(count));
+            }
+            if token_count > 0 {
+                println!(
+                    "Bearer token list configured with {token_count} {}",
+                    plural(token_count)
+                );
+            }
+            let plaintext_count = self
+                .entries
+                .iter()
+                .filter(|entry| entry.secret.is_plaintext())
+                .count();
+            if plaintext_count > 0 {
+                println!(
+                    "WARNING: {plaintext_count} plaintext password {} in the allow-list, deprecated in favor of bcrypt or argon2 hashes",
+                    plural(plaintext_count)
+                );
+            }
+            if !encrypted {
+                println!(
+                    "!!!!!! WARNING: HTTP transmits authentication in plaintext, use a HTTPS-proxy on the local machine!!!!!!!"
+                );
+            }
         }
         /// Evalutes the request against the rules
         ///
@@ -121,32 +434,93 @@ mod header {
                 .find(|header| header.field == *header_authorization)
                 .map(|header| header.value.clone())
             else {
-                return Ok(AuthResult::MissingAuthHeader);
+                return Ok(AuthResult::MissingAuthHeader(self.challenge()));
             };
 
-            let auth_str = parse_authorization_value(auth_value.as_str())?;
+            match parse_authorization_value(auth_value.as_str())? {
+                Credentials::Basic(auth_str) if !self.entries.is_empty() => {
+                    let (user, password) = auth_str.split_once(':').unwrap_or((&auth_str, ""));
 
-            if self.entries_sorted.binary_search(&auth_str).is_ok() {
-                Ok(AuthResult::Accept)
-            } else {
-                let who = DebugUserString::from(auth_str);
+                    if self.verify(user, password) {
+                        Ok(AuthResult::Accept)
+                    } else {
+                        let who = DebugUserString::from(auth_str);
 
-                Ok(AuthResult::Deny(who))
+                        Ok(AuthResult::Deny(who))
+                    }
+                }
+                Credentials::Bearer(token) if !self.tokens.is_empty() => {
+                    if self.verify_token(&token) {
+                        Ok(AuthResult::Accept)
+                    } else {
+                        let who = DebugUserString::token(&token);
+
+                        Ok(AuthResult::InvalidToken(who))
+                    }
+                }
+                // scheme not configured
+                Credentials::Basic(_) | Credentials::Bearer(_) => {
+                    Ok(AuthResult::MissingAuthHeader(self.challenge()))
+                }
+            }
+        }
+        fn challenge(&self) -> Challenge {
+            Challenge {
+                basic: !self.entries.is_empty(),
+                bearer: !self.tokens.is_empty(),
+            }
+        }
+        /// Returns true if the token matches any configured token
+        fn verify_token(&self, token: &str) -> bool {
+            // check all tokens, without short-circuit
+            self.tokens.iter().fold(false, |accepted, expected| {
+                bool::from(expected.as_bytes().ct_eq(token.as_bytes())) | accepted
+            })
+        }
+        /// Returns true if the password matches any entry for the user
+        pub(super) fn verify(&self, user: &str, password: &str) -> bool {
+            let mut user_entries = self
+                .entries
+                .iter()
+                .filter(|entry| entry.user == user)
+                .peekable();
+            if user_entries.peek().is_none() {
+                // similar duration as for a known user, to avoid revealing valid user names
+                if let Some(entry) = self.entries.first() {
+                    let _ = entry.secret.verify(password);
+                }
+                return false;
             }
+            // check all entries, without short-circuit
+            user_entries.fold(false, |accepted, entry| {
+                entry.secret.verify(password) | accepted
+            })
         }
     }
 
-    fn parse_authorization_value(auth_value: &str) -> Result<String, Error> {
+    /// Credentials provided in the "Authorization" header
+    enum Credentials {
+        /// Decoded `user:password`
+        Basic(String),
+        Bearer(String),
+    }
+
+    fn parse_authorization_value(auth_value: &str) -> Result<Credentials, Error> {
         const BASIC_PREFIX: &str = "Basic ";
+        const BEARER_PREFIX: &str = "Bearer ";
 
         let make_error = |kind| Error {
             auth_value: auth_value.to_owned().into(),
             kind,
         };
 
+        if let Some(token) = auth_value.strip_prefix(BEARER_PREFIX) {
+            return Ok(Credentials::Bearer(token.trim().to_owned()));
+        }
+
         let auth_base64 = auth_value
             .strip_prefix(BASIC_PREFIX)
-            .ok_or(ErrorKind::MissingBasic)
+            .ok_or(ErrorKind::MissingScheme)
             .map_err(make_error)?;
 
         let auth_bytes = base64::prelude::BASE64_STANDARD
@@ -158,7 +532,7 @@ mod header {
             .map_err(ErrorKind::Utf8)
             .map_err(make_error)?;
 
-        Ok(auth_str)
+        Ok(Credentials::Basic(auth_str))
     }
 
     #[derive(Debug)]
@@ -168,14 +542,14 @@ mod header {
     }
     #[derive(Debug)]
     enum ErrorKind {
-        MissingBasic,
+        MissingScheme,
         Base64(base64::DecodeError),
         Utf8(std::string::FromUtf8Error),
     }
     impl std::error::Error for Error {
         fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
             match &self.kind {
-                ErrorKind::MissingBasic => None,
+                ErrorKind::MissingScheme => None,
                 ErrorKind::Base64(error) => Some(error),
                 ErrorKind::Utf8(error) => Some(error),
             }
@@ -185,7 +559,7 @@ mod header {
         fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
             let Self { auth_value, kind } = self;
             let description = match kind {
-                ErrorKind::MissingBasic => "missing basic-authentication prefix",
+                ErrorKind::MissingScheme => "missing Basic or Bearer authentication scheme",
                 ErrorKind::Base64(_error) => "invalid base64",
                 ErrorKind::Utf8(_error) => "non-UTF8 string",
             };
@@ -205,8 +579,10 @@ pub(super) enum AuthResult {
     Accept,
     /// Provided authentication failed the rules
     Deny(DebugUserString),
-    /// No authentication provided
-    MissingAuthHeader,
+    /// Provided bearer token is not in the token list
+    InvalidToken(DebugUserString),
+    /// No authentication provided (or using a scheme that is not configured)
+    MissingAuthHeader(Challenge),
     /// No rules configured
     NoneConfigured,
 }
@@ -219,8 +595,23 @@ mod debug_user_string {
     #[allow(missing_docs)]
     #[derive(Clone, PartialEq, Eq)]
     pub(crate) enum DebugUserString {
-        Unchanged { value: Box<str> },
-        Truncated { value: Box<str>, orig_len: usize },
+        Unchanged {
+            value: Box<str>,
+        },
+        Truncated {
+            value: Box<str>,
+            orig_len: usize,
+        },
+        /// Bearer token, only the length is retained
+        Token {
+            len: usize,
+        },
+    }
+    impl DebugUserString {
+        /// Trace of a bearer token, without the secret contents
+        pub(crate) fn token(token: &str) -> Self {
+            Self::Token { len: token.len() }
+        }
     }
     impl From<String> for DebugUserString {
         fn from(mut value: String) -> Self {
@@ -254,6 +645,7 @@ mod debug_user_string {
     pub enum DebugUserStringRef<'a> {
         Unchanged { value: &'a str },
         Truncated { value: &'a str, orig_len: usize },
+        Token { len: usize },
     }
     impl std::fmt::Display for DebugUserStringRef<'_> {
         fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
@@ -264,6 +656,9 @@ mod debug_user_string {
                 Self::Truncated { value, orig_len } => {
                     write!(f, "{value:?}... (len {orig_len})")
                 }
+                Self::Token { len } => {
+                    write!(f, "bearer token (len {len})")
+                }
             }
         }
     }
@@ -276,6 +671,7 @@ mod debug_user_string {
                     ref value,
                     orig_len,
                 } => DebugUserStringRef::Truncated { value, orig_len },
+                DebugUserString::Token { len } => DebugUserStringRef::Token { len },
             }
         }
     }
@@ -300,3 +696,74 @@ mod debug_user_string {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::{AuthFiles, AuthRules};
+    use argon2::PasswordHasher as _;
+    use std::io::Write as _;
+
+    fn rules_from_lines(lines: &[String]) -> Result<AuthRules, super::FileError> {
+        let mut file = tempfile::NamedTempFile::new().expect("tempfile creation");
+        for line in lines {
+            writeln!(file, "{line}").expect("tempfile write");
+        }
+        AuthRules::from_files(&AuthFiles {
+            basic_auth_keys: Some(file.path().to_owned()),
+            bearer_tokens: None,
+        })
+    }
+
+    #[test]
+    fn hashed_and_plaintext_entries() {
+        let bcrypt_hash = bcrypt::hash("word1", 4).expect("bcrypt hash");
+        let argon2_hash = {
+            let params = argon2::Params::new(8, 1, 1, None).expect("argon2 params");
+            argon2::Argon2::new(argon2::Algorithm::Argon2id, argon2::Version::V0x13, params)
+                .hash_password_with_salt(b"word2", b"0123456789abcdef")
+                .expect("argon2 hash")
+                .to_string()
+        };
+        let rules = rules_from_lines(&[
+            format!("user1:{bcrypt_hash}"),
+            // YAML-style space after the colon
+            format!("user2: {argon2_hash}"),
+            String::new(),
+            "user3:word3".to_string(),
+        ])
+        .expect("valid rules");
+
+        assert!(rules.verify("user1", "word1"));
+        assert!(rules.verify("user2", "word2"));
+        assert!(rules.verify("user3", "word3"));
+
+        assert!(!rules.verify("user1", "word2"));
+        assert!(!rules.verify("user2", "word1"));
+        assert!(!rules.verify("user3", "word"));
+        assert!(!rules.verify("user4", "word1"));
+        assert!(!rules.verify(&format!("user1:{bcrypt_hash}"), ""));
+    }
+
+    #[test]
+    fn invalid_lines() {
+        let error = rules_from_lines(&["user1:$2b$04$not-a-hash".to_string()])
+            .err()
+            .expect("invalid bcrypt hash");
+        assert!(
+            error
+                .to_string()
+                .starts_with("invalid password hash on line 1 of file "),
+            "{error}"
+        );
+
+        let error = rules_from_lines(&["user1:word1".to_string(), "word2".to_string()])
+            .err()
+            .expect("missing user");
+        assert!(
+            error
+                .to_string()
+                .starts_with("missing \"user:\" prefix on line 2 of file "),
+            "{error}"
+        );
+    }
+}
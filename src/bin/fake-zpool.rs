@@ -16,6 +16,10 @@ struct Args {
     #[arg(value_enum)]
     #[clap(default_value_t)]
     fake_zpool_mode: Mode,
+
+    /// Marker file for [`Mode::FailAfterFirst`] and [`Mode::SlowAfterFirst`] (created by the first run)
+    #[clap(env)]
+    fake_zpool_marker: Option<std::path::PathBuf>,
 }
 #[derive(Clone, Copy, Default, clap::ValueEnum)]
 enum Mode {
@@ -27,6 +31,8 @@ enum Mode {
     SleepForever,
     ExitCode1,
     ExitCode2,
+    FailAfterFirst,
+    SlowAfterFirst,
 }
 
 fn main() {
@@ -34,9 +40,28 @@ fn main() {
         arg0,
         precise,
         fake_zpool_mode,
+        fake_zpool_marker,
     } = Args::parse();
 
     match fake_zpool_mode {
+        Mode::FailAfterFirst => {
+            let marker = fake_zpool_marker.expect("marker file required for fail-after-first");
+            if marker.exists() {
+                eprintln!("fail-after-first stderr contents");
+                std::process::exit(1);
+            }
+            std::fs::write(&marker, "").expect("marker file should be writable");
+            print!("{FAKE_INPUT}");
+        }
+        Mode::SlowAfterFirst => {
+            let marker = fake_zpool_marker.expect("marker file required for slow-after-first");
+            if marker.exists() {
+                std::thread::sleep(std::time::Duration::from_secs(2));
+            } else {
+                std::fs::write(&marker, "").expect("marker file should be writable");
+            }
+            print!("{FAKE_INPUT}");
+        }
         Mode::Normal => {
             if arg0 == "status" {
                 if precise {
@@ -0,0 +1,522 @@
+//! Nagios/Icinga check plugin: pool health against thresholds, reported as the plugin exit status
+//! and a one-line summary with performance data
+
+use crate::{
+    AppContext, Timestamp, ZfsParseError,
+    fmt::{DeviceStatusValue, ErrorStatusValue},
+    zfs::{PoolMetrics, ScanStatus},
+};
+use std::time::Duration;
+
+/// Service name at the start of the plugin output
+const SERVICE_NAME: &str = "ZPOOL";
+
+const DAY: Duration = Duration::from_hours(24);
+
+/// Plugin exit status, from the Nagios plugin API
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum CheckStatus {
+    #[allow(missing_docs)]
+    Ok,
+    #[allow(missing_docs)]
+    Warning,
+    #[allow(missing_docs)]
+    Critical,
+    /// The check could not determine the state (e.g. `zpool status` failed)
+    Unknown,
+}
+impl CheckStatus {
+    /// Returns the process exit code for the status
+    #[must_use]
+    pub fn exit_code(self) -> i32 {
+        match self {
+            Self::Ok => 0,
+            Self::Warning => 1,
+            Self::Critical => 2,
+            Self::Unknown => 3,
+        }
+    }
+    fn label(self) -> &'static str {
+        match self {
+            Self::Ok => "OK",
+            Self::Warning => "WARNING",
+            Self::Critical => "CRITICAL",
+            Self::Unknown => "UNKNOWN",
+        }
+    }
+    /// Rank for combining the statuses of several checks (the most severe wins)
+    fn severity(self) -> u8 {
+        match self {
+            Self::Ok => 0,
+            Self::Unknown => 1,
+            Self::Warning => 2,
+            Self::Critical => 3,
+        }
+    }
+}
+
+/// Pool state at which a threshold triggers, including all more severe states (e.g. `degraded`
+/// also matches `faulted`)
+///
+/// The severity follows the `pool_state` metric values.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct StateThreshold(pub(crate) DeviceStatusValue);
+impl StateThreshold {
+    /// Returns true if the state is at least as severe as the threshold
+    pub(crate) fn matches(self, state: DeviceStatusValue) -> bool {
+        state.value() >= self.0.value()
+    }
+    /// States that can be used as thresholds (excluding missing and unrecognized states)
+    fn candidates() -> impl Iterator<Item = DeviceStatusValue> {
+        DeviceStatusValue::ALL
+            .iter()
+            .copied()
+            .filter(|state| state.value() >= DeviceStatusValue::Online.value())
+    }
+}
+impl std::str::FromStr for StateThreshold {
+    type Err = StateThresholdError;
+    fn from_str(input: &str) -> Result<Self, StateThresholdError> {
+        Self::candidates()
+            .find(|state| state.name().eq_ignore_ascii_case(input))
+            .map(Self)
+            .ok_or_else(|| StateThresholdError {
+                input: input.to_owned(),
+            })
+    }
+}
+
+/// Unknown pool state for a threshold
+#[derive(Debug)]
+pub struct StateThresholdError {
+    input: String,
+}
+impl std::error::Error for StateThresholdError {}
+impl std::fmt::Display for StateThresholdError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let Self { input } = self;
+        write!(f, "unknown pool state {input:?}, expected one of ")?;
+        let mut first = Some(());
+        for state in StateThreshold::candidates() {
+            if first.take().is_none() {
+                write!(f, ", ")?;
+            }
+            write!(f, "{}", state.name().to_lowercase())?;
+        }
+        Ok(())
+    }
+}
+
+/// Warning and critical levels for a value (each optional)
+#[derive(Clone, Copy)]
+struct Levels<T> {
+    warning: Option<T>,
+    critical: Option<T>,
+}
+impl<T: Copy> Levels<T> {
+    /// Returns the status for the value, given a function testing whether a level is reached
+    fn status(&self, reached: impl Fn(T) -> bool) -> CheckStatus {
+        if self.critical.is_some_and(&reached) {
+            CheckStatus::Critical
+        } else if self.warning.is_some_and(&reached) {
+            CheckStatus::Warning
+        } else {
+            CheckStatus::Ok
+        }
+    }
+}
+
+/// Thresholds for the check plugin
+#[must_use]
+pub struct CheckThresholds {
+    state: Levels<StateThreshold>,
+    device_errors: Levels<u32>,
+    scan_age: Levels<Duration>,
+    data_errors: CheckStatus,
+}
+impl Default for CheckThresholds {
+    /// Warning for degraded pools, device errors, or a scan older than 35 days; critical for
+    /// faulted (or worse) pools, or data errors
+    fn default() -> Self {
+        Self {
+            state: Levels {
+                warning: Some(StateThreshold(DeviceStatusValue::Degraded)),
+                critical: Some(StateThreshold(DeviceStatusValue::Faulted)),
+            },
+            device_errors: Levels {
+                warning: Some(1),
+                critical: None,
+            },
+            scan_age: Levels {
+                warning: Some(DAY * 35),
+                critical: None,
+            },
+            data_errors: CheckStatus::Critical,
+        }
+    }
+}
+impl CheckThresholds {
+    /// Sets the pool state for a warning
+    pub fn set_state_warning(mut self, state: StateThreshold) -> Self {
+        self.state.warning = Some(state);
+        self
+    }
+    /// Sets the pool state for a critical status
+    pub fn set_state_critical(mut self, state: StateThreshold) -> Self {
+        self.state.critical = Some(state);
+        self
+    }
+    /// Sets the count of device errors (read, write and checksum, summed across leaf devices) for
+    /// a warning
+    pub fn set_device_errors_warning(mut self, count: u32) -> Self {
+        self.device_errors.warning = Some(count);
+        self
+    }
+    /// Sets the count of device errors for a critical status
+    pub fn set_device_errors_critical(mut self, count: u32) -> Self {
+        self.device_errors.critical = Some(count);
+        self
+    }
+    /// Sets the days since the last scan for a warning (pools never scanned always exceed it)
+    pub fn set_scan_age_warning_days(mut self, days: u32) -> Self {
+        self.scan_age.warning = Some(DAY * days);
+        self
+    }
+    /// Sets the days since the last scan for a critical status
+    pub fn set_scan_age_critical_days(mut self, days: u32) -> Self {
+        self.scan_age.critical = Some(DAY * days);
+        self
+    }
+    /// Sets the status for pools reporting data errors (default critical)
+    pub fn set_data_errors_status(mut self, status: CheckStatus) -> Self {
+        self.data_errors = status;
+        self
+    }
+}
+
+/// Result of the check, displayed as the plugin output line
+#[derive(Debug)]
+pub struct CheckReport {
+    status: CheckStatus,
+    summary: String,
+    perfdata: Vec<String>,
+}
+impl CheckReport {
+    /// Returns the overall status, the most severe of all pools
+    #[must_use]
+    pub fn status(&self) -> CheckStatus {
+        self.status
+    }
+}
+impl std::fmt::Display for CheckReport {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let Self {
+            status,
+            summary,
+            perfdata,
+        } = self;
+        write!(f, "{SERVICE_NAME} {} - {summary}", status.label())?;
+        if !perfdata.is_empty() {
+            write!(f, " | {}", perfdata.join(" "))?;
+        }
+        Ok(())
+    }
+}
+
+impl AppContext {
+    /// Runs `zpool status` and checks the pools against the thresholds
+    ///
+    /// Failure to run or parse the command is reported as [`CheckStatus::Unknown`].
+    pub fn check_now(&self, thresholds: &CheckThresholds) -> CheckReport {
+        let timestamp = self.timestamp_now();
+        match self.collect_now().latest {
+            Ok(snapshot) => check_pools(&snapshot.pools, &timestamp.datetime, thresholds),
+            Err(err) => {
+                let messages = crate::metrics::error_messages(&*err);
+                CheckReport {
+                    status: CheckStatus::Unknown,
+                    summary: messages.join(": ").replace('\n', " "),
+                    perfdata: vec![],
+                }
+            }
+        }
+    }
+}
+
+impl Timestamp<'_> {
+    /// Parses the `zpool_output` string and checks the pools against the thresholds
+    ///
+    /// # Errors
+    /// Returns errors when parsing ZFS metrics fails
+    pub fn check_output(
+        &self,
+        zpool_output: &str,
+        thresholds: &CheckThresholds,
+    ) -> Result<CheckReport, ZfsParseError> {
+        let pools = self.app_context.parse_zfs_metrics(zpool_output)?;
+        Ok(check_pools(&pools, &self.datetime, thresholds))
+    }
+}
+
+fn check_pools(
+    pools: &[PoolMetrics],
+    now: &jiff::Zoned,
+    thresholds: &CheckThresholds,
+) -> CheckReport {
+    let mut report = CheckReport {
+        status: CheckStatus::Ok,
+        summary: String::new(),
+        perfdata: vec![],
+    };
+    let mut problems = vec![];
+    for pool in pools {
+        let mut pool_check = PoolCheck {
+            pool,
+            thresholds,
+            report: &mut report,
+            problems: vec![],
+        };
+        pool_check.check_state();
+        pool_check.check_device_errors();
+        pool_check.check_scan_age(now);
+        pool_check.check_data_errors();
+
+        let PoolCheck {
+            problems: pool_problems,
+            ..
+        } = pool_check;
+        if !pool_problems.is_empty() {
+            problems.push(format!("{}: {}", pool.name, pool_problems.join(", ")));
+        }
+    }
+
+    report.summary = if !problems.is_empty() {
+        problems.join("; ")
+    } else if pools.is_empty() {
+        "no pools".to_owned()
+    } else {
+        let count = pools.len();
+        let plural = if count == 1 { "" } else { "s" };
+        format!("{count} pool{plural} OK")
+    };
+    report
+}
+
+/// Checks of a single pool, adding to the report
+struct PoolCheck<'a> {
+    pool: &'a PoolMetrics,
+    thresholds: &'a CheckThresholds,
+    report: &'a mut CheckReport,
+    /// Descriptions of the checks that are not OK
+    problems: Vec<String>,
+}
+impl PoolCheck<'_> {
+    fn add_problem(&mut self, status: CheckStatus, description: String) {
+        if status != CheckStatus::Ok {
+            if status.severity() > self.report.status.severity() {
+                self.report.status = status;
+            }
+            self.problems.push(description);
+        }
+    }
+    fn add_perfdata<T: Into<u64>>(
+        &mut self,
+        name: &str,
+        value: impl std::fmt::Display,
+        unit: &str,
+        levels: Levels<T>,
+    ) {
+        let perfdata = format_perfdata(&self.pool.name, name, value, unit, levels);
+        self.report.perfdata.push(perfdata);
+    }
+
+    fn check_state(&mut self) {
+        let Levels { warning, critical } = self.thresholds.state;
+        let status = match self.pool.state.as_ref().map(DeviceStatusValue::from) {
+            None | Some(DeviceStatusValue::UnknownMissing | DeviceStatusValue::Unrecognized) => {
+                CheckStatus::Unknown
+            }
+            Some(state) => self
+                .thresholds
+                .state
+                .status(|threshold| threshold.matches(state)),
+        };
+        let state_text = self.pool.text.state.as_deref().unwrap_or("missing");
+        self.add_problem(status, format!("state {state_text}"));
+
+        let state = DeviceStatusValue::from_opt(self.pool.state.as_ref());
+        let levels = Levels {
+            warning: warning.map(|threshold| threshold.0.value()),
+            critical: critical.map(|threshold| threshold.0.value()),
+        };
+        self.add_perfdata("state", state, "", levels);
+    }
+
+    /// Checks the read, write and checksum errors, summed across leaf devices
+    fn check_device_errors(&mut self) {
+        let device_errors: u32 = self
+            .pool
+            .leaf_devices()
+            .map(|leaf| {
+                leaf.errors_read
+                    .saturating_add(leaf.errors_write)
+                    .saturating_add(leaf.errors_checksum)
+            })
+            .fold(0, u32::saturating_add);
+        let status = self
+            .thresholds
+            .device_errors
+            .status(|count| device_errors >= count);
+        let plural = if device_errors == 1 { "" } else { "s" };
+        self.add_problem(status, format!("{device_errors} device error{plural}"));
+        self.add_perfdata(
+            "device_errors",
+            device_errors,
+            "",
+            self.thresholds.device_errors,
+        );
+    }
+
+    /// Checks the age of the last scan (exceeding all levels if unknown, e.g. never scanned)
+    fn check_scan_age(&mut self, now: &jiff::Zoned) {
+        let scan_age = self
+            .pool
+            .scan_status
+            .as_ref()
+            .and_then(|(_, scan_time)| scan_time.as_ref())
+            .map(|scan_time| {
+                let seconds = (now - scan_time)
+                    .total(jiff::Unit::Second)
+                    .expect("no overflow and relative zoned");
+                Duration::from_secs_f64(seconds.max(0.0))
+            });
+        let status = self
+            .thresholds
+            .scan_age
+            .status(|limit| scan_age.is_none_or(|age| age >= limit));
+        let description = match (scan_age, &self.pool.scan_status) {
+            (Some(age), _) => format!("last scan {} days ago", age.as_secs() / DAY.as_secs()),
+            (None, Some((ScanStatus::NeverScanned, _))) => "never scanned".to_owned(),
+            (None, _) => "no scan time".to_owned(),
+        };
+        self.add_problem(status, description);
+
+        if let Some(scan_age) = scan_age {
+            let Levels { warning, critical } = self.thresholds.scan_age;
+            let levels = Levels {
+                warning: warning.map(|limit| limit.as_secs()),
+                critical: critical.map(|limit| limit.as_secs()),
+            };
+            self.add_perfdata("scan_age", scan_age.as_secs(), "s", levels);
+        }
+    }
+
+    fn check_data_errors(&mut self) {
+        let error_text = self.pool.text.error.as_deref().unwrap_or_default();
+        let error_status = self.pool.error.as_ref().map(ErrorStatusValue::from);
+        match error_status {
+            Some(ErrorStatusValue::DataErrors) => {
+                let status = self.thresholds.data_errors;
+                self.add_problem(status, format!("errors: {error_text}"));
+            }
+            Some(ErrorStatusValue::Unrecognized) => {
+                self.add_problem(CheckStatus::Unknown, format!("errors: {error_text}"));
+            }
+            None | Some(ErrorStatusValue::UnknownMissing | ErrorStatusValue::Ok) => {}
+        }
+
+        let data_errors = u32::from(error_status == Some(ErrorStatusValue::DataErrors));
+        let levels: Levels<u32> = Levels {
+            warning: None,
+            critical: None,
+        };
+        self.add_perfdata("data_errors", data_errors, "", levels);
+    }
+}
+
+/// Returns the performance data `'label'=value[UOM];[warn];[crit]`
+///
+/// Levels are reached at or above the threshold, so each is written as the range `~:{N-1}`
+/// (alerting when the value is above `N-1`), since a bare `N` only alerts above `N`.
+fn format_perfdata<T: Into<u64>>(
+    pool_name: &str,
+    name: &str,
+    value: impl std::fmt::Display,
+    unit: &str,
+    levels: Levels<T>,
+) -> String {
+    let Levels { warning, critical } = levels;
+    let level = |level: Option<T>| {
+        level
+            .map(|level| format!("~:{}", i128::from(level.into()) - 1))
+            .unwrap_or_default()
+    };
+    // single quotes are escaped by doubling
+    let label = format!("{pool_name}_{name}").replace('\'', "''");
+    format!(
+        "'{label}'={value}{unit};{warning};{critical}",
+        warning = level(warning),
+        critical = level(critical),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{CheckStatus, CheckThresholds, StateThreshold};
+    use crate::AppContext;
+
+    fn check(input: &str, thresholds: &CheckThresholds) -> (CheckStatus, String) {
+        const TEST_TIMESTAMP: &str = "TEST_TIMESTAMP=";
+
+        let (timestamp_line, input) = input.split_once('\n').expect("timestamp line");
+        let timestamp = timestamp_line
+            .strip_prefix(TEST_TIMESTAMP)
+            .and_then(|timestamp| timestamp.parse().ok())
+            .expect("valid timestamp line");
+        let report = AppContext::new_assume_local_is_utc()
+            .timestamp_at_unix_utc(timestamp, None)
+            .expect("valid timestamp")
+            .check_output(input, thresholds)
+            .expect("valid input");
+        (report.status(), report.to_string())
+    }
+
+    #[test]
+    fn degraded_pool() {
+        let input = include_str!("../tests/input/input-01-corrupted.txt");
+
+        let (status, output) = check(input, &CheckThresholds::default());
+        assert_eq!(status, CheckStatus::Warning);
+        assert_eq!(
+            output,
+            "ZPOOL WARNING - dummy: state DEGRADED \
+            | 'dummy_state'=50;~:49;~:59 'dummy_device_errors'=0;~:0; 'dummy_scan_age'=91216s;~:3023999; \
+            'dummy_data_errors'=0;;"
+        );
+
+        let thresholds =
+            CheckThresholds::default().set_state_critical("degraded".parse().expect("valid state"));
+        let (status, _) = check(input, &thresholds);
+        assert_eq!(status, CheckStatus::Critical);
+    }
+
+    #[test]
+    fn data_errors() {
+        let input = include_str!("../tests/input/input-02-online-data-corruption.txt");
+
+        let (status, output) = check(input, &CheckThresholds::default());
+        assert_eq!(status, CheckStatus::Critical, "{output}");
+
+        let thresholds = CheckThresholds::default().set_data_errors_status(CheckStatus::Warning);
+        let (status, output) = check(input, &thresholds);
+        assert_eq!(status, CheckStatus::Warning, "{output}");
+    }
+
+    #[test]
+    fn state_threshold() {
+        let degraded: StateThreshold = "Degraded".parse().expect("valid state");
+        assert_eq!(degraded, "degraded".parse().expect("valid state"));
+        assert!("unrecognized".parse::<StateThreshold>().is_err());
+        assert!("bogus".parse::<StateThreshold>().is_err());
+    }
+}
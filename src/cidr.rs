@@ -0,0 +1,213 @@
+//! Client address filtering by IP network (CIDR notation)
+
+use std::net::IpAddr;
+
+/// IP network in CIDR notation, e.g. `10.0.0.0/8` or `fd00::/8` (a bare address matches only
+/// itself)
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Cidr {
+    address: IpAddr,
+    prefix_len: u8,
+}
+impl Cidr {
+    /// Returns true if the address is within the network
+    pub(crate) fn contains(self, address: IpAddr) -> bool {
+        match (self.address, address.to_canonical()) {
+            (IpAddr::V4(network), IpAddr::V4(address)) => {
+                let mask = u32::MAX
+                    .checked_shl(32 - u32::from(self.prefix_len))
+                    .unwrap_or(0);
+                u32::from(network) & mask == u32::from(address) & mask
+            }
+            (IpAddr::V6(network), IpAddr::V6(address)) => {
+                let mask = u128::MAX
+                    .checked_shl(128 - u32::from(self.prefix_len))
+                    .unwrap_or(0);
+                u128::from(network) & mask == u128::from(address) & mask
+            }
+            _ => false,
+        }
+    }
+}
+impl std::str::FromStr for Cidr {
+    type Err = Error;
+    fn from_str(input: &str) -> Result<Self, Error> {
+        let make_error = |kind| Error {
+            input: input.to_owned(),
+            kind,
+        };
+
+        let (address, prefix_len) = match input.split_once('/') {
+            Some((address, prefix_len)) => (address, Some(prefix_len)),
+            None => (input, None),
+        };
+        let address = address
+            .parse::<IpAddr>()
+            .map_err(|_| make_error(ErrorKind::InvalidAddress))?
+            .to_canonical();
+        let max_len = if address.is_ipv4() { 32 } else { 128 };
+        let prefix_len = match prefix_len {
+            Some(prefix_len) => prefix_len
+                .parse::<u8>()
+                .ok()
+                .filter(|&prefix_len| prefix_len <= max_len)
+                .ok_or_else(|| make_error(ErrorKind::InvalidPrefixLen { max_len }))?,
+            None => max_len,
+        };
+        Ok(Self {
+            address,
+            prefix_len,
+        })
+    }
+}
+impl std::fmt::Display for Cidr {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let Self {
+            address,
+            prefix_len,
+        } = self;
+        write!(f, "{address}/{prefix_len}")
+    }
+}
+
+/// Invalid CIDR network
+#[derive(Debug)]
+pub struct Error {
+    input: String,
+    kind: ErrorKind,
+}
+#[derive(Debug)]
+enum ErrorKind {
+    InvalidAddress,
+    InvalidPrefixLen { max_len: u8 },
+}
+impl std::error::Error for Error {}
+impl std::fmt::Display for Error {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let Self { input, kind } = self;
+        match kind {
+            ErrorKind::InvalidAddress => write!(
+                f,
+                "invalid IP address in {input:?}, expected format address/prefix-length"
+            ),
+            ErrorKind::InvalidPrefixLen { max_len } => write!(
+                f,
+                "invalid prefix length in {input:?}, expected 0 to {max_len}"
+            ),
+        }
+    }
+}
+
+/// Networks allowed to access the server, identifying clients behind trusted proxies by the
+/// `X-Forwarded-For` header
+pub(crate) struct ClientFilter {
+    allow: Vec<Cidr>,
+    trusted_proxies: Vec<Cidr>,
+}
+impl ClientFilter {
+    /// Returns a filter for the networks, or `None` if `allow` is empty (no filter)
+    pub fn new(allow: &[Cidr], trusted_proxies: &[Cidr]) -> Option<Self> {
+        (!allow.is_empty()).then(|| Self {
+            allow: allow.to_vec(),
+            trusted_proxies: trusted_proxies.to_vec(),
+        })
+    }
+    /// Returns true if the address is in an allowed network
+    pub fn is_allowed(&self, address: IpAddr) -> bool {
+        self.allow.iter().any(|network| network.contains(address))
+    }
+    fn is_trusted_proxy(&self, address: IpAddr) -> bool {
+        self.trusted_proxies
+            .iter()
+            .any(|network| network.contains(address))
+    }
+    /// Returns the client address, from the `X-Forwarded-For` values only if the peer is a
+    /// trusted proxy
+    ///
+    /// The values are searched from the nearest hop, skipping trusted proxies (earlier values are
+    /// set by the client, and may be forged).
+    ///
+    /// # Errors
+    /// Returns the value that is not a valid IP address
+    pub fn client_address<'a>(
+        &self,
+        peer: IpAddr,
+        forwarded_for: impl IntoIterator<Item = &'a str>,
+    ) -> Result<IpAddr, &'a str> {
+        let mut client = peer.to_canonical();
+        if !self.is_trusted_proxy(client) {
+            return Ok(client);
+        }
+        let hops: Vec<&str> = forwarded_for
+            .into_iter()
+            .flat_map(|value| value.split(','))
+            .map(str::trim)
+            .collect();
+        for hop in hops.into_iter().rev() {
+            client = hop.parse::<IpAddr>().map_err(|_| hop)?.to_canonical();
+            if !self.is_trusted_proxy(client) {
+                break;
+            }
+        }
+        Ok(client)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Cidr, ClientFilter};
+    use std::net::IpAddr;
+
+    fn cidr(input: &str) -> Cidr {
+        input.parse().expect("valid cidr")
+    }
+    fn ip(input: &str) -> IpAddr {
+        input.parse().expect("valid ip")
+    }
+
+    #[test]
+    fn contains() {
+        let network = cidr("10.1.0.0/16");
+        assert!(network.contains(ip("10.1.2.3")));
+        assert!(network.contains(ip("::ffff:10.1.2.3")));
+        assert!(!network.contains(ip("10.2.0.1")));
+        assert!(!network.contains(ip("::1")));
+
+        let network = cidr("fd00:1::/32");
+        assert!(network.contains(ip("fd00:1::5")));
+        assert!(!network.contains(ip("fd00:2::5")));
+        assert!(!network.contains(ip("10.1.2.3")));
+
+        assert!(cidr("0.0.0.0/0").contains(ip("192.0.2.1")));
+        assert!(cidr("192.0.2.1").contains(ip("192.0.2.1")));
+        assert!(!cidr("192.0.2.1").contains(ip("192.0.2.2")));
+
+        for invalid in ["10.0.0.0/33", "::/129", "10.0.0/8", "10.0.0.0/", "host/8"] {
+            assert!(invalid.parse::<Cidr>().is_err(), "{invalid:?}");
+        }
+    }
+
+    #[test]
+    fn forwarded_for() {
+        let filter = ClientFilter::new(&[cidr("192.0.2.0/24")], &[cidr("10.0.0.0/8")])
+            .expect("non-empty allow list");
+        let client = |peer, forwarded_for: &[&'static str]| {
+            filter.client_address(ip(peer), forwarded_for.iter().copied())
+        };
+
+        // untrusted peer, header ignored
+        assert_eq!(
+            client("198.51.100.1", &["192.0.2.1"]),
+            Ok(ip("198.51.100.1"))
+        );
+        // nearest untrusted hop, not the (forged) first value
+        assert_eq!(
+            client("10.0.0.1", &["192.0.2.1, 198.51.100.1", "10.0.0.2"]),
+            Ok(ip("198.51.100.1"))
+        );
+        assert_eq!(client("10.0.0.1", &["192.0.2.1"]), Ok(ip("192.0.2.1")));
+        // trusted proxy without header
+        assert_eq!(client("10.0.0.1", &[]), Ok(ip("10.0.0.1")));
+        assert_eq!(client("10.0.0.1", &["unknown"]), Err("unknown"));
+    }
+}
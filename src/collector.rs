@@ -0,0 +1,206 @@
+//! Shared results of running `zpool status`, for serving cached metrics
+//!
+//! See [`CollectMode`] for when the collection runs.
+
+use crate::{AppContext, CollectMode, MetricsError, watchdog::Progress, zfs::PoolMetrics};
+use std::{
+    sync::{Arc, Condvar, Mutex, MutexGuard, PoisonError},
+    time::{Duration, Instant},
+};
+
+/// Successful result of running and parsing `zpool status`
+pub(crate) struct Snapshot {
+    pub pools: Vec<PoolMetrics>,
+    pub collected_at: jiff::Timestamp,
+}
+
+/// Result of the most recent collection, along with the most recent success
+#[derive(Clone)]
+pub(crate) struct Collection {
+    pub latest: Result<Arc<Snapshot>, Arc<MetricsError>>,
+    pub last_success: Option<Arc<Snapshot>>,
+}
+
+pub(crate) struct Collector {
+    state: Mutex<State>,
+    /// Notified when a collection is stored, or polling is stopped
+    changed: Condvar,
+    /// Running collections, stalled if any runs too long
+    progress: Progress,
+}
+impl Default for Collector {
+    fn default() -> Self {
+        Self {
+            state: Mutex::default(),
+            changed: Condvar::new(),
+            progress: Progress::new(1),
+        }
+    }
+}
+#[derive(Default)]
+struct State {
+    /// Completion time and result of the most recent collection
+    latest: Option<(Instant, Collection)>,
+    /// Count of stored collections, to detect completion of an in-flight collection
+    generation: u64,
+    in_flight: bool,
+    stopped: bool,
+}
+
+impl Collector {
+    fn lock(&self) -> MutexGuard<'_, State> {
+        // state remains valid even if a holder panicked
+        self.state.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+    fn wait<'a>(&self, state: MutexGuard<'a, State>) -> MutexGuard<'a, State> {
+        self.changed
+            .wait(state)
+            .unwrap_or_else(PoisonError::into_inner)
+    }
+    fn store(&self, latest: Result<Arc<Snapshot>, Arc<MetricsError>>) -> Collection {
+        let mut state = self.lock();
+        let last_success = match &latest {
+            Ok(snapshot) => Some(Arc::clone(snapshot)),
+            Err(_) => state
+                .latest
+                .as_ref()
+                .and_then(|(_, collection)| collection.last_success.clone()),
+        };
+        let collection = Collection {
+            latest,
+            last_success,
+        };
+        state.latest = Some((Instant::now(), collection.clone()));
+        state.generation += 1;
+        state.in_flight = false;
+        self.changed.notify_all();
+        collection
+    }
+    /// Returns the most recent successful collection (if any)
+    pub fn last_success(&self) -> Option<Arc<Snapshot>> {
+        self.lock()
+            .latest
+            .as_ref()
+            .and_then(|(_, collection)| collection.last_success.clone())
+    }
+    pub fn progress(&self) -> &Progress {
+        &self.progress
+    }
+}
+
+impl AppContext {
+    /// Returns the collection to serve for a request, according to the [`CollectMode`]
+    pub(crate) fn collection(&self) -> Collection {
+        match self.collect_mode {
+            CollectMode::OnDemand => self.collect_now(),
+            CollectMode::MinInterval(interval) => self.collect_shared(interval),
+            CollectMode::Background(_) => {
+                let latest = self
+                    .collector
+                    .lock()
+                    .latest
+                    .as_ref()
+                    .map(|(_, collection)| collection.clone());
+                // only before the first poll completes
+                latest.unwrap_or_else(|| self.collect_now())
+            }
+        }
+    }
+
+    /// Runs `zpool status` in the current thread, and stores the result
+    pub(crate) fn collect_now(&self) -> Collection {
+        let _progress = self.collector.progress.start();
+        let latest = self
+            .collect_pools()
+            .map(|pools| {
+                Arc::new(Snapshot {
+                    pools,
+                    collected_at: jiff::Timestamp::now(),
+                })
+            })
+            .map_err(Arc::new);
+        self.collector.store(latest)
+    }
+
+    /// Returns the stored collection if newer than `interval`, otherwise collects (sharing the
+    /// result with concurrent callers)
+    fn collect_shared(&self, interval: Duration) -> Collection {
+        let mut state = self.collector.lock();
+        if let Some((completed, collection)) = &state.latest
+            && completed.elapsed() < interval
+        {
+            return collection.clone();
+        }
+        if state.in_flight {
+            let generation = state.generation;
+            while state.generation == generation {
+                state = self.collector.wait(state);
+            }
+            if let Some((_, collection)) = &state.latest {
+                return collection.clone();
+            }
+        }
+        state.in_flight = true;
+        drop(state);
+
+        self.collect_now()
+    }
+
+    /// Collects periodically in the current thread, until [`Self::stop_polling`] is called
+    ///
+    /// The first collection is after one `interval`, assuming the caller just collected (e.g.
+    /// the fail-fast check at startup)
+    pub(crate) fn poll_until_stopped(&self, interval: Duration) {
+        loop {
+            let start_time = Instant::now();
+
+            let mut state = self.collector.lock();
+            while !state.stopped {
+                let Some(remaining) = interval.checked_sub(start_time.elapsed()) else {
+                    break;
+                };
+                (state, _) = self
+                    .collector
+                    .changed
+                    .wait_timeout(state, remaining)
+                    .unwrap_or_else(PoisonError::into_inner);
+            }
+            if state.stopped {
+                return;
+            }
+            drop(state);
+
+            self.collect_now();
+        }
+    }
+
+    /// Returns the reason the server is not ready: the most recent collection failed, or is
+    /// older than the ready max age (if set)
+    pub(crate) fn check_ready(&self) -> Result<(), String> {
+        let state = self.collector.lock();
+        let Some((completed, collection)) = &state.latest else {
+            return Err("no collection yet".to_owned());
+        };
+        let age = completed.elapsed();
+        if let Err(error) = &collection.latest {
+            return Err(format!(
+                "last collection failed {}s ago: {error}",
+                age.as_secs()
+            ));
+        }
+        match self.ready_max_age {
+            Some(max_age) if age > max_age => Err(format!(
+                "last collection {}s ago, exceeds the maximum age {}s",
+                age.as_secs(),
+                max_age.as_secs()
+            )),
+            _ => Ok(()),
+        }
+    }
+
+    /// Signals [`Self::poll_until_stopped`] to return
+    pub(crate) fn stop_polling(&self) {
+        self.collector.lock().stopped = true;
+        self.collector.changed.notify_all();
+    }
+}
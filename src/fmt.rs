@@ -3,14 +3,17 @@
 #[macro_use]
 mod macros;
 
+mod exporter;
 mod meta;
+pub(crate) mod options;
+pub(crate) mod rules;
 
 // Define output values
 //
 // Keep the values stable, for continuity in prometheus history
 value_enum! {
     #[allow(missing_docs)]
-    pub(crate) enum DeviceStatusValue for DeviceStatus {
+    pub(crate) enum DeviceStatusValue for DeviceStatus, errors >= 50 {
         #[default]
         UnknownMissing => 0,
         Unrecognized => 1,
@@ -27,7 +30,7 @@ value_enum! {
         Unavail  => 100,
     }
     #[allow(missing_docs)]
-    pub(crate) enum PoolStatusDescriptionValue for PoolStatusDescription {
+    pub(crate) enum PoolStatusDescriptionValue for PoolStatusDescription, errors >= 50 {
         #[default]
         Normal => 0,
         Unrecognized => 1,
@@ -39,7 +42,7 @@ value_enum! {
         DataCorruption => 50,
     }
     #[allow(missing_docs)]
-    pub(crate) enum ScanStatusValue for ScanStatus {
+    pub(crate) enum ScanStatusValue for ScanStatus, errors >= 50 {
         #[default]
         UnknownMissing => 0,
         Unrecognized => 1,
@@ -48,13 +51,14 @@ value_enum! {
         Resilvered => 15,
         // misc
         ScrubInProgress => 30,
+        ResilverInProgress => 31,
         ScrubCanceled => 35,
         NeverScanned => 40,
         // errors
         // TODO Add new statuses here
     }
     #[allow(missing_docs)]
-    pub(crate) enum ErrorStatusValue for ErrorStatus {
+    pub(crate) enum ErrorStatusValue for ErrorStatus, errors >= 50 {
         #[default]
         UnknownMissing => 0,
         Unrecognized => 1,
@@ -66,16 +70,20 @@ value_enum! {
 }
 
 use self::context::WriteKeyValue as _;
+pub(crate) use self::exporter::{CollectionStatus, format_exporter_metrics, format_up_metric};
+pub(crate) use self::options::FormatOptions;
 use crate::{
     fmt::meta::MetricWrite as _,
     zfs::{
         DeviceMetrics, DeviceStatus, ErrorStatus, PoolMetrics, PoolStatusDescription, ScanStatus,
+        VdevType,
     },
 };
 use std::time::Instant;
 
 struct FormatPoolMetrics<'a> {
-    pools: Vec<PoolMetrics>,
+    pools: &'a [PoolMetrics],
+    options: &'a FormatOptions,
     now: &'a jiff::Zoned,
     /// If present, start time for the computation
     ///
@@ -86,12 +94,14 @@ struct FormatPoolMetrics<'a> {
 /// Returns the "prometheus style" output metrics for the specified `pools`
 #[must_use]
 pub(super) fn format_metrics(
-    pools: Vec<PoolMetrics>,
+    pools: &[PoolMetrics],
+    options: &FormatOptions,
     now: &'_ jiff::Zoned,
     compute_time_start: Option<Instant>,
 ) -> String {
     FormatPoolMetrics {
         pools,
+        options,
         now,
         compute_time_start,
     }
@@ -99,25 +109,37 @@ pub(super) fn format_metrics(
 }
 
 mod context {
+    use super::FormatOptions;
+
     pub fn write_prefix_label<T: super::meta::MetricWrite + ?Sized>(
         key: &T,
         f: &mut std::fmt::Formatter<'_>,
+        options: &FormatOptions,
     ) -> std::fmt::Result {
-        const PREFIX: &str = "zpool";
+        let prefix = options.prefix();
         let key = key.metric_name();
-        write!(f, "{PREFIX}_{key}")
+        write!(f, "{prefix}_{key}")
     }
 
     pub trait WriteKeyValue {
         fn write_kv<T: super::meta::MetricWrite + ?Sized>(
             &self,
             f: &mut std::fmt::Formatter<'_>,
+            options: &FormatOptions,
             key: &T,
             value: f64,
         ) -> std::fmt::Result {
-            write_prefix_label(key, f)?;
+            write_prefix_label(key, f, options)?;
 
-            self.fmt_context(f)?;
+            let mut labels = Labels { f, first: true };
+            self.fmt_context(&mut labels)?;
+            for const_label in options.const_labels() {
+                labels.write(const_label.name(), &const_label.value())?;
+            }
+            let Labels { f, first } = labels;
+            if !first {
+                write!(f, "}}")?;
+            }
 
             // detect integers to print normally
             let precision = if value.fract().abs() < f64::EPSILON {
@@ -129,11 +151,27 @@ mod context {
             };
             writeln!(f, " {value:.precision$}")
         }
-        fn fmt_context(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result;
+        fn fmt_context(&self, labels: &mut Labels<'_, '_>) -> std::fmt::Result;
+    }
+    /// Writer for the comma-separated label pairs, surrounded by braces (if any)
+    pub struct Labels<'a, 'b> {
+        f: &'a mut std::fmt::Formatter<'b>,
+        first: bool,
+    }
+    impl Labels<'_, '_> {
+        /// Writes the label, quoting the value using the `Debug` impl
+        pub fn write(&mut self, name: &str, value: &dyn std::fmt::Debug) -> std::fmt::Result {
+            let separator = if std::mem::take(&mut self.first) {
+                '{'
+            } else {
+                ','
+            };
+            write!(self.f, "{separator}{name}={value:?}")
+        }
     }
     pub struct Empty;
     impl WriteKeyValue for Empty {
-        fn fmt_context(&self, _f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        fn fmt_context(&self, _labels: &mut Labels<'_, '_>) -> std::fmt::Result {
             Ok(())
         }
     }
@@ -141,9 +179,9 @@ mod context {
         pub pool_name: &'a str,
     }
     impl WriteKeyValue for Pool<'_> {
-        fn fmt_context(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        fn fmt_context(&self, labels: &mut Labels<'_, '_>) -> std::fmt::Result {
             let Self { pool_name } = self;
-            write!(f, "{{pool={pool_name:?}}}")
+            labels.write("pool", pool_name)
         }
     }
     pub struct Device<'a> {
@@ -151,12 +189,63 @@ mod context {
         pub dev_name: &'a super::DeviceTreeName,
     }
     impl WriteKeyValue for Device<'_> {
-        fn fmt_context(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        fn fmt_context(&self, labels: &mut Labels<'_, '_>) -> std::fmt::Result {
             let Self {
                 pool_name,
                 dev_name,
             } = self;
-            write!(f, "{{pool={pool_name:?},dev={dev_name:?}}}")
+            labels.write("pool", pool_name)?;
+            labels.write("dev", dev_name)
+        }
+    }
+    pub struct PoolDeviceState<'a> {
+        pub pool_name: &'a str,
+        pub state: super::DeviceStatusValue,
+    }
+    impl WriteKeyValue for PoolDeviceState<'_> {
+        fn fmt_context(&self, labels: &mut Labels<'_, '_>) -> std::fmt::Result {
+            let Self { pool_name, state } = self;
+            labels.write("pool", pool_name)?;
+            labels.write("state", &format!("{state:?}"))
+        }
+    }
+    pub struct BuildInfo<'a> {
+        pub version: &'a str,
+    }
+    impl WriteKeyValue for BuildInfo<'_> {
+        fn fmt_context(&self, labels: &mut Labels<'_, '_>) -> std::fmt::Result {
+            let Self { version } = self;
+            labels.write("version", version)
+        }
+    }
+    pub struct CollectorError<'a> {
+        pub kind: &'a str,
+    }
+    impl WriteKeyValue for CollectorError<'_> {
+        fn fmt_context(&self, labels: &mut Labels<'_, '_>) -> std::fmt::Result {
+            let Self { kind } = self;
+            labels.write("kind", kind)
+        }
+    }
+    pub struct DeviceInfo<'a> {
+        pub device: Device<'a>,
+        pub vdev_type: super::VdevType,
+        pub depth: usize,
+    }
+    impl WriteKeyValue for DeviceInfo<'_> {
+        fn fmt_context(&self, labels: &mut Labels<'_, '_>) -> std::fmt::Result {
+            let Self {
+                device,
+                vdev_type,
+                depth,
+            } = self;
+            device.fmt_context(labels)?;
+            labels.write("vdev_type", &vdev_type.label())?;
+            match device.dev_name.parent() {
+                Some(parent) => labels.write("parent", &parent)?,
+                None => labels.write("parent", &"")?,
+            }
+            labels.write("depth", &depth.to_string())
         }
     }
 }
@@ -169,19 +258,44 @@ impl std::fmt::Display for FormatPoolMetrics<'_> {
             self.fmt_pool_sections(f)?;
 
             self.fmt_device_sections(f)?;
+
+            self.fmt_health_sections(f)?;
         }
 
         if let Some(start_time) = self.compute_time_start {
             const LOOKUP: meta::SimpleMetric =
                 meta::metric("lookup", "total duration of the lookup in seconds");
-            LOOKUP.write_meta(f)?;
+            LOOKUP.write_meta(f, self.options)?;
             let lookup_duration = start_time.elapsed().as_secs_f64();
-            context::Empty.write_kv(f, &LOOKUP, lookup_duration)?;
+            context::Empty.write_kv(f, self.options, &LOOKUP, lookup_duration)?;
         }
         Ok(())
     }
 }
 
+const POOL_STATE: meta::ValuesMetric<DeviceStatusValue> =
+    meta::metric("pool_state", "Pool state").with_values();
+const POOL_STATUS_DESCRIPTION: meta::ValuesMetric<PoolStatusDescriptionValue> =
+    meta::metric("pool_status_desc", "Pool status description").with_values();
+const SCAN_STATE: meta::ValuesMetric<ScanStatusValue> = //
+    meta::metric("scan_state", "Scan status").with_values();
+const SCAN_AGE: meta::SimpleMetric = //
+    meta::metric("scan_age", "Scan age in hours");
+const SCAN_END_TIMESTAMP: meta::SimpleMetric = meta::metric(
+    "scan_end_timestamp_seconds",
+    "Scan completion time as a Unix timestamp (absent if no completed scan)",
+);
+const SCAN_START_TIMESTAMP: meta::SimpleMetric = meta::metric(
+    "scan_start_timestamp_seconds",
+    "Scan start time as a Unix timestamp (only present for in-progress scans)",
+);
+const ERROR_STATE: meta::ValuesMetric<ErrorStatusValue> =
+    meta::metric("error_state", "Error status").with_values();
+/// Pool metrics for each of the value enums, except [`POOL_STATE`] (e.g. to alert on every error
+/// state)
+const POOL_ERROR_METRICS: [&dyn meta::ValuesMetricWrite; 3] =
+    [&POOL_STATUS_DESCRIPTION, &SCAN_STATE, &ERROR_STATE];
+
 enum_all! {
     #[derive(Clone, Copy)]
     enum PoolSections {
@@ -189,22 +303,13 @@ enum_all! {
         PoolStatusDescription,
         ScanState,
         ScanAge,
+        ScanEndTimestamp,
+        ScanStartTimestamp,
         ErrorState,
     }
 }
 impl FormatPoolMetrics<'_> {
     fn fmt_pool_sections(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        const POOL_STATE: meta::ValuesMetric<DeviceStatusValue> =
-            meta::metric("pool_state", "Pool state").with_values();
-        const POOL_STATUS_DESCRIPTION: meta::ValuesMetric<PoolStatusDescriptionValue> =
-            meta::metric("pool_status_desc", "Pool status description").with_values();
-        const SCAN_STATE: meta::ValuesMetric<ScanStatusValue> = //
-            meta::metric("scan_state", "Scan status").with_values();
-        const SCAN_AGE: meta::SimpleMetric = //
-            meta::metric("scan_age", "Scan age in hours");
-        const ERROR_STATE: meta::ValuesMetric<ErrorStatusValue> =
-            meta::metric("error_state", "Error status").with_values();
-
         const SECONDS_PER_HOUR: f64 = 60.0 * 60.0;
         const HUNDRED_YEARS_IN_HOURS: f64 = 876_000.0;
 
@@ -215,11 +320,13 @@ impl FormatPoolMetrics<'_> {
                 S::PoolStatusDescription => &POOL_STATUS_DESCRIPTION,
                 S::ScanState => &SCAN_STATE,
                 S::ScanAge => &SCAN_AGE,
+                S::ScanEndTimestamp => &SCAN_END_TIMESTAMP,
+                S::ScanStartTimestamp => &SCAN_START_TIMESTAMP,
                 S::ErrorState => &ERROR_STATE,
             };
-            metric.write_meta(f)?;
+            metric.write_meta(f, self.options)?;
 
-            for pool in &self.pools {
+            for pool in self.pools {
                 let PoolMetrics {
                     name: pool_name,
                     state,
@@ -227,6 +334,7 @@ impl FormatPoolMetrics<'_> {
                     scan_status,
                     devices: _, // see `fmt_device_sections`
                     error,
+                    text: _,
                 } = pool;
                 let value = match section {
                     S::PoolState => DeviceStatusValue::from_opt(state.as_ref()).into(),
@@ -246,9 +354,30 @@ impl FormatPoolMetrics<'_> {
                                 seconds / SECONDS_PER_HOUR
                             })
                     }
+                    S::ScanEndTimestamp | S::ScanStartTimestamp => {
+                        // unit: seconds since the Unix epoch
+                        //
+                        // in-progress scans only have a start time, completed scans only an end
+                        // time (no sample when the time is unknown, e.g. never scanned, or the
+                        // status is unrecognized)
+                        let Some((scan_status, Some(scan_time))) = scan_status else {
+                            continue;
+                        };
+                        let is_start = match scan_status {
+                            ScanStatus::ScrubInProgress | ScanStatus::ResilverInProgress => true,
+                            ScanStatus::ScrubRepaired
+                            | ScanStatus::Resilvered
+                            | ScanStatus::ScrubCanceled => false,
+                            ScanStatus::Unrecognized | ScanStatus::NeverScanned => continue,
+                        };
+                        if is_start != matches!(section, S::ScanStartTimestamp) {
+                            continue;
+                        }
+                        scan_time.timestamp().as_duration().as_secs_f64()
+                    }
                     S::ErrorState => ErrorStatusValue::from_opt(error.as_ref()).into(),
                 };
-                context::Pool { pool_name }.write_kv(f, metric, value)?;
+                context::Pool { pool_name }.write_kv(f, self.options, metric, value)?;
             }
         }
         Ok(())
@@ -262,6 +391,7 @@ enum_all! {
         ErrorsRead,
         ErrorsWrite,
         ErrorsChecksum,
+        Info,
     }
 }
 impl FormatPoolMetrics<'_> {
@@ -284,6 +414,11 @@ impl FormatPoolMetrics<'_> {
                 "dev_errors_checksum",
                 "Checksum error count (dev=\"__root__\" for pool root)",
             );
+        const DEVICE_INFO: meta::SimpleMetric = //
+            meta::metric(
+                "dev_info",
+                "Device tree position, always 1 (parent=\"\" for pool root)",
+            );
 
         use DeviceSections as S;
         for section in S::ALL {
@@ -292,10 +427,11 @@ impl FormatPoolMetrics<'_> {
                 S::ErrorsRead => &ERRORS_READ,
                 S::ErrorsWrite => &ERRORS_WRITE,
                 S::ErrorsChecksum => &ERRORS_CHECKSUM,
+                S::Info => &DEVICE_INFO,
             };
-            metric.write_meta(f)?;
+            metric.write_meta(f, self.options)?;
 
-            for pool in &self.pools {
+            for pool in self.pools {
                 let pool_name = &pool.name;
 
                 let mut dev_name = DeviceTreeName::default();
@@ -303,23 +439,34 @@ impl FormatPoolMetrics<'_> {
                     let DeviceMetrics {
                         depth,
                         ref name,
+                        vdev_type,
                         state,
+                        state_text: _,
                         errors_read,
                         errors_write,
                         errors_checksum,
                     } = *device;
                     dev_name.update(depth, name.clone());
+                    let device = context::Device {
+                        pool_name,
+                        dev_name: &dev_name,
+                    };
                     let value = match section {
                         S::State => DeviceStatusValue::from(&state).value(),
                         S::ErrorsRead => errors_read,
                         S::ErrorsWrite => errors_write,
                         S::ErrorsChecksum => errors_checksum,
+                        S::Info => {
+                            context::DeviceInfo {
+                                device,
+                                vdev_type,
+                                depth,
+                            }
+                            .write_kv(f, self.options, metric, 1.0)?;
+                            continue;
+                        }
                     };
-                    context::Device {
-                        pool_name,
-                        dev_name: &dev_name,
-                    }
-                    .write_kv(f, metric, value.into())?;
+                    device.write_kv(f, self.options, metric, value.into())?;
                 }
             }
         }
@@ -327,6 +474,119 @@ impl FormatPoolMetrics<'_> {
     }
 }
 
+const LEAF_ERRORS_READ: meta::SimpleMetric = meta::metric(
+    "pool_leaf_errors_read",
+    "Read error count, summed across leaf devices",
+);
+const LEAF_ERRORS_WRITE: meta::SimpleMetric = meta::metric(
+    "pool_leaf_errors_write",
+    "Write error count, summed across leaf devices",
+);
+const LEAF_ERRORS_CHECKSUM: meta::SimpleMetric = meta::metric(
+    "pool_leaf_errors_checksum",
+    "Checksum error count, summed across leaf devices",
+);
+
+enum_all! {
+    #[derive(Clone, Copy)]
+    enum HealthSections {
+        LeafDevices,
+        LeafErrorsRead,
+        LeafErrorsWrite,
+        LeafErrorsChecksum,
+        LeafWorstState,
+        RedundancyRemaining,
+    }
+}
+impl FormatPoolMetrics<'_> {
+    fn fmt_health_sections(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        const LEAF_DEVICES: meta::SimpleMetric = meta::metric(
+            "pool_leaf_devices",
+            "Count of leaf devices (disks or files) in each state",
+        );
+        const LEAF_WORST_STATE: meta::ValuesMetric<DeviceStatusValue> = meta::metric(
+            "pool_leaf_worst_state",
+            "Most severe leaf device state (see pool_leaf_devices for Unrecognized leaves)",
+        )
+        .with_values();
+        const REDUNDANCY_REMAINING: meta::SimpleMetric = meta::metric(
+            "vdev_redundancy_remaining",
+            "Additional device failures each top-level vdev can survive",
+        );
+
+        use HealthSections as S;
+        for section in S::ALL {
+            let metric: &dyn meta::MetricWrite = match section {
+                S::LeafDevices => &LEAF_DEVICES,
+                S::LeafErrorsRead => &LEAF_ERRORS_READ,
+                S::LeafErrorsWrite => &LEAF_ERRORS_WRITE,
+                S::LeafErrorsChecksum => &LEAF_ERRORS_CHECKSUM,
+                S::LeafWorstState => &LEAF_WORST_STATE,
+                S::RedundancyRemaining => &REDUNDANCY_REMAINING,
+            };
+            metric.write_meta(f, self.options)?;
+
+            for pool in self.pools {
+                let pool_name = &pool.name;
+                let sum_errors = |errors: fn(&DeviceMetrics) -> u32| -> f64 {
+                    pool.leaf_devices().map(errors).map(f64::from).sum()
+                };
+                let value = match section {
+                    S::LeafDevices => {
+                        for &state in DeviceStatusValue::ALL {
+                            let count = pool
+                                .leaf_devices()
+                                .filter(|leaf| DeviceStatusValue::from(&leaf.state) == state)
+                                .count();
+                            let count = u32::try_from(count).unwrap_or(u32::MAX);
+                            context::PoolDeviceState { pool_name, state }.write_kv(
+                                f,
+                                self.options,
+                                metric,
+                                count.into(),
+                            )?;
+                        }
+                        continue;
+                    }
+                    S::LeafErrorsRead => sum_errors(|leaf| leaf.errors_read),
+                    S::LeafErrorsWrite => sum_errors(|leaf| leaf.errors_write),
+                    S::LeafErrorsChecksum => sum_errors(|leaf| leaf.errors_checksum),
+                    S::LeafWorstState => {
+                        // no sample for pools without leaf devices
+                        let Some(worst) = pool
+                            .leaf_devices()
+                            .map(|leaf| DeviceStatusValue::from(&leaf.state))
+                            .max_by_key(|state| state.value())
+                        else {
+                            continue;
+                        };
+                        worst.value().into()
+                    }
+                    S::RedundancyRemaining => {
+                        for vdev in pool.top_level_vdevs() {
+                            let mut dev_name = DeviceTreeName::default();
+                            dev_name.update(vdev.device.depth, vdev.device.name.clone());
+                            context::Device {
+                                pool_name,
+                                dev_name: &dev_name,
+                            }
+                            .write_kv(
+                                f,
+                                self.options,
+                                metric,
+                                vdev.redundancy_remaining.into(),
+                            )?;
+                        }
+                        continue;
+                    }
+                };
+                context::Pool { pool_name }.write_kv(f, self.options, metric, value)?;
+            }
+        }
+        Ok(())
+    }
+}
+
 /// Helper for printing device tree elements as slash/separated/strings
 ///
 /// NOTE: The `Debug` implementation surrounds the output in quotes, to match the `String` behavior
@@ -342,8 +602,20 @@ impl DeviceTreeName {
         self.0.truncate(depth);
         self.0.push(name);
     }
+    /// Returns the name of the parent device, or `None` for the pool root
+    fn parent(&self) -> Option<DeviceTreePath<'_>> {
+        self.0
+            .split_last()
+            .map(|(_, parent_elems)| DeviceTreePath(parent_elems))
+    }
 }
 impl std::fmt::Debug for DeviceTreeName {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        DeviceTreePath(&self.0).fmt(f)
+    }
+}
+struct DeviceTreePath<'a>(&'a [String]);
+impl std::fmt::Debug for DeviceTreePath<'_> {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         write!(f, "\"")?;
         if self.0.is_empty() {
@@ -352,7 +624,7 @@ impl std::fmt::Debug for DeviceTreeName {
         } else {
             // Child devices: slash-separated hierarchy
             let mut first = Some(());
-            for elem in &self.0 {
+            for elem in self.0 {
                 if first.take().is_none() {
                     write!(f, "/")?;
                 }
@@ -388,6 +660,20 @@ mod tests {
         assert_eq!(format!("{name:?}"), "\"mirror-0/loop0\"");
     }
 
+    #[test]
+    fn device_tree_name_parent() {
+        let mut name = DeviceTreeName::default();
+        assert!(name.parent().is_none(), "pool root has no parent");
+
+        name.update(1, "mirror-0".to_string());
+        let parent = name.parent().map(|parent| format!("{parent:?}"));
+        assert_eq!(parent.as_deref(), Some("\"__root__\""));
+
+        name.update(2, "loop0".to_string());
+        let parent = name.parent().map(|parent| format!("{parent:?}"));
+        assert_eq!(parent.as_deref(), Some("\"mirror-0\""));
+    }
+
     #[test]
     fn device_tree_name_back_to_root() {
         let mut name = DeviceTreeName::default();
@@ -0,0 +1,206 @@
+//! Metrics about the exporter itself, rather than the pools
+
+use super::{
+    FormatOptions,
+    context::{self, WriteKeyValue as _},
+    meta::{self, MetricWrite as _},
+};
+use crate::stats::{CollectorErrorKind, Snapshot};
+
+pub(super) const UP: meta::SimpleMetric = meta::metric(
+    "up",
+    "Whether the latest zpool command and parsing succeeded (1) or failed (0)",
+);
+
+struct FormatExporterMetrics<'a> {
+    options: &'a FormatOptions,
+    stats: &'a Snapshot,
+    collection: &'a CollectionStatus,
+}
+
+/// Outcome of the collection for the current response
+pub(crate) struct CollectionStatus {
+    /// True if the latest collection succeeded
+    pub up: bool,
+    /// True if the pool metrics are from an earlier collection, since the latest one failed
+    pub stale: bool,
+    /// Time of the collection for the pool metrics (if any)
+    pub collected_at: Option<jiff::Timestamp>,
+}
+
+/// Returns the "prometheus style" output metrics for the exporter itself
+#[must_use]
+pub(crate) fn format_exporter_metrics(
+    options: &FormatOptions,
+    stats: &Snapshot,
+    collection: &CollectionStatus,
+) -> String {
+    FormatExporterMetrics {
+        options,
+        stats,
+        collection,
+    }
+    .to_string()
+}
+
+/// Returns only the `up` metric, e.g. for files without the server statistics
+#[must_use]
+pub(crate) fn format_up_metric(options: &FormatOptions, up: bool) -> String {
+    struct FormatUp<'a> {
+        options: &'a FormatOptions,
+        up: bool,
+    }
+    impl std::fmt::Display for FormatUp<'_> {
+        fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+            let Self { options, up } = *self;
+            UP.write_meta(f, options)?;
+            context::Empty.write_kv(f, options, &UP, if up { 1.0 } else { 0.0 })
+        }
+    }
+    FormatUp { options, up }.to_string()
+}
+
+impl std::fmt::Display for FormatExporterMetrics<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        const BUILD_INFO: meta::SimpleMetric = meta::metric(
+            "exporter_build_info",
+            "Exporter version, always 1 (version label)",
+        );
+        const SCRAPES_TOTAL: meta::SimpleMetric = meta::counter(
+            "exporter_scrapes_total",
+            "Count of requests to the metrics endpoint",
+        );
+        const COLLECTOR_ERRORS_TOTAL: meta::SimpleMetric = meta::counter(
+            "exporter_collector_errors_total",
+            "Count of failures running or parsing the zpool command, by kind",
+        );
+        const COLLECTOR_DURATION: meta::SimpleMetric = meta::metric(
+            "exporter_collector_duration_seconds",
+            "Duration of the most recent zpool command and parsing",
+        );
+        const LAST_COLLECTION_TIMESTAMP: meta::SimpleMetric = meta::metric(
+            "last_collection_timestamp_seconds",
+            "Time of the zpool command for the pool metrics, as a Unix timestamp",
+        );
+        const COLLECTION_STALE: meta::SimpleMetric = meta::metric(
+            "collection_stale",
+            "Whether the pool metrics are from an earlier zpool command, since the latest one failed",
+        );
+        let Self {
+            options,
+            stats,
+            collection,
+        } = self;
+        let Snapshot {
+            scrapes_total,
+            collector_errors_total,
+            collector_duration,
+            last_scrape: _,
+        } = stats;
+        let CollectionStatus {
+            up,
+            stale,
+            collected_at,
+        } = collection;
+
+        BUILD_INFO.write_meta(f, options)?;
+        context::BuildInfo {
+            version: env!("CARGO_PKG_VERSION"),
+        }
+        .write_kv(f, options, &BUILD_INFO, 1.0)?;
+
+        SCRAPES_TOTAL.write_meta(f, options)?;
+        #[allow(clippy::cast_precision_loss)] // counts will not exceed 2^52
+        context::Empty.write_kv(f, options, &SCRAPES_TOTAL, *scrapes_total as f64)?;
+
+        COLLECTOR_ERRORS_TOTAL.write_meta(f, options)?;
+        for (&kind, count) in CollectorErrorKind::ALL.iter().zip(collector_errors_total) {
+            #[allow(clippy::cast_precision_loss)] // counts will not exceed 2^52
+            context::CollectorError { kind: kind.label() }.write_kv(
+                f,
+                options,
+                &COLLECTOR_ERRORS_TOTAL,
+                *count as f64,
+            )?;
+        }
+
+        COLLECTOR_DURATION.write_meta(f, options)?;
+        if let Some(collector_duration) = collector_duration {
+            context::Empty.write_kv(
+                f,
+                options,
+                &COLLECTOR_DURATION,
+                collector_duration.as_secs_f64(),
+            )?;
+        }
+
+        LAST_COLLECTION_TIMESTAMP.write_meta(f, options)?;
+        if let Some(collected_at) = collected_at {
+            context::Empty.write_kv(
+                f,
+                options,
+                &LAST_COLLECTION_TIMESTAMP,
+                collected_at.as_duration().as_secs_f64(),
+            )?;
+        }
+
+        COLLECTION_STALE.write_meta(f, options)?;
+        context::Empty.write_kv(
+            f,
+            options,
+            &COLLECTION_STALE,
+            if *stale { 1.0 } else { 0.0 },
+        )?;
+
+        UP.write_meta(f, options)?;
+        context::Empty.write_kv(f, options, &UP, if *up { 1.0 } else { 0.0 })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{CollectionStatus, format_exporter_metrics};
+    use crate::{
+        fmt::FormatOptions,
+        stats::{CollectorErrorKind, Snapshot},
+    };
+
+    #[test]
+    fn failed_collection() {
+        let mut stats = Snapshot {
+            scrapes_total: 3,
+            ..Snapshot::default()
+        };
+        stats.collector_errors_total[CollectorErrorKind::Timeout as usize] = 2;
+
+        let collection = CollectionStatus {
+            up: false,
+            stale: false,
+            collected_at: None,
+        };
+        let output = format_exporter_metrics(&FormatOptions::default(), &stats, &collection);
+        let lines: Vec<_> = output
+            .lines()
+            .filter(|line| !line.starts_with('#'))
+            .collect();
+        assert!(
+            lines.contains(&"zpool_exporter_scrapes_total 3"),
+            "{output}"
+        );
+        assert!(
+            lines.contains(&r#"zpool_exporter_collector_errors_total{kind="timeout"} 2"#),
+            "{output}"
+        );
+        assert!(
+            lines.contains(&r#"zpool_exporter_collector_errors_total{kind="zfs_parse"} 0"#),
+            "{output}"
+        );
+        assert!(
+            !lines
+                .iter()
+                .any(|line| line.starts_with("zpool_exporter_collector_duration_seconds")),
+            "no duration before the first collection: {output}"
+        );
+        assert_eq!(lines.last(), Some(&"zpool_up 0"), "{output}");
+    }
+}
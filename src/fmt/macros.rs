@@ -20,7 +20,7 @@ macro_rules! enum_all {
                 ),+
             }
             impl $name {
-                const ALL: &'static [Self] = &[
+                pub(crate) const ALL: &'static [Self] = &[
                     $(Self::$variant,)+
                 ];
             }
@@ -29,18 +29,32 @@ macro_rules! enum_all {
 }
 
 pub trait SummarizeValues {
+    /// Returns the name and value of all variants (in declaration order)
+    fn named_values() -> Vec<(&'static str, u32)>;
+    /// Returns the lowest value of the error states
+    fn error_value_min() -> u32;
     /// Writes a comma-separated representation of all variants: "Variant = value"
-    fn summarize_values(f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result;
+    fn summarize_values(f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let mut first = Some(());
+        for (name, value) in Self::named_values() {
+            if first.take().is_none() {
+                write!(f, ", ")?;
+            }
+            write!(f, "{name} = {value}")?;
+        }
+        Ok(())
+    }
 }
 
 /// Defines the enum with:
-/// - `fn summarize_values()` to list the name/value pairs, and
+/// - `fn summarize_values()` to list the name/value pairs,
+/// - `fn error_value_min()` for the error states (values from `errors >= N`), and
 /// - `fn value()` to retrieve the value
 macro_rules! value_enum {
     (
         $(
             $(#[$meta:meta])*
-            $vis:vis enum $name:ident for $source:ident {
+            $vis:vis enum $name:ident for $source:ident, errors >= $error_min:literal {
                 #[default]
                 $UnknownMissing:ident => 0,
                 $(
@@ -52,7 +66,7 @@ macro_rules! value_enum {
     ) => {
         $(
             enum_all! {
-                #[derive(Clone, Copy, Debug, Default)]
+                #[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
                 $(#[$meta])*
                 $vis enum $name {
                     #[default]
@@ -64,19 +78,24 @@ macro_rules! value_enum {
                 }
             }
             impl $crate::fmt::macros::SummarizeValues for $name {
-                fn summarize_values(f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-                    let mut first = Some(());
-                    for &status in $name::ALL {
-                        if first.take().is_none() {
-                            write!(f, ", ")?;
-                        }
-                        let status_num = status.value();
-                        write!(f, "{status:?} = {status_num}")?;
-                    }
-                    Ok(())
+                fn named_values() -> Vec<(&'static str, u32)> {
+                    $name::ALL
+                        .iter()
+                        .map(|&status| (status.name(), status.value()))
+                        .collect()
+                }
+                fn error_value_min() -> u32 {
+                    $error_min
                 }
             }
             impl $name {
+                /// Returns the name of the variant (as listed in the metric help)
+                pub(crate) fn name(self) -> &'static str {
+                    match self {
+                        Self::$UnknownMissing => stringify!($UnknownMissing),
+                        $(Self::$variant => stringify!($variant)),+
+                    }
+                }
                 /// Returns the value from the specified `Option`
                 pub fn from_opt<T>(source: Option<&T>) -> u32
                 where
@@ -92,6 +111,11 @@ macro_rules! value_enum {
                     }
                 }
             }
+            impl From<$name> for u32 {
+                fn from(value: $name) -> u32 {
+                    value.value()
+                }
+            }
             impl From<&$source> for $name {
                 fn from(source: &$source) -> Self {
                     match source {
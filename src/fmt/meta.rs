@@ -1,16 +1,20 @@
-use super::{context::write_prefix_label, macros::SummarizeValues};
+use super::{FormatOptions, context::write_prefix_label, macros::SummarizeValues};
 use std::marker::PhantomData;
 
 pub trait MetricWrite {
-    fn write_meta(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    fn write_meta(
+        &self,
+        f: &mut std::fmt::Formatter<'_>,
+        options: &FormatOptions,
+    ) -> std::fmt::Result {
         write!(f, "# HELP ")?;
-        write_prefix_label(self, f)?;
+        write_prefix_label(self, f, options)?;
         write!(f, " ")?;
         self.write_help(f)?;
         writeln!(f)?;
 
         write!(f, "# TYPE ")?;
-        write_prefix_label(self, f)?;
+        write_prefix_label(self, f, options)?;
         writeln!(f, " {ty}", ty = self.metric_type())?;
 
         Ok(())
@@ -23,12 +27,14 @@ pub trait MetricWrite {
 #[derive(Clone, Copy, Debug, PartialEq, Eq)]
 pub enum Type {
     Gauge,
-    // TODO - any Counters?  likely no, since all zpool numbers can be reset
+    /// Only for the exporter's own counts (zpool numbers can be reset, so they are gauges)
+    Counter,
 }
 impl std::fmt::Display for Type {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         let label = match self {
             Type::Gauge => "gauge",
+            Type::Counter => "counter",
         };
         write!(f, "{label}")
     }
@@ -41,6 +47,13 @@ pub const fn metric(metric_name: &'static str, help: &'static str) -> SimpleMetr
         ty: Type::Gauge,
     }
 }
+pub const fn counter(metric_name: &'static str, help: &'static str) -> SimpleMetric {
+    SimpleMetric {
+        metric_name,
+        help,
+        ty: Type::Counter,
+    }
+}
 impl SimpleMetric {
     pub const fn with_values<T: SummarizeValues>(self) -> ValuesMetric<T> {
         ValuesMetric {
@@ -90,3 +103,22 @@ where
         T::summarize_values(f)
     }
 }
+
+/// Metric with named values, independent of the value type (e.g. for a list of metrics)
+pub trait ValuesMetricWrite: MetricWrite {
+    /// Returns the name and value of all values
+    fn named_values(&self) -> Vec<(&'static str, u32)>;
+    /// Returns the lowest value of the error states
+    fn error_value_min(&self) -> u32;
+}
+impl<T> ValuesMetricWrite for ValuesMetric<T>
+where
+    T: SummarizeValues,
+{
+    fn named_values(&self) -> Vec<(&'static str, u32)> {
+        T::named_values()
+    }
+    fn error_value_min(&self) -> u32 {
+        T::error_value_min()
+    }
+}
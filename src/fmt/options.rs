@@ -0,0 +1,189 @@
+//! User-configurable naming applied to every metric (prefix and constant labels)
+
+/// Label names emitted by the exporter itself, which constant labels must not shadow
+const RESERVED_LABEL_NAMES: &[&str] = &[
+    "pool",
+    "dev",
+    "vdev_type",
+    "parent",
+    "depth",
+    "state",
+    "version",
+    "kind",
+];
+
+/// Formatting options common to all metrics
+#[derive(Default)]
+pub(crate) struct FormatOptions {
+    prefix: MetricPrefix,
+    const_labels: Vec<ConstLabel>,
+}
+impl FormatOptions {
+    pub fn prefix(&self) -> &str {
+        &self.prefix.0
+    }
+    pub fn const_labels(&self) -> &[ConstLabel] {
+        &self.const_labels
+    }
+    pub fn set_prefix(&mut self, prefix: MetricPrefix) {
+        self.prefix = prefix;
+    }
+    /// Replaces the constant labels
+    ///
+    /// # Errors
+    /// Returns an error if any label name is repeated
+    pub fn set_const_labels(&mut self, const_labels: Vec<ConstLabel>) -> Result<(), Error> {
+        for (index, label) in const_labels.iter().enumerate() {
+            if const_labels[..index]
+                .iter()
+                .any(|previous| previous.name == label.name)
+            {
+                return Err(Error {
+                    input: label.name.clone(),
+                    kind: ErrorKind::DuplicateLabelName,
+                });
+            }
+        }
+        self.const_labels = const_labels;
+        Ok(())
+    }
+}
+
+/// Prefix for all metric names (validated as a Prometheus metric name)
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct MetricPrefix(String);
+impl Default for MetricPrefix {
+    fn default() -> Self {
+        Self("zpool".to_owned())
+    }
+}
+impl std::str::FromStr for MetricPrefix {
+    type Err = Error;
+    fn from_str(prefix: &str) -> Result<Self, Error> {
+        let make_error = |kind| Error {
+            input: prefix.to_owned(),
+            kind,
+        };
+
+        // metric names match the regex `[a-zA-Z_:][a-zA-Z0-9_:]*`
+        let mut chars = prefix.chars();
+        let valid_first = chars
+            .next()
+            .is_some_and(|c| c.is_ascii_alphabetic() || c == '_' || c == ':');
+        let valid_rest = chars.all(|c| c.is_ascii_alphanumeric() || c == '_' || c == ':');
+        if valid_first && valid_rest {
+            Ok(Self(prefix.to_owned()))
+        } else {
+            Err(make_error(ErrorKind::InvalidPrefix))
+        }
+    }
+}
+
+/// Label added to every metric, parsed from `name=value`
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ConstLabel {
+    name: String,
+    value: String,
+}
+impl ConstLabel {
+    pub(crate) fn name(&self) -> &str {
+        &self.name
+    }
+    pub(crate) fn value(&self) -> &str {
+        &self.value
+    }
+}
+impl std::str::FromStr for ConstLabel {
+    type Err = Error;
+    fn from_str(input: &str) -> Result<Self, Error> {
+        let make_error = |kind| Error {
+            input: input.to_owned(),
+            kind,
+        };
+
+        let (name, value) = input
+            .split_once('=')
+            .ok_or(ErrorKind::MissingEquals)
+            .map_err(make_error)?;
+
+        // label names match the regex `[a-zA-Z_][a-zA-Z0-9_]*`
+        let mut chars = name.chars();
+        let valid_first = chars
+            .next()
+            .is_some_and(|c| c.is_ascii_alphabetic() || c == '_');
+        let valid_rest = chars.all(|c| c.is_ascii_alphanumeric() || c == '_');
+        if !(valid_first && valid_rest) {
+            return Err(make_error(ErrorKind::InvalidLabelName));
+        }
+        if name.starts_with("__") {
+            return Err(make_error(ErrorKind::ReservedLabelPrefix));
+        }
+        if RESERVED_LABEL_NAMES.contains(&name) {
+            return Err(make_error(ErrorKind::ReservedLabelName));
+        }
+
+        // label values are written escaped via `Debug`, which only matches the prometheus
+        // escaping for printable characters (and newline)
+        if value.chars().any(|c| c.is_control() && c != '\n') {
+            return Err(make_error(ErrorKind::InvalidLabelValue));
+        }
+
+        Ok(Self {
+            name: name.to_owned(),
+            value: value.to_owned(),
+        })
+    }
+}
+
+/// Invalid metric prefix or constant label
+#[derive(Debug)]
+pub struct Error {
+    input: String,
+    kind: ErrorKind,
+}
+#[derive(Debug)]
+enum ErrorKind {
+    InvalidPrefix,
+    MissingEquals,
+    InvalidLabelName,
+    ReservedLabelPrefix,
+    ReservedLabelName,
+    InvalidLabelValue,
+    DuplicateLabelName,
+}
+impl std::error::Error for Error {}
+impl std::fmt::Display for Error {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let Self { input, kind } = self;
+        match kind {
+            ErrorKind::InvalidPrefix => write!(
+                f,
+                "invalid metric prefix {input:?}, expected pattern [a-zA-Z_:][a-zA-Z0-9_:]*"
+            ),
+            ErrorKind::MissingEquals => {
+                write!(f, "expected label format name=value, found {input:?}")
+            }
+            ErrorKind::InvalidLabelName => write!(
+                f,
+                "invalid label name in {input:?}, expected pattern [a-zA-Z_][a-zA-Z0-9_]*"
+            ),
+            ErrorKind::ReservedLabelPrefix => {
+                write!(
+                    f,
+                    "label names starting with \"__\" are reserved, in {input:?}"
+                )
+            }
+            ErrorKind::ReservedLabelName => write!(
+                f,
+                "label name in {input:?} conflicts with a built-in label (one of {RESERVED_LABEL_NAMES:?})"
+            ),
+            ErrorKind::InvalidLabelValue => {
+                write!(
+                    f,
+                    "control characters not allowed in label value of {input:?}"
+                )
+            }
+            ErrorKind::DuplicateLabelName => write!(f, "duplicate label name {input:?}"),
+        }
+    }
+}
@@ -0,0 +1,349 @@
+//! Prometheus alerting rules for the metrics, generated from the metric values (so the rules
+//! follow the states added to the value enums)
+
+use super::{
+    DeviceStatusValue, FormatOptions, LEAF_ERRORS_CHECKSUM, LEAF_ERRORS_READ, LEAF_ERRORS_WRITE,
+    POOL_ERROR_METRICS, POOL_STATE, SCAN_AGE, SCAN_STATE, ScanStatusValue,
+    context::write_prefix_label, exporter::UP, meta::MetricWrite,
+};
+use crate::StateThreshold;
+use std::time::Duration;
+
+/// Name of the rule group
+const GROUP_NAME: &str = "zpool-status-exporter";
+
+/// Thresholds for the generated alerting rules
+#[must_use]
+pub struct RuleThresholds {
+    state_warning: StateThreshold,
+    state_critical: StateThreshold,
+    device_errors: u32,
+    scan_age: Duration,
+    pending: Duration,
+}
+impl Default for RuleThresholds {
+    /// Warning for degraded pools, device errors, or a scan older than 35 days; critical for
+    /// faulted (or worse) pools, or data errors; each pending for 15 minutes before firing
+    fn default() -> Self {
+        Self {
+            state_warning: StateThreshold(DeviceStatusValue::Degraded),
+            state_critical: StateThreshold(DeviceStatusValue::Faulted),
+            device_errors: 1,
+            scan_age: Duration::from_hours(24 * 35),
+            pending: Duration::from_mins(15),
+        }
+    }
+}
+impl RuleThresholds {
+    /// Sets the pool state for a warning alert, including all more severe states
+    pub fn set_state_warning(mut self, state: StateThreshold) -> Self {
+        self.state_warning = state;
+        self
+    }
+    /// Sets the pool state for a critical alert, including all more severe states
+    pub fn set_state_critical(mut self, state: StateThreshold) -> Self {
+        self.state_critical = state;
+        self
+    }
+    /// Sets the count of device errors (summed across leaf devices) for a warning alert
+    pub fn set_device_errors(mut self, count: u32) -> Self {
+        self.device_errors = count;
+        self
+    }
+    /// Sets the time since the last scan for a warning alert
+    pub fn set_scan_age(mut self, age: Duration) -> Self {
+        self.scan_age = age;
+        self
+    }
+    /// Sets the time each condition must hold before the alert fires
+    pub fn set_pending(mut self, pending: Duration) -> Self {
+        self.pending = pending;
+        self
+    }
+}
+
+/// Returns the rules file (YAML) for the metrics
+pub(crate) fn format_alerting_rules(
+    options: &FormatOptions,
+    thresholds: &RuleThresholds,
+) -> String {
+    AlertingRules {
+        options,
+        thresholds,
+    }
+    .to_string()
+}
+
+struct AlertingRules<'a> {
+    options: &'a FormatOptions,
+    thresholds: &'a RuleThresholds,
+}
+
+#[derive(Clone, Copy)]
+enum Severity {
+    Warning,
+    Critical,
+}
+impl std::fmt::Display for Severity {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let label = match self {
+            Self::Warning => "warning",
+            Self::Critical => "critical",
+        };
+        write!(f, "{label}")
+    }
+}
+
+struct Rule {
+    alert: String,
+    expr: String,
+    severity: Severity,
+    summary: String,
+}
+
+impl AlertingRules<'_> {
+    /// Returns the selector for the metric, matching the constant labels
+    fn selector<'b>(&self, metric: &'b dyn MetricWrite) -> Selector<'_, 'b> {
+        Selector {
+            options: self.options,
+            metric,
+        }
+    }
+
+    fn rules(&self) -> Vec<Rule> {
+        let RuleThresholds {
+            state_warning,
+            state_critical,
+            device_errors,
+            scan_age,
+            pending: _,
+        } = self.thresholds;
+
+        let mut rules = vec![Rule {
+            alert: "ZpoolCollectionFailed".to_owned(),
+            expr: format!("{} == 0", self.selector(&UP)),
+            severity: Severity::Warning,
+            summary: "zpool status failed on {{ $labels.instance }}".to_owned(),
+        }];
+
+        for &state in DeviceStatusValue::ALL {
+            let severity = if state.value() < DeviceStatusValue::Online.value() {
+                // missing or unrecognized by the exporter
+                Severity::Warning
+            } else if state_critical.matches(state) {
+                Severity::Critical
+            } else if state_warning.matches(state) {
+                Severity::Warning
+            } else {
+                continue;
+            };
+            let name = state.name();
+            rules.push(Rule {
+                alert: format!("ZpoolPool{name}"),
+                expr: format!("{} == {}", self.selector(&POOL_STATE), state.value()),
+                severity,
+                summary: format!("Pool {{{{ $labels.pool }}}} state is {name}"),
+            });
+        }
+
+        for metric in POOL_ERROR_METRICS {
+            let error_value_min = metric.error_value_min();
+            for (name, value) in metric.named_values() {
+                if value < error_value_min {
+                    continue;
+                }
+                rules.push(Rule {
+                    alert: format!("Zpool{name}"),
+                    expr: format!("{} == {value}", self.selector(metric)),
+                    severity: Severity::Critical,
+                    summary: format!("Pool {{{{ $labels.pool }}}} reports {name}"),
+                });
+            }
+        }
+
+        rules.push(Rule {
+            alert: "ZpoolDeviceErrors".to_owned(),
+            expr: format!(
+                "{} + {} + {} >= {device_errors}",
+                self.selector(&LEAF_ERRORS_READ),
+                self.selector(&LEAF_ERRORS_WRITE),
+                self.selector(&LEAF_ERRORS_CHECKSUM),
+            ),
+            severity: Severity::Warning,
+            summary: "Pool {{ $labels.pool }} has {{ $value }} device errors".to_owned(),
+        });
+
+        let never_scanned = ScanStatusValue::NeverScanned.value();
+        let scan_age_hours = scan_age.as_secs() / 3600;
+        rules.push(Rule {
+            alert: "ZpoolScanStale".to_owned(),
+            expr: format!(
+                "{} > {scan_age_hours} unless {} == {never_scanned}",
+                self.selector(&SCAN_AGE),
+                self.selector(&SCAN_STATE),
+            ),
+            severity: Severity::Warning,
+            summary: "Pool {{ $labels.pool }} last scanned {{ $value | humanize }} hours ago"
+                .to_owned(),
+        });
+        rules.push(Rule {
+            alert: "ZpoolNeverScanned".to_owned(),
+            expr: format!("{} == {never_scanned}", self.selector(&SCAN_STATE)),
+            severity: Severity::Warning,
+            summary: "Pool {{ $labels.pool }} has never been scanned".to_owned(),
+        });
+
+        rules
+    }
+}
+
+impl std::fmt::Display for AlertingRules<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        writeln!(
+            f,
+            "# Prometheus alerting rules, generated by `zpool-status-exporter generate-rules`"
+        )?;
+        writeln!(f, "#")?;
+        writeln!(f, "# Metric values:")?;
+        let value_metrics = std::iter::once::<&dyn MetricWrite>(&POOL_STATE)
+            .chain(POOL_ERROR_METRICS.map(|metric| -> &dyn MetricWrite { metric }));
+        for metric in value_metrics {
+            write!(f, "# - ")?;
+            write_prefix_label(metric, f, self.options)?;
+            write!(f, ": ")?;
+            metric.write_help(f)?;
+            writeln!(f)?;
+        }
+
+        let pending = format_duration(self.thresholds.pending);
+        writeln!(f, "groups:")?;
+        writeln!(f, "  - name: {GROUP_NAME}")?;
+        writeln!(f, "    rules:")?;
+        for rule in self.rules() {
+            let Rule {
+                alert,
+                expr,
+                severity,
+                summary,
+            } = rule;
+            writeln!(f, "      - alert: {alert}")?;
+            writeln!(f, "        expr: {}", Quoted(&expr))?;
+            writeln!(f, "        for: {pending}")?;
+            writeln!(f, "        labels:")?;
+            writeln!(f, "          severity: {severity}")?;
+            writeln!(f, "        annotations:")?;
+            writeln!(f, "          summary: {}", Quoted(&summary))?;
+        }
+        Ok(())
+    }
+}
+
+/// Metric name (with prefix) and the constant labels, as a `PromQL` selector
+struct Selector<'a, 'b> {
+    options: &'a FormatOptions,
+    metric: &'b dyn MetricWrite,
+}
+impl std::fmt::Display for Selector<'_, '_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let Self { options, metric } = self;
+        write_prefix_label(*metric, f, options)?;
+        let mut separator = '{';
+        for const_label in options.const_labels() {
+            // quoted as in the metrics output
+            write!(
+                f,
+                "{separator}{}={:?}",
+                const_label.name(),
+                const_label.value()
+            )?;
+            separator = ',';
+        }
+        if separator == ',' {
+            write!(f, "}}")?;
+        }
+        Ok(())
+    }
+}
+
+/// YAML double-quoted string (the contents have no control characters, except newline)
+struct Quoted<'a>(&'a str);
+impl std::fmt::Display for Quoted<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "\"")?;
+        for c in self.0.chars() {
+            match c {
+                '"' => write!(f, "\\\"")?,
+                '\\' => write!(f, "\\\\")?,
+                '\n' => write!(f, "\\n")?,
+                c => write!(f, "{c}")?,
+            }
+        }
+        write!(f, "\"")
+    }
+}
+
+/// Returns the duration in the Prometheus format, in whole minutes if possible (e.g. "15m")
+fn format_duration(duration: Duration) -> String {
+    let secs = duration.as_secs();
+    if secs.is_multiple_of(60) {
+        format!("{}m", secs / 60)
+    } else {
+        format!("{secs}s")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{RuleThresholds, format_alerting_rules};
+    use crate::fmt::{FormatOptions, POOL_ERROR_METRICS};
+
+    #[test]
+    fn state_alerts_follow_thresholds() {
+        let thresholds = RuleThresholds::default()
+            .set_state_warning("faulted".parse().expect("valid state"))
+            .set_state_critical("unavail".parse().expect("valid state"));
+        let rules = format_alerting_rules(&FormatOptions::default(), &thresholds);
+
+        let alerts: Vec<_> = rules
+            .lines()
+            .filter_map(|line| line.strip_prefix("      - alert: ZpoolPool"))
+            .collect();
+        assert_eq!(
+            alerts,
+            [
+                "UnknownMissing",
+                "Unrecognized",
+                "Faulted",
+                "Suspended",
+                "Removed",
+                "Unavail"
+            ]
+        );
+        assert!(rules.contains(
+            "      - alert: ZpoolPoolUnavail\n        expr: \"zpool_pool_state == 100\"\n        for: 15m\n        labels:\n          severity: critical\n"
+        ));
+        assert!(rules.contains(
+            "      - alert: ZpoolPoolRemoved\n        expr: \"zpool_pool_state == 80\"\n        for: 15m\n        labels:\n          severity: warning\n"
+        ));
+    }
+
+    #[test]
+    fn error_alerts_cover_every_error_value() {
+        let rules = format_alerting_rules(&FormatOptions::default(), &RuleThresholds::default());
+
+        for metric in POOL_ERROR_METRICS {
+            let metric_name = metric.metric_name();
+            let error_value_min = metric.error_value_min();
+            let error_values = metric
+                .named_values()
+                .into_iter()
+                .filter(|&(_, value)| value >= error_value_min);
+            for (name, value) in error_values {
+                let alert = format!(
+                    "      - alert: Zpool{name}\n        expr: \"zpool_{metric_name} == {value}\"\n"
+                );
+                assert!(rules.contains(&alert), "{metric_name} {name} = {value}");
+            }
+        }
+    }
+}
@@ -0,0 +1,112 @@
+//! Derived health summary of a pool, computed from the flat device list
+//!
+//! The device list is in tree order (parents before children), with the nesting given by `depth`
+
+use crate::zfs::{DeviceMetrics, DeviceStatus, PoolMetrics, VdevType};
+
+/// Top-level vdev (direct child of the pool root) with its computed redundancy
+pub(crate) struct TopLevelVdev<'a> {
+    pub device: &'a DeviceMetrics,
+    /// Number of additional child failures the vdev can survive
+    ///
+    /// Zero when the vdev has no redundancy left (or has already lost data)
+    pub redundancy_remaining: u32,
+}
+
+impl PoolMetrics {
+    /// Returns the leaf devices (disks or files), excluding the pool root
+    pub fn leaf_devices(&self) -> impl Iterator<Item = &DeviceMetrics> {
+        self.devices
+            .iter()
+            .enumerate()
+            .filter(|(index, device)| device.depth > 0 && self.children(*index).next().is_none())
+            .map(|(_, device)| device)
+    }
+
+    /// Returns the top-level vdevs, with the remaining redundancy of each
+    pub fn top_level_vdevs(&self) -> impl Iterator<Item = TopLevelVdev<'_>> {
+        self.devices
+            .iter()
+            .enumerate()
+            .filter(|(_, device)| device.depth == 1)
+            .map(|(index, device)| {
+                let children: Vec<_> = self.children(index).collect();
+                let failed = children
+                    .iter()
+                    .filter(|child| !is_available(child.state))
+                    .count();
+                let tolerance = fault_tolerance(device, children.len());
+                let failed = u32::try_from(failed).unwrap_or(u32::MAX);
+                TopLevelVdev {
+                    device,
+                    redundancy_remaining: tolerance.saturating_sub(failed),
+                }
+            })
+    }
+
+    /// Returns the direct children of the device at the specified `index`
+    fn children(&self, index: usize) -> impl Iterator<Item = &DeviceMetrics> {
+        let depth = self.devices.get(index).map_or(0, |device| device.depth);
+        self.devices
+            .iter()
+            .skip(index + 1)
+            .take_while(move |child| child.depth > depth)
+            .filter(move |child| child.depth == depth + 1)
+    }
+}
+
+/// Returns true if the device still provides data (possibly with errors)
+fn is_available(state: DeviceStatus) -> bool {
+    match state {
+        DeviceStatus::Online | DeviceStatus::Degraded => true,
+        // unknown states are counted as failed, to err on the side of alerting
+        DeviceStatus::Unrecognized
+        | DeviceStatus::Offline
+        | DeviceStatus::Split
+        | DeviceStatus::Faulted
+        | DeviceStatus::Suspended
+        | DeviceStatus::Removed
+        | DeviceStatus::Unavail => false,
+    }
+}
+
+/// Returns the number of child failures the vdev can survive when fully healthy
+fn fault_tolerance(device: &DeviceMetrics, child_count: usize) -> u32 {
+    match device.vdev_type {
+        // every child holds a full copy
+        VdevType::Mirror | VdevType::Spare | VdevType::Replacing => {
+            u32::try_from(child_count.saturating_sub(1)).unwrap_or(u32::MAX)
+        }
+        VdevType::Raidz1 => 1,
+        VdevType::Raidz2 => 2,
+        VdevType::Raidz3 => 3,
+        VdevType::Draid => draid_parity(&device.name),
+        VdevType::Root | VdevType::File | VdevType::Disk => 0,
+    }
+}
+
+/// Parses the parity level from a dRAID vdev name, e.g. "draid2:4d:1c:0s-0"
+///
+/// The parity digit is omitted for single parity, e.g. "draid:4d:1c:0s-0"
+fn draid_parity(name: &str) -> u32 {
+    let kind = name.split(':').next().unwrap_or_default();
+    let parity = kind.strip_prefix("draid").unwrap_or_default();
+    if parity.is_empty() {
+        1
+    } else {
+        parity.parse().unwrap_or(0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::draid_parity;
+
+    #[test]
+    fn draid_parity_levels() {
+        assert_eq!(draid_parity("draid:4d:1c:0s-0"), 1);
+        assert_eq!(draid_parity("draid1:4d:1c:0s-0"), 1);
+        assert_eq!(draid_parity("draid2:4d:1c:0s-1"), 2);
+        assert_eq!(draid_parity("draid3:8d:12c:1s-0"), 3);
+    }
+}
@@ -0,0 +1,189 @@
+//! Serializable mirror of the parsed pool tree, for the JSON endpoint
+//!
+//! The field names and nesting are part of the public API (`/api/v1/pools`),
+//! so keep them stable even when the parser internals change.
+
+use crate::{
+    fmt::{DeviceStatusValue, ErrorStatusValue, PoolStatusDescriptionValue, ScanStatusValue},
+    zfs::{self, DeviceMetrics, PoolMetrics},
+};
+
+/// Returns the pretty-printed JSON document for the specified `pools`
+#[must_use]
+pub(crate) fn format_pools_json(pools: &[PoolMetrics]) -> String {
+    let document = Document {
+        pools: pools.iter().map(Pool::from).collect(),
+    };
+    serde_json::to_string_pretty(&document).expect("serializable document (string keys only)")
+}
+
+#[derive(serde::Serialize)]
+struct Document<'a> {
+    pools: Vec<Pool<'a>>,
+}
+
+#[derive(serde::Serialize)]
+struct Pool<'a> {
+    name: &'a str,
+    state: Option<Status<'a>>,
+    status: Option<Status<'a>>,
+    scan: Option<Scan<'a>>,
+    errors: Option<Status<'a>>,
+    /// Device tree, starting from the pool root device(s)
+    devices: Vec<Device<'a>>,
+}
+
+/// Parsed status with the prometheus metric value, and the original text
+#[derive(serde::Serialize)]
+struct Status<'a> {
+    kind: String,
+    value: u32,
+    text: Option<&'a str>,
+}
+
+#[derive(serde::Serialize)]
+struct Scan<'a> {
+    #[serde(flatten)]
+    status: Status<'a>,
+    /// RFC 3339 time of the scan (start time for in-progress scans, otherwise end time)
+    time: Option<String>,
+    time_unix: Option<i64>,
+}
+
+#[derive(serde::Serialize)]
+struct Device<'a> {
+    name: &'a str,
+    vdev_type: &'static str,
+    state: Status<'a>,
+    errors: DeviceErrors,
+    children: Vec<Device<'a>>,
+}
+
+#[derive(serde::Serialize)]
+#[allow(clippy::struct_field_names)]
+struct DeviceErrors {
+    read: u32,
+    write: u32,
+    checksum: u32,
+}
+
+impl<'a> From<&'a PoolMetrics> for Pool<'a> {
+    fn from(pool: &'a PoolMetrics) -> Self {
+        let PoolMetrics {
+            name,
+            state,
+            pool_status,
+            scan_status,
+            devices,
+            error,
+            text,
+        } = pool;
+        let zfs::PoolText {
+            state: state_text,
+            pool_status: pool_status_text,
+            scan_status: scan_status_text,
+            error: error_text,
+        } = text;
+
+        Self {
+            name,
+            state: state
+                .as_ref()
+                .map(|state| Status::new(DeviceStatusValue::from(state), state_text.as_deref())),
+            status: pool_status.as_ref().map(|pool_status| {
+                Status::new(
+                    PoolStatusDescriptionValue::from(pool_status),
+                    pool_status_text.as_deref(),
+                )
+            }),
+            scan: scan_status.as_ref().map(|(scan_status, scan_time)| Scan {
+                status: Status::new(
+                    ScanStatusValue::from(scan_status),
+                    scan_status_text.as_deref(),
+                ),
+                time: scan_time.as_ref().map(|scan_time| {
+                    scan_time
+                        .timestamp()
+                        .display_with_offset(scan_time.offset())
+                        .to_string()
+                }),
+                time_unix: scan_time
+                    .as_ref()
+                    .map(|scan_time| scan_time.timestamp().as_second()),
+            }),
+            errors: error
+                .as_ref()
+                .map(|error| Status::new(ErrorStatusValue::from(error), error_text.as_deref())),
+            devices: Device::build_tree(devices),
+        }
+    }
+}
+
+impl<'a> Status<'a> {
+    fn new<T>(value: T, text: Option<&'a str>) -> Self
+    where
+        T: Copy + std::fmt::Debug + Into<u32>,
+    {
+        Self {
+            kind: format!("{value:?}"),
+            value: value.into(),
+            text,
+        }
+    }
+}
+
+impl<'a> Device<'a> {
+    /// Nests the flat device list according to the depth of each device
+    fn build_tree(devices: &'a [DeviceMetrics]) -> Vec<Self> {
+        let mut roots = vec![];
+        // path from the current root to the most recent device
+        let mut path: Vec<Self> = vec![];
+
+        let pop_into_parent = |path: &mut Vec<Self>, roots: &mut Vec<Self>| {
+            if let Some(child) = path.pop() {
+                if let Some(parent) = path.last_mut() {
+                    parent.children.push(child);
+                } else {
+                    roots.push(child);
+                }
+            }
+        };
+
+        for device in devices {
+            while path.len() > device.depth {
+                pop_into_parent(&mut path, &mut roots);
+            }
+            path.push(Self::from(device));
+        }
+        while !path.is_empty() {
+            pop_into_parent(&mut path, &mut roots);
+        }
+
+        roots
+    }
+}
+impl<'a> From<&'a DeviceMetrics> for Device<'a> {
+    fn from(device: &'a DeviceMetrics) -> Self {
+        let DeviceMetrics {
+            depth: _, // see `build_tree`
+            name,
+            vdev_type,
+            state,
+            state_text,
+            errors_read,
+            errors_write,
+            errors_checksum,
+        } = device;
+        Self {
+            name,
+            vdev_type: vdev_type.label(),
+            state: Status::new(DeviceStatusValue::from(state), Some(state_text)),
+            errors: DeviceErrors {
+                read: *errors_read,
+                write: *errors_write,
+                checksum: *errors_checksum,
+            },
+            children: vec![],
+        }
+    }
+}
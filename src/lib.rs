@@ -24,40 +24,162 @@
 #![deny(missing_docs)]
 #![deny(rustdoc::broken_intra_doc_links)]
 
+pub use check::{CheckReport, CheckStatus, CheckThresholds, StateThreshold, StateThresholdError};
+pub use cidr::{Cidr, Error as CidrError};
+pub use exec::ZpoolCommand;
+pub use fmt::options::{ConstLabel, Error as FormatOptionsError, MetricPrefix};
+pub use fmt::rules::RuleThresholds;
+pub use listen::{
+    ActivatedListener, ActivationError, Error as ListenAddressError, ListenAddress,
+    take_activated_listeners,
+};
 pub use metrics::Error as MetricsError;
+pub use privileges::{Error as PrivilegesError, RunAs};
+pub use push::{PushError, Pushgateway};
 pub use server::Builder as ServerBuilder;
 pub use server::Error as ServerError;
 use std::time::Instant;
+pub use textfile::TextfileError;
 use tinytemplate::TinyTemplate;
+pub use tls::Files as TlsFiles;
+pub use watchdog::Heartbeat;
 pub use zfs::ParseError as ZfsParseError;
 
 mod auth;
+mod check;
+mod cidr;
+mod collector;
+#[macro_use]
 mod fmt;
+mod health;
+mod json;
+mod listen;
+mod privileges;
+mod push;
+mod stats;
+mod textfile;
+mod tls;
+mod watchdog;
 mod zfs;
 
 /// Command-line arguments for the server
 #[must_use]
 pub struct Args {
-    /// Bind address for the server
-    listen_address: std::net::SocketAddr,
+    /// Bind addresses for the server
+    listen_addresses: Vec<ListenAddress>,
+    /// Permissions of Unix domain socket files
+    unix_socket_mode: u32,
     /// Filename containing allowed basic authentication tokens
     basic_auth_keys_file: Option<std::path::PathBuf>,
     /// Maximum number of bind retry attempts
     max_bind_retries: u32,
+    /// Filename containing allowed bearer tokens
+    bearer_token_file: Option<std::path::PathBuf>,
+    /// Reload the authentication files when modified
+    watch_auth_files: bool,
+    /// Certificate files for serving HTTPS (otherwise HTTP)
+    tls_files: Option<TlsFiles>,
+    /// Networks allowed to connect (empty allows all)
+    allow_cidrs: Vec<Cidr>,
+    /// Proxies trusted to report the client address in the `X-Forwarded-For` header
+    trusted_proxies: Vec<Cidr>,
+    /// Require authentication for the health and readiness endpoints
+    authenticate_health: bool,
 }
 impl Args {
     /// Configure listenining with basic authentication
     pub fn listen_basic_auth(
-        listen_address: std::net::SocketAddr,
+        listen_address: impl Into<ListenAddress>,
+        basic_auth_keys_file: Option<std::path::PathBuf>,
+        max_bind_retries: u32,
+    ) -> Self {
+        Self::basic_auth(basic_auth_keys_file, max_bind_retries).add_listen_address(listen_address)
+    }
+    /// Configure basic authentication, without any listen address
+    ///
+    /// Add addresses using [`Self::add_listen_address`], or use listeners from socket activation
+    /// (see [`ServerBuilder::set_activated_listeners`])
+    pub fn basic_auth(
         basic_auth_keys_file: Option<std::path::PathBuf>,
         max_bind_retries: u32,
     ) -> Self {
         Self {
-            listen_address,
+            listen_addresses: vec![],
+            unix_socket_mode: 0o660,
             basic_auth_keys_file,
             max_bind_retries,
+            bearer_token_file: None,
+            watch_auth_files: false,
+            tls_files: None,
+            allow_cidrs: vec![],
+            trusted_proxies: vec![],
+            authenticate_health: false,
         }
     }
+    /// Configure listening on an additional address
+    pub fn add_listen_address(mut self, listen_address: impl Into<ListenAddress>) -> Self {
+        self.listen_addresses.push(listen_address.into());
+        self
+    }
+    /// Configure the permissions of Unix domain socket files (default `0o660`)
+    pub fn set_unix_socket_mode(mut self, mode: u32) -> Self {
+        self.unix_socket_mode = mode;
+        self
+    }
+    /// Configure bearer token authentication, using the tokens listed in the file
+    pub fn set_bearer_token_file(mut self, bearer_token_file: std::path::PathBuf) -> Self {
+        self.bearer_token_file = Some(bearer_token_file);
+        self
+    }
+    /// Configure reloading the authentication files when modified (checked per request)
+    pub fn set_watch_auth_files(mut self, watch: bool) -> Self {
+        self.watch_auth_files = watch;
+        self
+    }
+    /// Configure serving HTTPS using the specified certificate files
+    pub fn set_tls_files(mut self, tls_files: TlsFiles) -> Self {
+        self.tls_files = Some(tls_files);
+        self
+    }
+    /// Configure allowing only clients in the specified networks (checked before authentication)
+    pub fn set_allow_cidrs(mut self, allow_cidrs: Vec<Cidr>) -> Self {
+        self.allow_cidrs = allow_cidrs;
+        self
+    }
+    /// Configure the proxies trusted to report the client address in `X-Forwarded-For`
+    pub fn set_trusted_proxies(mut self, trusted_proxies: Vec<Cidr>) -> Self {
+        self.trusted_proxies = trusted_proxies;
+        self
+    }
+    /// Configure requiring authentication for the health and readiness endpoints (by default
+    /// public, like the root page)
+    pub fn set_authenticate_health(mut self, authenticate: bool) -> Self {
+        self.authenticate_health = authenticate;
+        self
+    }
+}
+
+/// Response of the metrics endpoint when collecting the metrics fails
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum FailureResponse {
+    /// HTTP 200 with `up` = 0, and the most recent successful metrics (if any)
+    #[default]
+    UpZero,
+    /// HTTP 503 with the error description
+    Unavailable,
+}
+
+/// Timing of running `zpool status`, relative to the metrics requests
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum CollectMode {
+    /// Run for every request
+    #[default]
+    OnDemand,
+    /// Reuse results newer than the interval, sharing one in-flight run between concurrent
+    /// requests
+    MinInterval(std::time::Duration),
+    /// Run periodically in a background thread, requests only read the latest results
+    Background(std::time::Duration),
 }
 
 /// Signal to cleanly terminate after finishing the current request (if any)
@@ -66,14 +188,25 @@ pub struct Shutdown;
 /// Signal that the server is ready to receive requests
 pub struct Ready;
 
+/// Signal to re-read the authentication files
+pub struct Reload;
+
 const TEMPLATE_ROOT_NAME: &str = "root";
 
 /// System local-time context for calculating durations
 #[must_use]
 pub struct AppContext {
     timezone: jiff::tz::TimeZone,
-    templates: TinyTemplate<'static>,
-    template_context: TemplateContext,
+    /// Rendered root page (the template is not `Sync`, so render only when the context changes)
+    root_html: String,
+    format_options: fmt::FormatOptions,
+    stats: stats::Stats,
+    failure_response: FailureResponse,
+    collect_mode: CollectMode,
+    collector: collector::Collector,
+    /// Maximum age of the most recent collection for the readiness endpoint (if any)
+    ready_max_age: Option<std::time::Duration>,
+    zpool_command: ZpoolCommand,
 }
 
 #[derive(serde::Serialize)]
@@ -81,6 +214,18 @@ struct TemplateContext {
     name_suffix: String,
 }
 
+fn render_root_html(template_context: &TemplateContext) -> String {
+    const ROOT_HTML: &str = include_str!("root.html");
+
+    let mut templates = TinyTemplate::new();
+    templates
+        .add_template(TEMPLATE_ROOT_NAME, ROOT_HTML)
+        .expect("root.html should be a valid template");
+    templates
+        .render(TEMPLATE_ROOT_NAME, template_context)
+        .expect("root.html template should render as valid")
+}
+
 impl Default for AppContext {
     fn default() -> Self {
         Self::new()
@@ -98,37 +243,74 @@ impl AppContext {
     }
 
     fn new_with_timezone(timezone: jiff::tz::TimeZone) -> Self {
-        const ROOT_HTML: &str = include_str!("root.html");
-
-        let mut templates = TinyTemplate::new();
-        templates
-            .add_template(TEMPLATE_ROOT_NAME, ROOT_HTML)
-            .expect("root.html should be a valid template");
-
         let template_context = TemplateContext {
             name_suffix: String::new(),
         };
 
         Self {
             timezone,
-            templates,
-            template_context,
+            root_html: render_root_html(&template_context),
+            format_options: fmt::FormatOptions::default(),
+            stats: stats::Stats::default(),
+            failure_response: FailureResponse::default(),
+            collect_mode: CollectMode::default(),
+            collector: collector::Collector::default(),
+            ready_max_age: None,
+            zpool_command: ZpoolCommand::default(),
         }
     }
 
     /// Sets the app version string for the root page
     pub fn set_app_version(&mut self, app_version: Option<&str>) {
-        if let Some(app_version) = app_version {
-            self.template_context.name_suffix = format!(" v{app_version}");
-        } else {
-            self.template_context.name_suffix.clear();
-        }
+        let name_suffix = app_version
+            .map(|app_version| format!(" v{app_version}"))
+            .unwrap_or_default();
+        self.root_html = render_root_html(&TemplateContext { name_suffix });
+    }
+
+    /// Sets the prefix for all metric names (default "zpool")
+    pub fn set_metric_prefix(&mut self, prefix: MetricPrefix) {
+        self.format_options.set_prefix(prefix);
+    }
+
+    /// Sets the labels added to every metric
+    ///
+    /// # Errors
+    /// Returns an error if a label name is repeated
+    pub fn set_const_labels(
+        &mut self,
+        const_labels: Vec<ConstLabel>,
+    ) -> Result<(), FormatOptionsError> {
+        self.format_options.set_const_labels(const_labels)
     }
 
-    fn render_root_html(&self) -> String {
-        self.templates
-            .render(TEMPLATE_ROOT_NAME, &self.template_context)
-            .expect("root.html template should render as valid")
+    /// Returns the Prometheus alerting rules (YAML) for the metrics, with the metric prefix and
+    /// matching the constant labels
+    #[must_use]
+    pub fn alerting_rules(&self, thresholds: &RuleThresholds) -> String {
+        fmt::rules::format_alerting_rules(&self.format_options, thresholds)
+    }
+
+    /// Sets the response of the metrics endpoint when collecting the metrics fails
+    pub fn set_failure_response(&mut self, failure_response: FailureResponse) {
+        self.failure_response = failure_response;
+    }
+
+    /// Sets when `zpool status` runs, relative to the metrics requests
+    pub fn set_collect_mode(&mut self, collect_mode: CollectMode) {
+        self.collect_mode = collect_mode;
+    }
+
+    /// Sets the path, wrapper and timeout for running `zpool status`
+    pub fn set_zpool_command(&mut self, zpool_command: ZpoolCommand) {
+        self.zpool_command = zpool_command;
+    }
+
+    /// Sets the maximum age of the most recent (successful) collection for the readiness endpoint
+    ///
+    /// Without a maximum age, only the result of the most recent collection is checked.
+    pub fn set_ready_max_age(&mut self, max_age: std::time::Duration) {
+        self.ready_max_age = Some(max_age);
     }
 
     /// Returns the current metrics as a string (no server)
@@ -142,32 +324,89 @@ impl AppContext {
 
 mod server {
     use crate::{
-        AppContext, Args, MetricsError, Ready, Shutdown,
-        auth::{self, AuthRules},
+        ActivatedListener, AppContext, Args, CollectMode, ListenAddress, MetricsError, Ready,
+        Reload, RunAs, Shutdown,
+        auth::{self, AuthFiles, RulesFile},
+        cidr::ClientFilter,
+        listen::Listener,
+        privileges, tls,
+        watchdog::{Heartbeat, Progress},
     };
-    use std::{net::SocketAddr, time::Duration};
+    use std::{
+        net::{SocketAddr, TcpListener},
+        os::unix::{
+            fs::FileTypeExt as _,
+            net::{UnixListener, UnixStream},
+        },
+        path::Path,
+        sync::{
+            Arc,
+            atomic::{AtomicBool, Ordering},
+            mpsc::RecvTimeoutError,
+        },
+        time::Duration,
+    };
+
+    /// Number of threads handling requests concurrently
+    const WORKER_COUNT: usize = 4;
 
     /// Calculate delay for exponential backoff
     pub(crate) fn calculate_delay_seconds(attempt: u32) -> u64 {
         (1u64 << (attempt - 1)).min(16) // 1, 2, 4, 8, 16, 16, 16... seconds (capped at 16)
     }
 
-    /// Start server with retry logic
-    pub(crate) fn start_server_with_retry(
+    /// Bind listener with retry logic
+    pub(crate) fn bind_with_retry(
         listen_address: SocketAddr,
         max_retries: u32,
-    ) -> Result<tiny_http::Server, Error> {
+    ) -> Result<TcpListener, Error> {
+        retry_bind(&ListenAddress::Tcp(listen_address), max_retries, || {
+            TcpListener::bind(listen_address)
+        })
+    }
+
+    /// Bind Unix domain socket listener with retry logic, setting the socket file `mode`
+    fn bind_unix_with_retry(
+        path: &Path,
+        mode: u32,
+        max_retries: u32,
+    ) -> Result<UnixListener, Error> {
+        retry_bind(&ListenAddress::Unix(path.to_owned()), max_retries, || {
+            // replace a stale socket file, left by a previous process that did not clean up
+            let is_socket = std::fs::symlink_metadata(path)
+                .is_ok_and(|metadata| metadata.file_type().is_socket());
+            if is_socket {
+                if UnixStream::connect(path).is_ok() {
+                    return Err(std::io::ErrorKind::AddrInUse.into());
+                }
+                std::fs::remove_file(path)?;
+            }
+            // create the socket file with the mode, rather than changing it after `bind` (when
+            // any local user could connect in between)
+            let umask = nix::sys::stat::Mode::from_bits_truncate(!mode & 0o777);
+            let previous_umask = nix::sys::stat::umask(umask);
+            let listener = UnixListener::bind(path);
+            nix::sys::stat::umask(previous_umask);
+            listener
+        })
+    }
+
+    fn retry_bind<T>(
+        listen_address: &ListenAddress,
+        max_retries: u32,
+        mut bind: impl FnMut() -> std::io::Result<T>,
+    ) -> Result<T, Error> {
         let mut attempt = 1;
         let mut retries_remaining = max_retries;
 
         loop {
             // Attempt connection
-            match tiny_http::Server::http(listen_address) {
-                Ok(server) => {
+            match bind() {
+                Ok(listener) => {
                     if attempt > 1 {
                         println!("Successfully bound to {listen_address} on attempt {attempt}");
                     }
-                    return Ok(server);
+                    return Ok(listener);
                 }
                 Err(e) => {
                     // Check retries remaining
@@ -175,8 +414,8 @@ mod server {
                         // Return error when exhausted
                         return Err(Error {
                             kind: ErrorKind::HttpServerBind {
-                                io_error: e,
-                                listen_address,
+                                io_error: e.into(),
+                                listen_address: listen_address.clone(),
                             },
                         });
                     }
@@ -200,6 +439,10 @@ mod server {
         args: &'a Args,
         ready_tx: Option<std::sync::mpsc::Sender<Ready>>,
         shutdown_rx: Option<std::sync::mpsc::Receiver<Shutdown>>,
+        reload_rx: Option<std::sync::mpsc::Receiver<Reload>>,
+        activated_listeners: Vec<ActivatedListener>,
+        heartbeat: Option<(std::sync::mpsc::Sender<Heartbeat>, Duration)>,
+        run_as: Option<RunAs>,
     }
 
     impl AppContext {
@@ -210,6 +453,10 @@ mod server {
                 args,
                 ready_tx: None,
                 shutdown_rx: None,
+                reload_rx: None,
+                activated_listeners: vec![],
+                heartbeat: None,
+                run_as: None,
             }
         }
     }
@@ -229,39 +476,84 @@ mod server {
             self
         }
 
-        /// Spawn a blocking HTTP server on the address specified by args
+        /// Sets the receiver for the [`Reload`] signal
+        pub fn set_reload_receiver(mut self, reload_rx: std::sync::mpsc::Receiver<Reload>) -> Self {
+            self.reload_rx = Some(reload_rx);
+            self
+        }
+
+        /// Sets the listeners passed by socket activation, served in addition to the addresses
+        /// specified by args
+        pub fn set_activated_listeners(mut self, listeners: Vec<ActivatedListener>) -> Self {
+            self.activated_listeners = listeners;
+            self
+        }
+
+        /// Sets the sender to receive a [`Heartbeat`] every interval while serving (e.g. for the
+        /// service manager watchdog)
+        pub fn set_heartbeat_sender(
+            mut self,
+            heartbeat_tx: std::sync::mpsc::Sender<Heartbeat>,
+            interval: Duration,
+        ) -> Self {
+            self.heartbeat = Some((heartbeat_tx, interval));
+            self
+        }
+
+        /// Sets the user and group to switch to after binding the listeners (and before running
+        /// `zpool status`)
+        ///
+        /// The authentication and TLS files are loaded before switching, but reloaded as the
+        /// user.
+        pub fn set_run_as(mut self, run_as: RunAs) -> Self {
+            self.run_as = Some(run_as);
+            self
+        }
+
+        /// Spawn a blocking HTTP server on the addresses specified by args, and the activated
+        /// listeners
+        ///
+        /// Readiness is signalled after binding all of the addresses.
         ///
         /// # Errors
         ///
         /// Returns an error for any of the following:
-        /// - binding the server fails
+        /// - no addresses or activated listeners are specified
+        /// - binding the server to any address fails
+        /// - switching to the user and group fails (only if set)
         /// - fail-fast metrics creation fails
         /// - shutdown receive fails (only if a `Receiver` was provided)
-        /// - loading the auth key file fails
+        /// - loading the auth key or bearer token file fails
+        /// - loading the TLS certificate files fails
         ///
         pub fn serve(self) -> Result<(), Error> {
             let Self {
                 app_context,
-                args:
-                    Args {
-                        listen_address,
-                        basic_auth_keys_file,
-                        max_bind_retries,
-                    },
+                args,
                 mut ready_tx,
-                mut shutdown_rx,
+                shutdown_rx,
+                reload_rx,
+                activated_listeners,
+                heartbeat,
+                run_as,
             } = self;
+            let Args {
+                tls_files,
+                allow_cidrs,
+                trusted_proxies,
+                ..
+            } = args;
 
             let make_error = |kind| Error { kind };
 
-            let auth_rules = basic_auth_keys_file
-                .as_ref()
-                .map(AuthRules::from_file)
-                .transpose()
-                .map_err(ErrorKind::AuthFile)
-                .map_err(make_error)?;
+            let auth_rules = Self::load_auth_rules(args)?;
 
-            let server = start_server_with_retry(*listen_address, *max_bind_retries)?;
+            let client_filter = ClientFilter::new(allow_cidrs, trusted_proxies);
+
+            let listening = Self::listen_all(activated_listeners, args)?;
+            if let Some(run_as) = &run_as {
+                Self::drop_privileges(run_as, &listening)?;
+            }
 
             // ensure fail-fast
             {
@@ -271,9 +563,21 @@ mod server {
                     .map_err(make_error)?;
             }
 
-            println!("Listening at http://{listen_address:?}");
+            Self::print_start_messages(&listening, run_as.as_ref(), allow_cidrs);
             if let Some(auth_rules) = &auth_rules {
-                auth_rules.print_start_message();
+                auth_rules
+                    .current()
+                    .print_start_message(tls_files.is_some());
+
+                if let Some(reload_rx) = reload_rx {
+                    let auth_rules = Arc::clone(auth_rules);
+                    // not joined, ends when the sender is dropped (or at process exit)
+                    std::thread::spawn(move || {
+                        for Reload in reload_rx {
+                            auth_rules.reload();
+                        }
+                    });
+                }
             }
 
             if let Some(ready_tx) = ready_tx.take() {
@@ -281,39 +585,351 @@ mod server {
                 let _ = ready_tx.send(Ready);
             }
 
-            while Self::check_shutdown(shutdown_rx.as_mut()).is_none() {
-                let response_result =
-                    Self::serve_next_peer(&server, app_context, auth_rules.as_ref());
+            let peers: Vec<_> = listening
+                .iter()
+                .map(|listening| Peers {
+                    server: &listening.server,
+                    auth_rules: auth_rules.as_deref(),
+                    tls_proxy: listening.tls_proxy.as_ref(),
+                    client_filter: client_filter.as_ref(),
+                    progress: &listening.progress,
+                    authenticate_health: args.authenticate_health,
+                })
+                .collect();
+            let stopping = AtomicBool::new(false);
+            let (stop_heartbeat_tx, stop_heartbeat_rx) = std::sync::mpsc::channel::<()>();
+            std::thread::scope(|scope| {
+                if let CollectMode::Background(interval) = app_context.collect_mode {
+                    scope.spawn(move || app_context.poll_until_stopped(interval));
+                }
+                if let Some((heartbeat_tx, interval)) = heartbeat {
+                    let peers = &peers;
+                    scope.spawn(move || {
+                        Self::send_heartbeats(
+                            app_context,
+                            peers,
+                            &heartbeat_tx,
+                            interval,
+                            &stop_heartbeat_rx,
+                        );
+                    });
+                }
+                for peers in &peers {
+                    if let Some(tls_proxy) = peers.tls_proxy {
+                        scope.spawn(|| tls_proxy.run());
+                    }
+                    for _ in 0..WORKER_COUNT {
+                        scope.spawn(|| Self::serve_peers(peers, app_context, &stopping));
+                    }
+                }
+
+                // without a receiver, serve forever
+                if let Some(shutdown_rx) = shutdown_rx {
+                    Self::wait_shutdown(&shutdown_rx);
+
+                    Self::stop_workers(&peers, &stopping);
+                    app_context.stop_polling();
+                    drop(stop_heartbeat_tx);
+                }
+            });
+            drop(peers);
+            for listening in listening {
+                listening.close();
+            }
+            Ok(())
+        }
+        fn load_auth_rules(args: &Args) -> Result<Option<Arc<RulesFile>>, Error> {
+            let auth_files = AuthFiles {
+                basic_auth_keys: args.basic_auth_keys_file.clone(),
+                bearer_tokens: args.bearer_token_file.clone(),
+            };
+            let auth_rules = (auth_files.basic_auth_keys.is_some()
+                || auth_files.bearer_tokens.is_some())
+            .then(|| RulesFile::load(auth_files, args.watch_auth_files))
+            .transpose()
+            .map_err(|error| Error {
+                kind: ErrorKind::AuthFile(error),
+            })?;
+            Ok(auth_rules.map(Arc::new))
+        }
+        fn listen_all(
+            activated_listeners: Vec<ActivatedListener>,
+            args: &Args,
+        ) -> Result<Vec<Listening>, Error> {
+            if args.listen_addresses.is_empty() && activated_listeners.is_empty() {
+                return Err(Error {
+                    kind: ErrorKind::NoListeners,
+                });
+            }
+            activated_listeners
+                .into_iter()
+                .map(|activated| Self::listen_activated(activated, args))
+                .chain(
+                    args.listen_addresses
+                        .iter()
+                        .map(|listen_address| Self::listen(listen_address, args)),
+                )
+                .collect()
+        }
+        fn print_start_messages(
+            listening: &[Listening],
+            run_as: Option<&RunAs>,
+            allow_cidrs: &[crate::Cidr],
+        ) {
+            for listening in listening {
+                listening.print_start_message();
+            }
+            if let Some(run_as) = run_as {
+                run_as.print_start_message();
+            }
+            if !allow_cidrs.is_empty() {
+                let count = allow_cidrs.len();
+                let plural = if count == 1 { "network" } else { "networks" };
+                println!("Client allow-list configured with {count} {plural}");
+            }
+        }
+        /// Switches to the user, keeping the bound Unix domain socket files usable
+        fn drop_privileges(run_as: &RunAs, listening: &[Listening]) -> Result<(), Error> {
+            let make_error = |kind| Error { kind };
+
+            for listening in listening {
+                if let (ListenAddress::Unix(path), Source::Bound) =
+                    (&listening.listen_address, &listening.source)
+                {
+                    run_as
+                        .chown(path)
+                        .map_err(ErrorKind::DropPrivileges)
+                        .map_err(make_error)?;
+                }
+            }
+            run_as
+                .apply()
+                .map_err(ErrorKind::DropPrivileges)
+                .map_err(make_error)
+        }
+        /// Binds the server to the address (Unix domain sockets are served without TLS)
+        fn listen(listen_address: &ListenAddress, args: &Args) -> Result<Listening, Error> {
+            let (server, tls_proxy) = match listen_address {
+                ListenAddress::Tcp(address) => {
+                    let listener = bind_with_retry(*address, args.max_bind_retries)?;
+                    Self::http_server_with_tls(listener, listen_address, args.tls_files.as_ref())?
+                }
+                ListenAddress::Unix(path) => {
+                    let listener =
+                        bind_unix_with_retry(path, args.unix_socket_mode, args.max_bind_retries)?;
+                    (Self::http_server(listener, listen_address)?, None)
+                }
+            };
+            Ok(Listening {
+                listen_address: listen_address.clone(),
+                server,
+                tls_proxy,
+                source: Source::Bound,
+                progress: Progress::new(WORKER_COUNT),
+            })
+        }
+        /// Serves on the listener from socket activation (Unix domain sockets without TLS)
+        fn listen_activated(activated: ActivatedListener, args: &Args) -> Result<Listening, Error> {
+            let (listener, name) = activated.into_parts();
+            let (listen_address, server, tls_proxy) = match listener {
+                Listener::Tcp(listener) => {
+                    let listen_address = ListenAddress::Tcp(
+                        listener
+                            .local_addr()
+                            .unwrap_or_else(|_| SocketAddr::from(([0, 0, 0, 0], 0))),
+                    );
+                    let (server, tls_proxy) = Self::http_server_with_tls(
+                        listener,
+                        &listen_address,
+                        args.tls_files.as_ref(),
+                    )?;
+                    (listen_address, server, tls_proxy)
+                }
+                Listener::Unix(listener) => {
+                    let listen_address = ListenAddress::Unix(
+                        listener
+                            .local_addr()
+                            .ok()
+                            .and_then(|address| address.as_pathname().map(Path::to_owned))
+                            .unwrap_or_default(),
+                    );
+                    let server = Self::http_server(listener, &listen_address)?;
+                    (listen_address, server, None)
+                }
+            };
+            Ok(Listening {
+                listen_address,
+                server,
+                tls_proxy,
+                source: Source::Activated { name },
+                progress: Progress::new(WORKER_COUNT),
+            })
+        }
+        /// Returns the HTTP server, behind a TLS proxy if `tls_files` are specified
+        fn http_server_with_tls(
+            listener: TcpListener,
+            listen_address: &ListenAddress,
+            tls_files: Option<&tls::Files>,
+        ) -> Result<(tiny_http::Server, Option<tls::Proxy>), Error> {
+            let make_error = |kind| Error { kind };
+
+            let Some(tls_files) = tls_files else {
+                return Ok((Self::http_server(listener, listen_address)?, None));
+            };
+            // TLS is terminated by the proxy, forwarding to the server on an internal address
+            let internal_address = SocketAddr::from(([127, 0, 0, 1], 0));
+            let internal_listener = bind_with_retry(internal_address, 0)?;
+            let internal_address = internal_listener
+                .local_addr()
+                .map_err(|io_error| ErrorKind::HttpServerBind {
+                    io_error: io_error.into(),
+                    listen_address: internal_address.into(),
+                })
+                .map_err(make_error)?;
+            let tls_proxy = tls::Proxy::new(listener, internal_address, tls_files.clone())
+                .map_err(ErrorKind::TlsFiles)
+                .map_err(make_error)?;
+            let internal_server = Self::http_server(internal_listener, &internal_address.into())?;
+            Ok((internal_server, Some(tls_proxy)))
+        }
+        fn http_server(
+            listener: impl Into<tiny_http::Listener>,
+            listen_address: &ListenAddress,
+        ) -> Result<tiny_http::Server, Error> {
+            tiny_http::Server::from_listener(listener, None).map_err(|io_error| Error {
+                kind: ErrorKind::HttpServerBind {
+                    io_error,
+                    listen_address: listen_address.clone(),
+                },
+            })
+        }
+        /// Serves requests in the current thread, until `stopping` is set
+        fn serve_peers(peers: &Peers<'_>, app_context: &AppContext, stopping: &AtomicBool) {
+            loop {
+                let response_result = Self::serve_next_peer(peers, app_context);
+                if stopping.load(Ordering::SeqCst) {
+                    break;
+                }
                 if let Err(error) = response_result {
                     eprintln!("failed to send response: {error}");
                     // TODO log error to console, cannot shutdown server for peer errors
                 }
             }
-            Ok(())
         }
-        fn check_shutdown(
-            shutdown_rx: Option<&mut std::sync::mpsc::Receiver<Shutdown>>,
-        ) -> Option<Shutdown> {
-            shutdown_rx
-                .map(|rx| rx.try_recv())
-                .transpose()
-                .unwrap_or_else(|err| {
-                    use std::sync::mpsc::TryRecvError as E;
-                    match err {
-                        E::Disconnected => {
-                            eprintln!("termination channel receive failure");
-                            Some(Shutdown)
-                            // TODO log an error to the console
-                        }
-                        E::Empty => {
-                            // no shutdown signaled, yet
-                            None
-                        }
-                    }
-                })
+        /// Signals the workers (and TLS proxies) to stop, after finishing any pending requests
+        fn stop_workers(peers: &[Peers<'_>], stopping: &AtomicBool) {
+            stopping.store(true, Ordering::SeqCst);
+            for peers in peers {
+                // queued after any pending requests, one for each worker
+                for _ in 0..WORKER_COUNT {
+                    peers.server.unblock();
+                }
+                if let Some(tls_proxy) = peers.tls_proxy {
+                    tls_proxy.stop();
+                }
+            }
+        }
+        /// Sends a [`Heartbeat`] every interval, until the stop sender is dropped
+        fn send_heartbeats(
+            app_context: &AppContext,
+            peers: &[Peers<'_>],
+            heartbeat_tx: &std::sync::mpsc::Sender<Heartbeat>,
+            interval: Duration,
+            stop_rx: &std::sync::mpsc::Receiver<()>,
+        ) {
+            let mut was_alive = true;
+            loop {
+                let heartbeat = app_context.heartbeat(peers.iter().map(|peers| peers.progress));
+                if was_alive && !heartbeat.is_alive() {
+                    eprintln!("withholding watchdog keepalive: {}", heartbeat.status());
+                }
+                was_alive = heartbeat.is_alive();
+                if heartbeat_tx.send(heartbeat).is_err() {
+                    break;
+                }
+                match stop_rx.recv_timeout(interval) {
+                    Err(RecvTimeoutError::Timeout) => {}
+                    Ok(()) | Err(RecvTimeoutError::Disconnected) => break,
+                }
+            }
+        }
+        fn wait_shutdown(shutdown_rx: &std::sync::mpsc::Receiver<Shutdown>) {
+            if shutdown_rx.recv().is_err() {
+                eprintln!("termination channel receive failure");
+                // TODO log an error to the console
+            }
         }
     }
 
+    /// Server bound to one of the listen addresses (or an activated listener)
+    struct Listening {
+        listen_address: ListenAddress,
+        server: tiny_http::Server,
+        /// TLS proxy forwarding to the `server` (if any)
+        tls_proxy: Option<tls::Proxy>,
+        source: Source,
+        /// Requests being handled by the workers
+        progress: Progress,
+    }
+    enum Source {
+        /// Bound to one of the listen addresses
+        Bound,
+        /// Passed by socket activation, with the name of the listener (if any)
+        Activated { name: Option<String> },
+    }
+    impl Listening {
+        fn print_start_message(&self) {
+            let Self {
+                listen_address,
+                server: _,
+                tls_proxy,
+                source,
+                progress: _,
+            } = self;
+            let activated = match source {
+                Source::Bound => String::new(),
+                Source::Activated { name: None } => " (socket activated)".to_owned(),
+                Source::Activated { name: Some(name) } => {
+                    format!(" (socket activated, {name:?})")
+                }
+            };
+            match listen_address {
+                ListenAddress::Tcp(address) => {
+                    let scheme = if tls_proxy.is_some() { "https" } else { "http" };
+                    println!("Listening at {scheme}://{address:?}{activated}");
+                }
+                ListenAddress::Unix(_) => println!("Listening at {listen_address}{activated}"),
+            }
+        }
+        fn close(self) {
+            let Self {
+                listen_address,
+                server,
+                tls_proxy: _,
+                source,
+                progress: _,
+            } = self;
+            if let (ListenAddress::Unix(_), Source::Activated { .. }) = (listen_address, source) {
+                // keep the socket file for the service manager (removed when the server drops)
+                std::mem::forget(server);
+            }
+        }
+    }
+
+    /// Sources of requests for the workers
+    pub(crate) struct Peers<'a> {
+        pub server: &'a tiny_http::Server,
+        pub auth_rules: Option<&'a RulesFile>,
+        /// TLS proxy forwarding to the `server` (if any)
+        pub tls_proxy: Option<&'a tls::Proxy>,
+        /// Allowed client networks (if any)
+        pub client_filter: Option<&'a ClientFilter>,
+        /// Requests being handled by the workers
+        pub progress: &'a Progress,
+        /// Require authentication for the health and readiness endpoints
+        pub authenticate_health: bool,
+    }
+
     /// Error establishing the server
     #[derive(Debug)]
     pub struct Error {
@@ -323,17 +939,23 @@ mod server {
     #[derive(Debug)]
     enum ErrorKind {
         AuthFile(auth::FileError),
+        TlsFiles(tls::ConfigError),
         Metrics(MetricsError),
+        DropPrivileges(privileges::Error),
+        NoListeners,
         HttpServerBind {
             io_error: Box<dyn std::error::Error + Send + Sync>,
-            listen_address: SocketAddr,
+            listen_address: ListenAddress,
         },
     }
     impl std::error::Error for Error {
         fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
             match &self.kind {
                 ErrorKind::AuthFile(error) => Some(error),
+                ErrorKind::TlsFiles(error) => Some(error),
                 ErrorKind::Metrics(error) => Some(error),
+                ErrorKind::DropPrivileges(error) => Some(error),
+                ErrorKind::NoListeners => None,
                 ErrorKind::HttpServerBind { io_error, .. } => Some(&**io_error),
             }
         }
@@ -343,7 +965,15 @@ mod server {
             let Self { kind } = self;
             match kind {
                 ErrorKind::AuthFile(_error) => write!(f, "invalid auth file"),
+                ErrorKind::TlsFiles(_error) => write!(f, "invalid TLS certificate files"),
                 ErrorKind::Metrics(_error) => write!(f, "failed to create metrics"),
+                ErrorKind::DropPrivileges(_error) => write!(f, "failed to drop privileges"),
+                ErrorKind::NoListeners => {
+                    write!(
+                        f,
+                        "no listen addresses, and no listeners from socket activation"
+                    )
+                }
                 ErrorKind::HttpServerBind {
                     io_error: _,
                     listen_address,
@@ -355,10 +985,14 @@ mod server {
 
 mod respond {
     use crate::{
-        AppContext, MetricsError, ServerBuilder, Timestamp,
-        auth::{self, AuthResult, AuthRules, DebugUserStringRef},
+        AppContext, FailureResponse, MetricsError, ServerBuilder, Timestamp,
+        auth::{self, AuthResult, DebugUserStringRef},
+        cidr::ClientFilter,
+        collector::Collection,
+        fmt::{self, CollectionStatus},
+        metrics::error_messages,
+        server::Peers,
     };
-    use std::time::Duration;
 
     pub(super) const HTTP_BAD_REQUEST: (u32, &str) = (400, "Bad Request");
     pub(super) const HTTP_UNAUTHORIZED: (u32, &str) = (401, "Unauthorized");
@@ -367,12 +1001,12 @@ mod respond {
     pub(super) fn respond_code(
         request: tiny_http::Request,
         code_label: (u32, &'static str),
-        header: Option<tiny_http::Header>,
+        headers: impl IntoIterator<Item = tiny_http::Header>,
     ) -> Result<(), Error> {
         let (code, label) = code_label;
         let mut response = tiny_http::Response::from_string(label).with_status_code(code);
 
-        if let Some(header) = header {
+        for header in headers {
             response = response.with_header(header);
         }
 
@@ -383,35 +1017,83 @@ mod respond {
 
     impl ServerBuilder<'_> {
         pub(super) fn serve_next_peer(
-            server: &tiny_http::Server,
+            peers: &Peers<'_>,
             app_context: &AppContext,
-            auth_rules: Option<&AuthRules>,
         ) -> Result<(), Error> {
-            const RECV_TIMEOUT: Duration = Duration::from_millis(100);
-            const RECV_SLEEP: Duration = Duration::from_millis(10);
+            let Peers {
+                server,
+                auth_rules,
+                tls_proxy,
+                client_filter,
+                progress,
+                authenticate_health,
+            } = *peers;
+
+            // blocks until a request arrives, or the server is unblocked (error)
+            let request = server.recv().map_err(|io_error| Error {
+                io_error,
+                kind: ErrorKind::PeerReceive,
+            })?;
+            let _progress = progress.start();
+
+            let mut peer_addr = request.remote_addr().copied();
+            if let Some(tls_proxy) = tls_proxy {
+                let forwarded = peer_addr.and_then(|remote_addr| tls_proxy.peer_addr(remote_addr));
+                if forwarded.is_none() {
+                    println!("denied request to the internal address, not from the TLS listener");
+                    return respond_code(request, HTTP_FORBIDDEN, []);
+                }
+                peer_addr = forwarded;
+            }
 
-            if let Some(request) = server
-                .recv_timeout(RECV_TIMEOUT)
-                .map_err(|io_error| Error {
-                    io_error,
-                    kind: ErrorKind::PeerReceive,
-                })?
+            // Unix domain socket peers have no address, limited by the socket file permissions
+            if let Some(client_filter) = client_filter
+                && let Some(peer_addr) = peer_addr
+                && let Err(who) = Self::check_client(client_filter, peer_addr, &request)
             {
-                let auth_result = auth_rules.map_or(Ok(AuthResult::NoneConfigured), |auth_rules| {
-                    auth_rules.query(&request)
-                });
-                match auth_result {
-                    Ok(auth_result) => app_context
-                        .timestamp_now()
-                        .handle_request(request, auth_result),
-                    Err(err) => {
-                        println!("{err}");
-                        respond_code(request, HTTP_BAD_REQUEST, None)
-                    }
+                println!(
+                    "denied access for {who} to url {url}",
+                    url = DebugUserStringRef::from(request.url())
+                );
+                return respond_code(request, HTTP_FORBIDDEN, []);
+            }
+
+            let auth_result = auth_rules.map_or(Ok(AuthResult::NoneConfigured), |auth_rules| {
+                auth_rules.current().query(&request)
+            });
+            match auth_result {
+                Ok(auth_result) => app_context.timestamp_now().handle_request(
+                    request,
+                    auth_result,
+                    authenticate_health,
+                ),
+                Err(err) => {
+                    println!("{err}");
+                    respond_code(request, HTTP_BAD_REQUEST, [])
                 }
-            } else {
-                std::thread::sleep(RECV_SLEEP);
-                Ok(())
+            }
+        }
+    }
+
+    impl ServerBuilder<'_> {
+        /// Returns a description of the client, if not allowed
+        fn check_client(
+            client_filter: &ClientFilter,
+            peer_addr: std::net::SocketAddr,
+            request: &tiny_http::Request,
+        ) -> Result<(), String> {
+            let forwarded_for = request
+                .headers()
+                .iter()
+                .filter(|header| header.field.equiv("X-Forwarded-For"))
+                .map(|header| header.value.as_str());
+            match client_filter.client_address(peer_addr.ip(), forwarded_for) {
+                Ok(client) if client_filter.is_allowed(client) => Ok(()),
+                Ok(client) => Err(format!("client {client}")),
+                Err(invalid) => Err(format!(
+                    "client with X-Forwarded-For {}",
+                    DebugUserStringRef::from(invalid)
+                )),
             }
         }
     }
@@ -421,30 +1103,47 @@ mod respond {
             self,
             request: tiny_http::Request,
             auth: AuthResult,
+            authenticate_health: bool,
         ) -> Result<(), Error> {
             const ENDPOINT_METRICS: &str = "/metrics";
+            const ENDPOINT_POOLS_JSON: &str = "/api/v1/pools";
             const ENDPOINT_ROOT: &str = "/";
 
             let url = request.url();
+            let health_endpoint = HealthEndpoint::from_url(url);
             if url == ENDPOINT_ROOT {
                 let response = self.get_public_root_response();
                 request.respond(response).map_err(Endpoint::Root.error_fn())
+            } else if let Some(health_endpoint) = health_endpoint
+                && !authenticate_health
+            {
+                self.respond_health(request, health_endpoint)
             } else {
                 match auth {
-                    AuthResult::MissingAuthHeader => respond_code(
-                        request,
-                        HTTP_UNAUTHORIZED,
-                        Some(auth::get_header_www_authenticate()),
-                    ),
+                    AuthResult::MissingAuthHeader(challenge) => {
+                        respond_code(request, HTTP_UNAUTHORIZED, challenge.headers())
+                    }
+                    AuthResult::InvalidToken(who) => {
+                        println!(
+                            "denied access for {who} to url {url}",
+                            url = DebugUserStringRef::from(url)
+                        );
+                        respond_code(
+                            request,
+                            HTTP_UNAUTHORIZED,
+                            [auth::Challenge::header_invalid_token()],
+                        )
+                    }
                     AuthResult::Deny(who) => {
                         println!(
                             "denied access for {who} to url {url}",
                             url = DebugUserStringRef::from(url)
                         );
-                        respond_code(request, HTTP_FORBIDDEN, None)
+                        respond_code(request, HTTP_FORBIDDEN, [])
                     }
                     AuthResult::Accept | AuthResult::NoneConfigured => {
                         if url == ENDPOINT_METRICS {
+                            self.app_context.stats.record_scrape();
                             let (response, metrics_result) = self.get_metrics_response();
                             if let Err(err) = metrics_result {
                                 eprintln!("failed to get metrics: {err}");
@@ -453,43 +1152,171 @@ mod respond {
                             request
                                 .respond(response)
                                 .map_err(Endpoint::Metrics.error_fn())
+                        } else if let Some(health_endpoint) = health_endpoint {
+                            self.respond_health(request, health_endpoint)
+                        } else if url == ENDPOINT_POOLS_JSON {
+                            let (response, json_result) = self.get_pools_json_response();
+                            if let Err(err) = json_result {
+                                eprintln!("failed to get pools json: {err}");
+                            }
+                            request
+                                .respond(response)
+                                .map_err(Endpoint::PoolsJson.error_fn())
                         } else {
-                            respond_code(request, HTTP_NOT_FOUND, None)
+                            respond_code(request, HTTP_NOT_FOUND, [])
                         }
                     }
                 }
             }
         }
+        /// Responds with 200 if healthy (or ready), otherwise 503 with the reason
+        ///
+        /// Both are cheap checks of the stored state, never running `zpool status`.
+        fn respond_health(
+            &self,
+            request: tiny_http::Request,
+            health_endpoint: HealthEndpoint,
+        ) -> Result<(), Error> {
+            const HTTP_OK: u32 = 200;
+            const HTTP_SERVICE_UNAVAILABLE: u32 = 503;
+
+            let app_context = self.app_context;
+            let (result, endpoint) = match health_endpoint {
+                HealthEndpoint::Healthy => {
+                    let heartbeat = app_context.heartbeat([]);
+                    let result = if heartbeat.is_alive() {
+                        Ok(())
+                    } else {
+                        Err(heartbeat.status().to_owned())
+                    };
+                    (result, Endpoint::Healthy)
+                }
+                HealthEndpoint::Ready => (app_context.check_ready(), Endpoint::Ready),
+            };
+            let (status_code, body) = match result {
+                Ok(()) => (HTTP_OK, "OK".to_owned()),
+                Err(reason) => (HTTP_SERVICE_UNAVAILABLE, reason),
+            };
+            let response = tiny_http::Response::from_string(body).with_status_code(status_code);
+            request.respond(response).map_err(endpoint.error_fn())
+        }
         fn get_public_root_response(&self) -> tiny_http::Response<impl std::io::Read> {
-            let root_html = self.app_context.render_root_html();
+            let root_html = self.app_context.root_html.clone();
 
             tiny_http::Response::from_string(root_html).with_header(
                 tiny_http::Header::from_bytes(&b"Content-Type"[..], &b"text/html"[..])
                     .expect("valid hard-coded content type header"),
             )
         }
-        // Infallible, returns commented error response on failure
+        // Infallible, returns commented error response on failure (see `FailureResponse`)
         pub(crate) fn get_metrics_response(
             &self,
         ) -> (
             tiny_http::Response<impl std::io::Read>,
             Result<(), MetricsError>,
         ) {
-            use std::fmt::Write as _;
+            const HTTP_OK: u32 = 200;
+            const HTTP_SERVICE_UNAVAILABLE: u32 = 503;
+
+            let app_context = self.app_context;
+            let format_exporter_metrics = |status| {
+                fmt::format_exporter_metrics(
+                    &app_context.format_options,
+                    &app_context.stats.snapshot(),
+                    &status,
+                )
+            };
 
-            let (response_str, metrics_result) = match self.get_metrics_str() {
-                Ok(metrics_str) => (metrics_str, Ok(())),
+            let Collection {
+                latest,
+                last_success,
+            } = app_context.collection();
+
+            let (status_code, response_str, metrics_result) = match latest {
+                Ok(snapshot) => {
+                    let mut metrics_str = self.format_metrics(&snapshot.pools);
+                    metrics_str.push_str(&format_exporter_metrics(CollectionStatus {
+                        up: true,
+                        stale: false,
+                        collected_at: Some(snapshot.collected_at),
+                    }));
+                    (HTTP_OK, metrics_str, Ok(()))
+                }
                 Err(err) => {
-                    let mut response_str = "# ERROR:".to_owned();
-                    for line in err.to_string().lines() {
-                        write!(&mut response_str, "\n# {line}").expect("string write infallible");
-                    }
-                    (response_str, Err(err))
+                    let err = MetricsError::from(err);
+                    let mut response_str = err.format_comments();
+
+                    let status_code = match app_context.failure_response {
+                        FailureResponse::UpZero => {
+                            if let Some(last_success) = &last_success {
+                                response_str.push_str(
+                                    "# STALE: metrics below are from the last successful collection\n",
+                                );
+                                response_str.push_str(&self.format_metrics(&last_success.pools));
+                            }
+                            response_str.push_str(&format_exporter_metrics(CollectionStatus {
+                                up: false,
+                                stale: last_success.is_some(),
+                                collected_at: last_success
+                                    .as_ref()
+                                    .map(|last_success| last_success.collected_at),
+                            }));
+                            HTTP_OK
+                        }
+                        FailureResponse::Unavailable => HTTP_SERVICE_UNAVAILABLE,
+                    };
+                    (status_code, response_str, Err(err))
                 }
             };
-            let response = tiny_http::Response::from_string(response_str);
+            let response =
+                tiny_http::Response::from_string(response_str).with_status_code(status_code);
             (response, metrics_result)
         }
+        // Infallible, returns JSON error object (with status 500) on failure
+        fn get_pools_json_response(
+            &self,
+        ) -> (
+            tiny_http::Response<impl std::io::Read>,
+            Result<(), MetricsError>,
+        ) {
+            const HTTP_INTERNAL_SERVER_ERROR: u32 = 500;
+
+            let (response, json_result) = match self.app_context.get_pools_json_now() {
+                Ok(json_str) => (tiny_http::Response::from_string(json_str), Ok(())),
+                Err(err) => {
+                    let error_json = serde_json::json!({
+                        "error": error_messages(&err),
+                        "code": err.code(),
+                    });
+                    let response = tiny_http::Response::from_string(error_json.to_string())
+                        .with_status_code(HTTP_INTERNAL_SERVER_ERROR);
+                    (response, Err(err))
+                }
+            };
+            let response = response.with_header(
+                tiny_http::Header::from_bytes(&b"Content-Type"[..], &b"application/json"[..])
+                    .expect("valid hard-coded content type header"),
+            );
+            (response, json_result)
+        }
+    }
+
+    /// Cheap liveness and readiness checks, e.g. for load balancers and container health checks
+    #[derive(Clone, Copy)]
+    enum HealthEndpoint {
+        /// The process is alive (the collection is not stalled)
+        Healthy,
+        /// The most recent collection succeeded (recently, if a maximum age is set)
+        Ready,
+    }
+    impl HealthEndpoint {
+        fn from_url(url: &str) -> Option<Self> {
+            match url {
+                "/-/healthy" => Some(Self::Healthy),
+                "/-/ready" => Some(Self::Ready),
+                _ => None,
+            }
+        }
     }
 
     #[derive(Debug)]
@@ -515,6 +1342,9 @@ mod respond {
         Code((u32, &'static str)),
         Root,
         Metrics,
+        PoolsJson,
+        Healthy,
+        Ready,
     }
     impl std::error::Error for Error {
         fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
@@ -532,6 +1362,9 @@ mod respond {
                         Endpoint::Code((code, label)) => write!(f, "code {code} {label}"),
                         Endpoint::Root => write!(f, "root endpoint"),
                         Endpoint::Metrics => write!(f, "metrics endpoint"),
+                        Endpoint::PoolsJson => write!(f, "pools json endpoint"),
+                        Endpoint::Healthy => write!(f, "health endpoint"),
+                        Endpoint::Ready => write!(f, "readiness endpoint"),
                     }
                 }
             }
@@ -581,23 +1414,52 @@ pub struct Timestamp<'a> {
     compute_time_start: Option<Instant>,
 }
 mod metrics {
-    use crate::{Timestamp, ZfsParseError, exec, fmt};
+    use crate::{
+        AppContext, Timestamp, ZfsParseError, exec, fmt, json, stats::CollectorErrorKind,
+        zfs::PoolMetrics,
+    };
+    use std::{sync::Arc, time::Instant};
 
-    impl Timestamp<'_> {
-        pub(crate) fn get_metrics_str(&self) -> Result<String, Error> {
-            let make_error = |kind| Error { kind };
+    /// Returns the non-empty output of the `zpool status` command
+    fn get_zpool_output(zpool_command: &exec::ZpoolCommand) -> Result<String, Error> {
+        let make_error = |kind| Error { kind };
 
-            let zpool_output = exec::zpool_status()
-                .map_err(ErrorKind::Exec)
-                .map_err(make_error)?;
+        let zpool_output = zpool_command
+            .zpool_status()
+            .map_err(ErrorKind::Exec)
+            .map_err(make_error)?;
 
-            if zpool_output.is_empty() {
-                Err(make_error(ErrorKind::EmptyOutput))
-            } else {
-                self.get_metrics_for_output(&zpool_output)
+        if zpool_output.is_empty() {
+            Err(make_error(ErrorKind::EmptyOutput))
+        } else {
+            Ok(zpool_output)
+        }
+    }
+
+    impl AppContext {
+        /// Runs the `zpool status` command and parses the output, recording the collector stats
+        pub(crate) fn collect_pools(&self) -> Result<Vec<PoolMetrics>, Error> {
+            let start_time = Instant::now();
+
+            let result = get_zpool_output(&self.zpool_command).and_then(|zpool_output| {
+                self.parse_zfs_metrics(&zpool_output)
                     .map_err(ErrorKind::ZfsParse)
-                    .map_err(make_error)
-            }
+                    .map_err(|kind| Error { kind })
+            });
+
+            let error_kind = result.as_ref().err().map(Error::collector_error_kind);
+            self.stats
+                .record_collection(start_time.elapsed(), error_kind);
+
+            result
+        }
+    }
+
+    impl Timestamp<'_> {
+        pub(crate) fn get_metrics_str(&self) -> Result<String, Error> {
+            let snapshot = self.app_context.collect_now().latest?;
+
+            Ok(self.format_metrics(&snapshot.pools))
         }
 
         /// Parses the `zpool_output` string and returns a formatted Prometheus-style metrics document
@@ -607,14 +1469,66 @@ mod metrics {
         pub fn get_metrics_for_output(&self, zpool_output: &str) -> Result<String, ZfsParseError> {
             let zpool_metrics = self.app_context.parse_zfs_metrics(zpool_output)?;
 
-            Ok(fmt::format_metrics(
+            Ok(self.format_metrics(&zpool_metrics))
+        }
+
+        pub(crate) fn format_metrics(&self, zpool_metrics: &[PoolMetrics]) -> String {
+            fmt::format_metrics(
                 zpool_metrics,
+                &self.app_context.format_options,
                 &self.datetime,
                 self.compute_time_start,
-            ))
+            )
         }
     }
 
+    impl AppContext {
+        /// Returns the current metrics followed by the `up` metric, or the error description and
+        /// `up` = 0 (for outputs without the server statistics)
+        pub(crate) fn get_metrics_document(&self) -> (String, Result<(), Error>) {
+            let (mut document, result) = match self.get_metrics_now() {
+                Ok(metrics) => (metrics, Ok(())),
+                Err(err) => (err.format_comments(), Err(err)),
+            };
+            document.push_str(&fmt::format_up_metric(&self.format_options, result.is_ok()));
+            (document, result)
+        }
+
+        /// Returns the current pool tree as a JSON document (no server)
+        ///
+        /// # Errors
+        /// Returns an error if the command execution fails, the output is non-utf8, or parsing fails
+        pub fn get_pools_json_now(&self) -> Result<String, Error> {
+            let snapshot = self.collection().latest?;
+
+            Ok(json::format_pools_json(&snapshot.pools))
+        }
+
+        /// Parses the `zpool_output` string and returns the pool tree as a JSON document
+        ///
+        /// # Errors
+        /// Returns errors when parsing ZFS metrics fails
+        pub fn get_pools_json_for_output(
+            &self,
+            zpool_output: &str,
+        ) -> Result<String, ZfsParseError> {
+            let zpool_metrics = self.parse_zfs_metrics(zpool_output)?;
+
+            Ok(json::format_pools_json(&zpool_metrics))
+        }
+    }
+
+    /// Returns the display message of the error, followed by each source
+    pub(crate) fn error_messages(error: &dyn std::error::Error) -> Vec<String> {
+        let mut messages = vec![error.to_string()];
+        let mut source = error.source();
+        while let Some(error) = source {
+            messages.push(error.to_string());
+            source = error.source();
+        }
+        messages
+    }
+
     /// Error obtaining zpool status metrics from the system
     #[derive(Debug)]
     pub struct Error {
@@ -625,6 +1539,15 @@ mod metrics {
         Exec(exec::Error),
         EmptyOutput,
         ZfsParse(ZfsParseError),
+        /// Error from a stored collection (transparent)
+        Shared(Arc<Error>),
+    }
+    impl From<Arc<Error>> for Error {
+        fn from(error: Arc<Error>) -> Self {
+            Self {
+                kind: ErrorKind::Shared(error),
+            }
+        }
     }
     impl std::error::Error for Error {
         fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
@@ -632,6 +1555,38 @@ mod metrics {
                 ErrorKind::Exec(error) => Some(error),
                 ErrorKind::EmptyOutput => None,
                 ErrorKind::ZfsParse(error) => Some(error),
+                ErrorKind::Shared(error) => error.source(),
+            }
+        }
+    }
+    impl Error {
+        /// Returns a stable identifier for the cause of the error, e.g. `"timeout"`
+        ///
+        /// Intended for machine consumption, unlike the `Display` message which may change
+        #[must_use]
+        pub fn code(&self) -> &'static str {
+            self.collector_error_kind().label()
+        }
+        /// Returns the error messages and code as comments, for a metrics document
+        pub(crate) fn format_comments(&self) -> String {
+            use std::fmt::Write as _;
+
+            let mut comments = "# ERROR:".to_owned();
+            for message in error_messages(self) {
+                for line in message.lines() {
+                    write!(&mut comments, "\n# {line}").expect("string write infallible");
+                }
+            }
+            writeln!(&mut comments, "\n# ERROR_CODE: {code}", code = self.code())
+                .expect("string write infallible");
+            comments
+        }
+        fn collector_error_kind(&self) -> CollectorErrorKind {
+            match &self.kind {
+                ErrorKind::Exec(error) => error.collector_error_kind(),
+                ErrorKind::EmptyOutput => CollectorErrorKind::EmptyOutput,
+                ErrorKind::ZfsParse(_) => CollectorErrorKind::ZfsParse,
+                ErrorKind::Shared(error) => error.collector_error_kind(),
             }
         }
     }
@@ -639,6 +1594,7 @@ mod metrics {
         fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
             let Self { kind } = self;
             let description = match kind {
+                ErrorKind::Shared(error) => return write!(f, "{error}"),
                 ErrorKind::Exec(_error) => "failed to execute",
                 ErrorKind::EmptyOutput => "empty output from",
                 ErrorKind::ZfsParse(_error) => "failed to parse output from",
@@ -651,30 +1607,119 @@ mod metrics {
 mod exec {
     //! I/O portion of executing status commands
 
+    use crate::stats::CollectorErrorKind;
     use std::{
+        ffi::OsString,
+        path::PathBuf,
         process::{Command, Output, Stdio},
         time::{Duration, Instant},
     };
 
-    /// Returns the output of the `zpool status` command
-    ///
-    /// # Errors
-    /// Returns an error if the command execution fails, or the output is non-utf8
-    pub fn zpool_status() -> Result<String, Error> {
-        // NOTE: "-p" for parsable (exact) values in the device table
-        const ARGS: &[&str] = &["status", "-p"];
-
-        run_command("/sbin/zpool", ARGS).or_else(|err| {
-            if err.is_spawn_error() {
-                run_command("zpool", ARGS)
-            } else {
-                Err(err)
+    /// Paths tried in order when no path is set (falling back only if the process fails to spawn)
+    const DEFAULT_PATHS: [&str; 2] = ["/sbin/zpool", "zpool"];
+
+    /// Location, wrapper and timeout for running the `zpool` command
+    #[derive(Clone, Debug)]
+    #[must_use]
+    pub struct ZpoolCommand {
+        path: Option<PathBuf>,
+        wrapper: Vec<OsString>,
+        timeout: Duration,
+    }
+    impl Default for ZpoolCommand {
+        fn default() -> Self {
+            Self {
+                path: None,
+                wrapper: vec![],
+                timeout: Duration::from_secs(15),
             }
-        })
+        }
+    }
+    impl ZpoolCommand {
+        /// Sets the path of the `zpool` binary (default tries `/sbin/zpool`, then `zpool` on the
+        /// `PATH`, or only `zpool` when using a wrapper)
+        pub fn set_path(mut self, path: PathBuf) -> Self {
+            self.path = Some(path);
+            self
+        }
+        /// Sets the command and arguments to run `zpool` through, e.g. `["sudo", "-n"]`
+        pub fn set_wrapper(mut self, wrapper: Vec<OsString>) -> Self {
+            self.wrapper = wrapper;
+            self
+        }
+        /// Sets the time limit for `zpool` to complete, before it is killed (default 15 seconds)
+        pub fn set_timeout(mut self, timeout: Duration) -> Self {
+            self.timeout = timeout;
+            self
+        }
+        pub(crate) fn timeout(&self) -> Duration {
+            self.timeout
+        }
+
+        /// Returns the output of the `zpool status` command
+        ///
+        /// # Errors
+        /// Returns an error if the command execution fails, or the output is non-utf8
+        pub(crate) fn zpool_status(&self) -> Result<String, Error> {
+            let paths: Vec<OsString> = match &self.path {
+                Some(path) => vec![path.clone().into()],
+                // resolved by the wrapper
+                None if !self.wrapper.is_empty() => vec![DEFAULT_PATHS[1].into()],
+                None => DEFAULT_PATHS.into_iter().map(OsString::from).collect(),
+            };
+
+            let (last_path, fallback_from) = paths.split_last().expect("at least one zpool path");
+
+            let mut spawn_failed = vec![];
+            for zpool_path in fallback_from {
+                let (program, args) = self.program_args(zpool_path.clone());
+                match run_command(program, args, self.timeout) {
+                    Err(err) if err.is_spawn_error() => spawn_failed.push(err.command),
+                    result => return result,
+                }
+            }
+            let (program, args) = self.program_args(last_path.clone());
+            run_command(program, args, self.timeout).map_err(|mut err| {
+                if let ErrorKind::ChildSpawn { also_tried, .. } = &mut err.kind {
+                    *also_tried = spawn_failed;
+                }
+                err
+            })
+        }
+        /// Returns the program and arguments to run `zpool status` at the path
+        fn program_args(&self, zpool_path: OsString) -> (OsString, Vec<OsString>) {
+            // NOTE: "-p" for parsable (exact) values in the device table
+            const ARGS: [&str; 2] = ["status", "-p"];
+
+            let zpool_args = ARGS.into_iter().map(OsString::from);
+            match self.wrapper.split_first() {
+                Some((program, wrapper_args)) => {
+                    let args = wrapper_args
+                        .iter()
+                        .cloned()
+                        .chain(std::iter::once(zpool_path))
+                        .chain(zpool_args)
+                        .collect();
+                    (program.clone(), args)
+                }
+                None => (zpool_path, zpool_args.collect()),
+            }
+        }
     }
 
-    fn run_command(program: &'static str, args: &'static [&'static str]) -> Result<String, Error> {
-        const TIMEOUT: Duration = Duration::from_secs(15);
+    fn run_command(
+        program: OsString,
+        args: Vec<OsString>,
+        timeout: Duration,
+    ) -> Result<String, Error> {
+        /// Longest sleep between checks for completion
+        const MAX_WAIT_MILLIS: u64 = 1000;
+
+        let spawn_result = Command::new(&program)
+            .args(&args)
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn();
 
         let make_error = |kind| Error {
             command: program,
@@ -682,45 +1727,43 @@ mod exec {
             kind,
         };
 
-        let mut subcommand = Command::new(program)
-            .args(args)
-            .stdout(Stdio::piped())
-            .stderr(Stdio::piped())
-            .spawn()
-            .map_err(ErrorKind::ChildSpawn)
-            .map_err(make_error)?;
+        let mut subcommand = match spawn_result {
+            Ok(subcommand) => subcommand,
+            Err(io_error) => {
+                return Err(make_error(ErrorKind::ChildSpawn {
+                    io_error,
+                    also_tried: vec![],
+                }));
+            }
+        };
 
         let start_time = Instant::now();
 
         let mut wait = 1;
         loop {
-            if start_time.elapsed() >= TIMEOUT {
-                subcommand
-                    .kill()
-                    .map_err(ErrorKind::ChildTerminate)
-                    .map_err(make_error)?;
-                return Err(make_error(ErrorKind::Timeout));
+            if start_time.elapsed() >= timeout {
+                if let Err(err) = subcommand.kill() {
+                    return Err(make_error(ErrorKind::ChildTerminate(err)));
+                }
+                return Err(make_error(ErrorKind::Timeout { timeout }));
             }
-            if subcommand
-                .try_wait()
-                .map_err(ErrorKind::ChildStatus)
-                .map_err(make_error)?
-                .is_some()
-            {
-                break;
+            match subcommand.try_wait() {
+                Ok(Some(_)) => break,
+                Ok(None) => {}
+                Err(err) => return Err(make_error(ErrorKind::ChildStatus(err))),
             }
             std::thread::sleep(Duration::from_millis(wait));
-            wait *= 2;
+            wait = (wait * 2).min(MAX_WAIT_MILLIS);
         }
 
         let Output {
             status,
             stdout,
             stderr,
-        } = subcommand
-            .wait_with_output()
-            .map_err(ErrorKind::ChildOutput)
-            .map_err(make_error)?;
+        } = match subcommand.wait_with_output() {
+            Ok(output) => output,
+            Err(err) => return Err(make_error(ErrorKind::ChildOutput(err))),
+        };
 
         if !status.success() {
             return Err(make_error(ErrorKind::ChildFailed {
@@ -739,17 +1782,23 @@ mod exec {
 
     #[derive(Debug)]
     pub struct Error {
-        command: &'static str,
-        args: &'static [&'static str],
+        command: OsString,
+        args: Vec<OsString>,
         kind: ErrorKind,
     }
     #[derive(Debug)]
     enum ErrorKind {
-        ChildSpawn(std::io::Error),
+        ChildSpawn {
+            io_error: std::io::Error,
+            /// Paths that failed to spawn, before falling back to the `command`
+            also_tried: Vec<OsString>,
+        },
         ChildStatus(std::io::Error),
         ChildOutput(std::io::Error),
         ChildTerminate(std::io::Error),
-        Timeout,
+        Timeout {
+            timeout: Duration,
+        },
         NonUtf8Output(std::string::FromUtf8Error),
         ChildFailed {
             status: std::process::ExitStatus,
@@ -760,11 +1809,11 @@ mod exec {
     impl std::error::Error for Error {
         fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
             match &self.kind {
-                ErrorKind::ChildSpawn(err)
+                ErrorKind::ChildSpawn { io_error: err, .. }
                 | ErrorKind::ChildStatus(err)
                 | ErrorKind::ChildOutput(err)
                 | ErrorKind::ChildTerminate(err) => Some(err),
-                ErrorKind::Timeout | ErrorKind::ChildFailed { .. } => None,
+                ErrorKind::Timeout { .. } | ErrorKind::ChildFailed { .. } => None,
                 ErrorKind::NonUtf8Output(err) => Some(err),
             }
         }
@@ -777,11 +1826,17 @@ mod exec {
                 kind,
             } = self;
             let kind_str = match kind {
-                ErrorKind::ChildSpawn(_) => "should spawn a process",
+                ErrorKind::ChildSpawn { also_tried, .. } if !also_tried.is_empty() => {
+                    &format!("should spawn a process (also tried {also_tried:?})")
+                }
+                ErrorKind::ChildSpawn { .. } => "should spawn a process",
                 ErrorKind::ChildStatus(_) => "should have a retrievable exit status",
                 ErrorKind::ChildOutput(_) => "should have a retrievable output",
                 ErrorKind::ChildTerminate(_) => "should terminate successfully",
-                ErrorKind::Timeout => "should complete within the timeout",
+                ErrorKind::Timeout { timeout } => &format!(
+                    "should complete within the timeout of {}s",
+                    timeout.as_secs()
+                ),
                 ErrorKind::NonUtf8Output(_) => "should output valid UTF-8",
                 ErrorKind::ChildFailed {
                     status,
@@ -791,19 +1846,34 @@ mod exec {
                     "failed with exit code {status},  stdout: {stdout:?}, stderr: {stderr:?}"
                 ),
             };
-            write!(f, "command {command:?} (args {args:?}) {kind_str}")
+            write!(
+                f,
+                "command \"{command}\" (args {args:?}) {kind_str}",
+                command = command.display()
+            )
         }
     }
     impl Error {
         fn is_spawn_error(&self) -> bool {
-            matches!(self.kind, ErrorKind::ChildSpawn(_))
+            matches!(self.kind, ErrorKind::ChildSpawn { .. })
+        }
+        pub(crate) fn collector_error_kind(&self) -> CollectorErrorKind {
+            match &self.kind {
+                ErrorKind::ChildSpawn { .. } => CollectorErrorKind::ChildSpawn,
+                ErrorKind::ChildStatus(_) => CollectorErrorKind::ChildStatus,
+                ErrorKind::ChildOutput(_) => CollectorErrorKind::ChildOutput,
+                ErrorKind::ChildTerminate(_) => CollectorErrorKind::ChildTerminate,
+                ErrorKind::Timeout { .. } => CollectorErrorKind::Timeout,
+                ErrorKind::NonUtf8Output(_) => CollectorErrorKind::NonUtf8Output,
+                ErrorKind::ChildFailed { .. } => CollectorErrorKind::ChildFailed,
+            }
         }
     }
 }
 
 #[cfg(test)]
 mod tests {
-    use super::server::{calculate_delay_seconds, start_server_with_retry};
+    use super::server::{bind_with_retry, calculate_delay_seconds};
     use std::net::{SocketAddr, TcpListener};
 
     #[test]
@@ -825,7 +1895,7 @@ mod tests {
         let local_addr = listener.local_addr().expect("Failed to get local address");
 
         // Test that 0 retries = exactly 1 attempt
-        let result = start_server_with_retry(local_addr, 0);
+        let result = bind_with_retry(local_addr, 0);
         assert!(result.is_err());
 
         if let Err(error) = result {
@@ -839,7 +1909,7 @@ mod tests {
         // Use port 0 to let the OS assign an available port
         let addr: SocketAddr = "127.0.0.1:0".parse().expect("Valid socket address");
 
-        let result = start_server_with_retry(addr, 5);
+        let result = bind_with_retry(addr, 5);
         assert!(result.is_ok());
     }
 
@@ -848,7 +1918,7 @@ mod tests {
         // Test that having retries available doesn't affect successful first attempt
         let addr: SocketAddr = "127.0.0.1:0".parse().expect("Valid socket address");
 
-        let result = start_server_with_retry(addr, 10);
+        let result = bind_with_retry(addr, 10);
         assert!(result.is_ok());
     }
 }
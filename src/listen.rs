@@ -0,0 +1,147 @@
+//! Addresses for the server to listen on
+
+use std::{net::SocketAddr, path::PathBuf};
+
+/// Prefix distinguishing Unix domain socket paths from TCP addresses
+const UNIX_PREFIX: &str = "unix:";
+
+/// TCP address (e.g. `127.0.0.1:9582`) or Unix domain socket path (e.g.
+/// `unix:/run/zpool-status-exporter.sock`)
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ListenAddress {
+    /// TCP socket address
+    Tcp(SocketAddr),
+    /// Path of the Unix domain socket (replaced if a stale socket exists)
+    Unix(PathBuf),
+}
+impl From<SocketAddr> for ListenAddress {
+    fn from(address: SocketAddr) -> Self {
+        Self::Tcp(address)
+    }
+}
+impl std::str::FromStr for ListenAddress {
+    type Err = Error;
+    fn from_str(input: &str) -> Result<Self, Error> {
+        let make_error = |kind| Error {
+            input: input.to_owned(),
+            kind,
+        };
+
+        if let Some(path) = input.strip_prefix(UNIX_PREFIX) {
+            if path.is_empty() {
+                Err(make_error(ErrorKind::EmptyPath))
+            } else {
+                Ok(Self::Unix(path.into()))
+            }
+        } else {
+            input
+                .parse()
+                .map(Self::Tcp)
+                .map_err(|_| make_error(ErrorKind::InvalidAddress))
+        }
+    }
+}
+impl std::fmt::Display for ListenAddress {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Tcp(address) => write!(f, "{address}"),
+            Self::Unix(path) => write!(f, "{UNIX_PREFIX}{}", path.display()),
+        }
+    }
+}
+
+/// Invalid listen address
+#[derive(Debug)]
+pub struct Error {
+    input: String,
+    kind: ErrorKind,
+}
+#[derive(Debug)]
+enum ErrorKind {
+    InvalidAddress,
+    EmptyPath,
+}
+impl std::error::Error for Error {}
+impl std::fmt::Display for Error {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let Self { input, kind } = self;
+        match kind {
+            ErrorKind::InvalidAddress => write!(
+                f,
+                "invalid listen address {input:?}, expected IP:PORT or {UNIX_PREFIX}PATH"
+            ),
+            ErrorKind::EmptyPath => write!(f, "empty Unix socket path in {input:?}"),
+        }
+    }
+}
+
+/// Listener passed by the service manager (systemd socket activation)
+pub struct ActivatedListener {
+    listener: Listener,
+    /// Name from `LISTEN_FDNAMES`, e.g. the `FileDescriptorName` of the socket unit
+    name: Option<String>,
+}
+pub(crate) enum Listener {
+    Tcp(std::net::TcpListener),
+    Unix(std::os::unix::net::UnixListener),
+}
+impl ActivatedListener {
+    pub(crate) fn into_parts(self) -> (Listener, Option<String>) {
+        let Self { listener, name } = self;
+        (listener, name)
+    }
+}
+
+/// Takes the listeners passed by systemd socket activation (`LISTEN_FDS`, `LISTEN_PID` and
+/// `LISTEN_FDNAMES`), or none if not activated
+///
+/// Call before spawning any threads, as this removes the `LISTEN_FDS` and `LISTEN_PID`
+/// environment variables.
+///
+/// # Errors
+/// Returns an error if a file descriptor is not a TCP or Unix stream listener
+pub fn take_activated_listeners() -> Result<Vec<ActivatedListener>, ActivationError> {
+    let names = std::env::var("LISTEN_FDNAMES").unwrap_or_default();
+    let mut names = names.split(':').map(str::to_owned);
+
+    let mut listen_fds = listenfd::ListenFd::from_env();
+    (0..listen_fds.len())
+        .map(|index| {
+            let name = names.next().filter(|name| !name.is_empty());
+            let make_error = |io_error| ActivationError { index, io_error };
+
+            let listener = match listen_fds.take_tcp_listener(index) {
+                Ok(listener) => listener.map(Listener::Tcp),
+                // not TCP, the descriptor is left in place
+                Err(_) => listen_fds
+                    .take_unix_listener(index)
+                    .map_err(make_error)?
+                    .map(Listener::Unix),
+            };
+            let listener = listener
+                .ok_or_else(|| make_error(std::io::Error::from(std::io::ErrorKind::NotFound)))?;
+            Ok(ActivatedListener { listener, name })
+        })
+        .collect()
+}
+
+/// Invalid file descriptor passed by socket activation
+#[derive(Debug)]
+pub struct ActivationError {
+    index: usize,
+    io_error: std::io::Error,
+}
+impl std::error::Error for ActivationError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        Some(&self.io_error)
+    }
+}
+impl std::fmt::Display for ActivationError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let Self { index, io_error: _ } = self;
+        write!(
+            f,
+            "socket activation file descriptor {index} is not a TCP or Unix stream listener"
+        )
+    }
+}
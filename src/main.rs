@@ -14,29 +14,526 @@
 #![deny(missing_docs)]
 #![deny(rustdoc::broken_intra_doc_links)]
 
+use anyhow::Context as _;
 use clap::Parser as _;
+use std::time::Duration;
+use zpool_status_exporter::CollectMode;
 
 /// Command-line arguments for the server
 #[derive(clap::Parser)]
-#[clap(version)]
+#[clap(version, args_conflicts_with_subcommands = true)]
+#[command(group = clap::ArgGroup::new("auth_files").multiple(true))]
+#[command(group = clap::ArgGroup::new("serve_only").multiple(true).args([
+    "listen_address",
+    "listen",
+    "unix_socket_mode",
+    "basic_auth_keys_file",
+    "bearer_token_file",
+    "watch_auth_files",
+    "max_bind_retries",
+    "tls_cert_file",
+    "tls_key_file",
+    "tls_client_ca_file",
+    "allow_cidr",
+    "trusted_proxy",
+    "authenticate_health",
+    "ready_max_age",
+    "failure_response",
+    "min_interval",
+]))]
 struct Args {
-    /// Bind address for the server
+    #[command(subcommand)]
+    command: Option<Command>,
+    /// Bind address for the server, `IP:PORT` or `unix:PATH` for a Unix domain socket
+    ///
+    /// Optional when started by systemd socket activation (`LISTEN_FDS`)
     #[clap(env)]
-    listen_address: std::net::SocketAddr,
+    listen_address: Option<zpool_status_exporter::ListenAddress>,
+    /// Additional bind address (repeat, or separate by commas)
+    #[clap(env)]
+    #[arg(long, value_delimiter = ',')]
+    listen: Vec<zpool_status_exporter::ListenAddress>,
+    /// Permissions (octal) of Unix domain socket files
+    #[clap(env)]
+    #[arg(long, default_value = "660", value_parser = parse_octal_mode)]
+    unix_socket_mode: u32,
     /// Filename containing allowed basic authentication tokens
     #[clap(env)]
-    #[arg(long)]
+    #[arg(long, group = "auth_files")]
     basic_auth_keys_file: Option<std::path::PathBuf>,
+    /// Filename containing allowed bearer tokens (one per line)
+    #[clap(env)]
+    #[arg(long, group = "auth_files")]
+    bearer_token_file: Option<std::path::PathBuf>,
+    /// Reload the basic authentication and bearer token files when modified (they are always
+    /// reloaded on SIGHUP)
+    #[clap(env)]
+    #[arg(long, alias = "watch-basic-auth-keys-file", requires = "auth_files")]
+    watch_auth_files: bool,
     /// Maximum number of bind retry attempts (0 = no retries, just 1 attempt)
     #[clap(env)]
     #[arg(long, default_value = "5")]
     max_bind_retries: u32,
+    /// Filename of the PEM certificate chain for serving HTTPS (reloaded when modified)
+    #[clap(env)]
+    #[arg(long, requires = "tls_key_file")]
+    tls_cert_file: Option<std::path::PathBuf>,
+    /// Filename of the PEM private key for serving HTTPS (reloaded when modified)
+    #[clap(env)]
+    #[arg(long, requires = "tls_cert_file")]
+    tls_key_file: Option<std::path::PathBuf>,
+    /// Filename of the PEM CA certificates for verifying required client certificates
+    #[clap(env)]
+    #[arg(long, requires = "tls_cert_file")]
+    tls_client_ca_file: Option<std::path::PathBuf>,
+    /// Network `address/prefix-length` allowed to connect, e.g. the Prometheus server subnet
+    /// (repeat, or separate by commas; default allows all)
+    #[clap(env)]
+    #[arg(long, value_delimiter = ',')]
+    allow_cidr: Vec<zpool_status_exporter::Cidr>,
+    /// Network of proxies trusted to report the client address in the `X-Forwarded-For` header
+    /// (repeat, or separate by commas)
+    #[clap(env)]
+    #[arg(long, value_delimiter = ',', requires = "allow_cidr")]
+    trusted_proxy: Vec<zpool_status_exporter::Cidr>,
+    /// Require authentication for the `/-/healthy` and `/-/ready` endpoints (public by default)
+    #[clap(env)]
+    #[arg(long)]
+    authenticate_health: bool,
+    /// Maximum age of the last successful collection for `/-/ready` to report ready (by default
+    /// only the result of the last collection is checked, see also `--poll-interval`)
+    #[clap(env)]
+    #[arg(long, value_name = "SECONDS")]
+    #[arg(value_parser = clap::value_parser!(u64).range(1..))]
+    ready_max_age: Option<u64>,
+    #[command(flatten)]
+    run_as: RunAsArgs,
+    #[command(flatten)]
+    zpool: ZpoolArgs,
+    #[command(flatten)]
+    format: FormatArgs,
+    /// Response of the metrics endpoint when running or parsing `zpool status` fails
+    #[clap(env)]
+    #[arg(long, value_enum, default_value_t)]
+    failure_response: FailureResponse,
+    /// Run `zpool status` in the background every N seconds, serving the latest results
+    #[clap(env)]
+    #[arg(long, value_name = "SECONDS", conflicts_with = "min_interval")]
+    #[arg(value_parser = clap::value_parser!(u64).range(1..))]
+    poll_interval: Option<u64>,
+    /// Reuse `zpool status` results for N seconds, sharing one run between concurrent requests
+    #[clap(env)]
+    #[arg(long, value_name = "SECONDS")]
+    #[arg(value_parser = clap::value_parser!(u64).range(1..))]
+    min_interval: Option<u64>,
+    /// Directory of the `node_exporter` textfile collector, to write `zpool.prom` instead of
+    /// serving the metrics (once, or every `--poll-interval` seconds)
+    #[clap(env)]
+    #[arg(long, value_name = "DIR", conflicts_with = "serve_only")]
+    textfile_output: Option<std::path::PathBuf>,
+    #[command(flatten)]
+    push: PushArgs,
+}
+impl Args {
+    /// Returns the destination of the metrics, if not serving them
+    fn take_output(&mut self) -> anyhow::Result<Option<Output>> {
+        if let Some(dir) = self.textfile_output.take() {
+            return Ok(Some(Output::Textfile(dir)));
+        }
+        let pushgateway = self.push.pushgateway()?;
+        Ok(pushgateway.map(Output::Push))
+    }
 }
 
-fn main() -> anyhow::Result<()> {
-    if nix::unistd::Uid::effective().is_root() {
-        anyhow::bail!("refusing to run as super-user, try a non-privileged user");
+/// Pushgateway to push the metrics to, instead of serving them
+#[derive(clap::Args)]
+#[command(group = clap::ArgGroup::new("push_only")
+    .multiple(true)
+    .args(["job", "instance", "basic_auth_file", "max_retries"])
+    .requires("url")
+    .conflicts_with_all(["serve_only", "textfile_output"]))]
+struct PushArgs {
+    /// Base URL (`http://` or `https://`) of a Prometheus Pushgateway to push the metrics to,
+    /// instead of serving them (once, or every `--poll-interval` seconds)
+    #[clap(env = "PUSH_URL")]
+    #[arg(long = "push-url", value_name = "URL")]
+    #[arg(conflicts_with_all = ["serve_only", "textfile_output"])]
+    url: Option<String>,
+    /// Value of the `job` grouping key for the pushed metrics
+    #[clap(env = "PUSH_JOB")]
+    #[arg(
+        long = "push-job",
+        default_value = "zpool-status-exporter",
+        requires = "url"
+    )]
+    job: String,
+    /// Value of the `instance` grouping key for the pushed metrics (default is the host name)
+    #[clap(env = "PUSH_INSTANCE")]
+    #[arg(long = "push-instance", requires = "url")]
+    instance: Option<String>,
+    /// Filename containing `username:password` for basic authentication to the Pushgateway
+    #[clap(env = "PUSH_BASIC_AUTH_FILE")]
+    #[arg(long = "push-basic-auth-file", requires = "url")]
+    basic_auth_file: Option<std::path::PathBuf>,
+    /// Maximum number of retries for each push, with exponential backoff (0 = no retries)
+    #[clap(env = "PUSH_MAX_RETRIES")]
+    #[arg(long = "push-max-retries", default_value = "3", requires = "url")]
+    max_retries: u32,
+}
+impl PushArgs {
+    /// Returns the Pushgateway, if the URL is set
+    fn pushgateway(&self) -> anyhow::Result<Option<zpool_status_exporter::Pushgateway>> {
+        let Self {
+            url,
+            job,
+            instance,
+            basic_auth_file,
+            max_retries,
+        } = self;
+        let Some(url) = url else {
+            return Ok(None);
+        };
+        let instance = match instance {
+            Some(instance) => instance.clone(),
+            None => nix::unistd::gethostname()?
+                .into_string()
+                .map_err(|hostname| anyhow::anyhow!("non-utf8 host name {hostname:?}"))?,
+        };
+        let mut pushgateway = zpool_status_exporter::Pushgateway::new(url, job, &instance)
+            .set_max_retries(*max_retries);
+        if let Some(basic_auth_file) = basic_auth_file {
+            let credentials = std::fs::read_to_string(basic_auth_file).with_context(|| {
+                format!(
+                    "failed to read push basic auth file {}",
+                    basic_auth_file.display()
+                )
+            })?;
+            let Some((username, password)) = credentials.trim_end().split_once(':') else {
+                anyhow::bail!(
+                    "expected username:password in push basic auth file {}",
+                    basic_auth_file.display()
+                );
+            };
+            pushgateway = pushgateway.set_basic_auth(username, password);
+        }
+        Ok(Some(pushgateway))
+    }
+}
+
+/// Destination of the metrics, when not serving them
+enum Output {
+    /// Directory of the `node_exporter` textfile collector
+    Textfile(std::path::PathBuf),
+    Push(zpool_status_exporter::Pushgateway),
+}
+impl Output {
+    fn write(&self, app_context: &zpool_status_exporter::AppContext) -> anyhow::Result<()> {
+        match self {
+            Self::Textfile(dir) => app_context.write_textfile(dir)?,
+            Self::Push(pushgateway) => app_context.push_metrics(pushgateway)?,
+        }
+        Ok(())
+    }
+}
+
+/// Alternatives to serving the metrics
+#[derive(clap::Subcommand)]
+enum Command {
+    /// Print the metrics for `zpool status -p` output read from a file or stdin (e.g. captured by
+    /// cron)
+    Parse(ParseArgs),
+    /// Check the pools against thresholds, as a Nagios/Icinga plugin (exit status 0 = OK,
+    /// 1 = WARNING, 2 = CRITICAL, 3 = UNKNOWN)
+    Check(CheckArgs),
+    /// Print Prometheus alerting rules (YAML) for the metrics
+    GenerateRules(GenerateRulesArgs),
+}
+
+/// Thresholds for the check plugin
+#[derive(clap::Args)]
+struct CheckArgs {
+    /// Pool state for a warning, including all more severe states
+    #[arg(long, value_name = "STATE", default_value = "degraded")]
+    state_warning: zpool_status_exporter::StateThreshold,
+    /// Pool state for a critical status, including all more severe states
+    #[arg(long, value_name = "STATE", default_value = "faulted")]
+    state_critical: zpool_status_exporter::StateThreshold,
+    /// Count of device errors (read, write and checksum, summed across leaf devices) for a warning
+    #[arg(long, value_name = "COUNT", default_value = "1")]
+    errors_warning: u32,
+    /// Count of device errors for a critical status
+    #[arg(long, value_name = "COUNT")]
+    errors_critical: Option<u32>,
+    /// Days since the last scan for a warning (pools never scanned always exceed it)
+    #[arg(long, value_name = "DAYS", default_value = "35")]
+    scan_age_warning: u32,
+    /// Days since the last scan for a critical status
+    #[arg(long, value_name = "DAYS")]
+    scan_age_critical: Option<u32>,
+    /// Status for pools reporting data errors
+    #[arg(long, value_enum, default_value_t)]
+    data_errors: DataErrorsStatus,
+    #[command(flatten)]
+    zpool: ZpoolArgs,
+}
+impl CheckArgs {
+    /// Prints the plugin output, returning the exit code
+    fn run(self, mut app_context: zpool_status_exporter::AppContext) -> i32 {
+        let Self {
+            state_warning,
+            state_critical,
+            errors_warning,
+            errors_critical,
+            scan_age_warning,
+            scan_age_critical,
+            data_errors,
+            zpool,
+        } = self;
+
+        if nix::unistd::Uid::effective().is_root() {
+            println!("ZPOOL UNKNOWN - {SUPER_USER_REFUSED}");
+            return zpool_status_exporter::CheckStatus::Unknown.exit_code();
+        }
+        app_context.set_zpool_command(zpool.into());
+
+        let mut thresholds = zpool_status_exporter::CheckThresholds::default()
+            .set_state_warning(state_warning)
+            .set_state_critical(state_critical)
+            .set_device_errors_warning(errors_warning)
+            .set_scan_age_warning_days(scan_age_warning)
+            .set_data_errors_status(data_errors.into());
+        if let Some(errors_critical) = errors_critical {
+            thresholds = thresholds.set_device_errors_critical(errors_critical);
+        }
+        if let Some(scan_age_critical) = scan_age_critical {
+            thresholds = thresholds.set_scan_age_critical_days(scan_age_critical);
+        }
+
+        let report = app_context.check_now(&thresholds);
+        println!("{report}");
+        report.status().exit_code()
+    }
+}
+
+/// Upper limit for `--for` (minutes in a year), far from overflowing the duration
+const MAX_PENDING_MINUTES: u64 = 365 * 24 * 60;
+
+/// Thresholds and metric naming for the alerting rules
+#[derive(clap::Args)]
+struct GenerateRulesArgs {
+    /// Pool state for a warning alert, including all more severe states
+    #[arg(long, value_name = "STATE", default_value = "degraded")]
+    state_warning: zpool_status_exporter::StateThreshold,
+    /// Pool state for a critical alert, including all more severe states
+    #[arg(long, value_name = "STATE", default_value = "faulted")]
+    state_critical: zpool_status_exporter::StateThreshold,
+    /// Count of device errors (read, write and checksum, summed across leaf devices) for a warning
+    #[arg(long, value_name = "COUNT", default_value = "1")]
+    device_errors: u32,
+    /// Days since the last scan for a warning (pools never scanned have a separate alert)
+    #[arg(long, value_name = "DAYS", default_value = "35")]
+    scan_age: u32,
+    /// Minutes each condition must hold before the alert fires (at most a year)
+    #[arg(long = "for", value_name = "MINUTES", default_value = "15")]
+    #[arg(value_parser = clap::value_parser!(u64).range(..=MAX_PENDING_MINUTES))]
+    pending: u64,
+    #[command(flatten)]
+    format: FormatArgs,
+}
+impl GenerateRulesArgs {
+    /// Prints the rules file
+    fn run(self, mut app_context: zpool_status_exporter::AppContext) -> anyhow::Result<()> {
+        let Self {
+            state_warning,
+            state_critical,
+            device_errors,
+            scan_age,
+            pending,
+            format,
+        } = self;
+        format.apply(&mut app_context)?;
+
+        let thresholds = zpool_status_exporter::RuleThresholds::default()
+            .set_state_warning(state_warning)
+            .set_state_critical(state_critical)
+            .set_device_errors(device_errors)
+            .set_scan_age(Duration::from_hours(24) * scan_age)
+            .set_pending(Duration::from_mins(pending));
+        print!("{}", app_context.alerting_rules(&thresholds));
+        Ok(())
+    }
+}
+
+#[derive(Clone, Copy, Default, clap::ValueEnum)]
+enum DataErrorsStatus {
+    Warning,
+    #[default]
+    Critical,
+}
+impl From<DataErrorsStatus> for zpool_status_exporter::CheckStatus {
+    fn from(value: DataErrorsStatus) -> Self {
+        match value {
+            DataErrorsStatus::Warning => Self::Warning,
+            DataErrorsStatus::Critical => Self::Critical,
+        }
+    }
+}
+
+/// Arguments for parsing captured `zpool status -p` output
+#[derive(clap::Args)]
+struct ParseArgs {
+    /// File containing the output of `zpool status -p` (default, or `-`, reads stdin)
+    input: Option<std::path::PathBuf>,
+    /// Unix timestamp (seconds) to use as the current time, for reproducible durations (omits the
+    /// lookup duration metric)
+    #[arg(long, value_name = "UNIX_SECONDS")]
+    now: Option<i64>,
+    #[command(flatten)]
+    format: FormatArgs,
+}
+impl ParseArgs {
+    /// Prints the metrics for the input
+    fn run(self, mut app_context: zpool_status_exporter::AppContext) -> anyhow::Result<()> {
+        use std::io::Read as _;
+
+        let Self { input, now, format } = self;
+        format.apply(&mut app_context)?;
+
+        let timestamp = match now {
+            Some(now) => app_context
+                .timestamp_at_unix_utc(now, None)
+                .ok_or_else(|| anyhow::anyhow!("timestamp {now} is out of range"))?,
+            None => app_context.timestamp_now(),
+        };
+        let zpool_output = match input {
+            Some(path) if path.as_os_str() != "-" => std::fs::read_to_string(&path)
+                .with_context(|| format!("failed to read input file {}", path.display()))?,
+            _ => {
+                let mut zpool_output = String::new();
+                std::io::stdin()
+                    .read_to_string(&mut zpool_output)
+                    .context("failed to read stdin")?;
+                zpool_output
+            }
+        };
+        let metrics = timestamp.get_metrics_for_output(&zpool_output)?;
+        print!("{metrics}");
+        Ok(())
+    }
+}
+
+/// Names and labels of the metrics
+#[derive(clap::Args)]
+struct FormatArgs {
+    /// Prefix for all metric names
+    #[clap(env)]
+    #[arg(long, default_value = "zpool")]
+    metric_prefix: zpool_status_exporter::MetricPrefix,
+    /// Label `name=value` added to every metric (repeat, or separate by commas)
+    #[clap(env)]
+    #[arg(long, value_delimiter = ',')]
+    const_label: Vec<zpool_status_exporter::ConstLabel>,
+}
+impl FormatArgs {
+    fn apply(self, app_context: &mut zpool_status_exporter::AppContext) -> anyhow::Result<()> {
+        let Self {
+            metric_prefix,
+            const_label,
+        } = self;
+        app_context.set_metric_prefix(metric_prefix);
+        app_context.set_const_labels(const_label)?;
+        Ok(())
+    }
+}
+
+/// User and group to switch to after binding the listeners
+#[derive(clap::Args)]
+struct RunAsArgs {
+    /// User (name or uid) to switch to after binding the listeners, required when started as the
+    /// super-user
+    #[clap(env = "RUN_AS_USER")]
+    #[arg(long)]
+    user: Option<String>,
+    /// Group (name or gid) to switch to after binding the listeners (default is the primary group
+    /// of the user)
+    #[clap(env = "RUN_AS_GROUP")]
+    #[arg(long, requires = "user")]
+    group: Option<String>,
+}
+impl RunAsArgs {
+    /// Looks up the user and group, if set (required when running as the super-user)
+    fn lookup(self, is_root: bool) -> anyhow::Result<Option<zpool_status_exporter::RunAs>> {
+        let Self { user, group } = self;
+        match user {
+            Some(user) => Ok(Some(zpool_status_exporter::RunAs::lookup(
+                &user,
+                group.as_deref(),
+            )?)),
+            None if is_root => {
+                anyhow::bail!("{SUPER_USER_REFUSED}, or drop privileges using --user");
+            }
+            None => Ok(None),
+        }
+    }
+}
+
+/// Options for running the `zpool` command
+#[derive(clap::Args)]
+struct ZpoolArgs {
+    /// Path of the `zpool` binary (default tries `/sbin/zpool`, then `zpool` on the `PATH`)
+    #[clap(env = "ZPOOL_PATH")]
+    #[arg(long = "zpool-path")]
+    path: Option<std::path::PathBuf>,
+    /// Command to run `zpool` through, split on whitespace, e.g. `sudo -n` or `doas`
+    #[clap(env = "ZPOOL_WRAPPER")]
+    #[arg(long = "zpool-wrapper")]
+    wrapper: Option<String>,
+    /// Time limit for `zpool status` to complete, before it is killed
+    #[clap(env = "ZPOOL_TIMEOUT")]
+    #[arg(long = "zpool-timeout", value_name = "SECONDS", default_value = "15")]
+    #[arg(value_parser = clap::value_parser!(u64).range(1..))]
+    timeout: u64,
+}
+impl From<ZpoolArgs> for zpool_status_exporter::ZpoolCommand {
+    fn from(value: ZpoolArgs) -> Self {
+        let ZpoolArgs {
+            path,
+            wrapper,
+            timeout,
+        } = value;
+        let mut zpool_command = Self::default().set_timeout(Duration::from_secs(timeout));
+        if let Some(path) = path {
+            zpool_command = zpool_command.set_path(path);
+        }
+        if let Some(wrapper) = wrapper {
+            let wrapper = wrapper.split_whitespace().map(Into::into).collect();
+            zpool_command = zpool_command.set_wrapper(wrapper);
+        }
+        zpool_command
     }
+}
+
+#[derive(Clone, Copy, Default, clap::ValueEnum)]
+enum FailureResponse {
+    /// HTTP 200 with `up` = 0, and the last successful metrics (if any)
+    #[default]
+    UpZero,
+    /// HTTP 503 with the error description
+    Unavailable,
+}
+impl From<FailureResponse> for zpool_status_exporter::FailureResponse {
+    fn from(value: FailureResponse) -> Self {
+        match value {
+            FailureResponse::UpZero => Self::UpZero,
+            FailureResponse::Unavailable => Self::Unavailable,
+        }
+    }
+}
+
+const SUPER_USER_REFUSED: &str = "refusing to run as super-user, try a non-privileged user";
+
+fn main() -> anyhow::Result<()> {
+    let mut args = Args::parse();
 
     let mut app_context = zpool_status_exporter::AppContext::new();
     {
@@ -45,6 +542,44 @@ fn main() -> anyhow::Result<()> {
         app_context.set_app_version(app_version);
     }
 
+    match args.command.take() {
+        Some(Command::Parse(parse_args)) => parse_args.run(app_context),
+        Some(Command::Check(check_args)) => std::process::exit(check_args.run(app_context)),
+        Some(Command::GenerateRules(rules_args)) => rules_args.run(app_context),
+        None => match args.take_output()? {
+            Some(output) => write_output(args, &output, app_context),
+            None => serve(args, app_context),
+        },
+    }
+}
+
+/// Writes the metrics once, or every poll interval until shutdown
+fn write_output(
+    args: Args,
+    output: &Output,
+    mut app_context: zpool_status_exporter::AppContext,
+) -> anyhow::Result<()> {
+    let is_root = nix::unistd::Uid::effective().is_root();
+
+    let Args {
+        run_as,
+        zpool,
+        format,
+        poll_interval,
+        ..
+    } = args;
+    let run_as = run_as.lookup(is_root)?;
+    app_context.set_zpool_command(zpool.into());
+    format.apply(&mut app_context)?;
+    if let Some(run_as) = run_as {
+        run_as.apply()?;
+        run_as.print_start_message();
+    }
+
+    let Some(seconds) = poll_interval else {
+        return output.write(&app_context);
+    };
+
     let (shutdown_tx, shutdown_rx) = std::sync::mpsc::channel();
     ctrlc::set_handler(move || {
         eprintln!("user requested shutdown...");
@@ -52,7 +587,105 @@ fn main() -> anyhow::Result<()> {
             .send(zpool_status_exporter::Shutdown)
             .expect("termination channel send failed");
     })?;
+    loop {
+        if let Err(err) = output.write(&app_context) {
+            eprintln!("error: {err:#}");
+        }
+        match shutdown_rx.recv_timeout(Duration::from_secs(seconds)) {
+            Err(std::sync::mpsc::RecvTimeoutError::Timeout) => {}
+            Ok(zpool_status_exporter::Shutdown)
+            | Err(std::sync::mpsc::RecvTimeoutError::Disconnected) => return Ok(()),
+        }
+    }
+}
+
+/// Runs the server until shutdown
+fn serve(args: Args, mut app_context: zpool_status_exporter::AppContext) -> anyhow::Result<()> {
+    let is_root = nix::unistd::Uid::effective().is_root();
+
+    // before spawning threads (modifies the environment)
+    let activated_listeners = zpool_status_exporter::take_activated_listeners()?;
 
+    let (shutdown_tx, shutdown_rx) = std::sync::mpsc::channel();
+    ctrlc::set_handler(move || {
+        eprintln!("user requested shutdown...");
+        if let Err(err) = sd_notify::notify(&[sd_notify::NotifyState::Stopping]) {
+            eprintln!("error sending sd_notify Stopping: {err}");
+        }
+        shutdown_tx
+            .send(zpool_status_exporter::Shutdown)
+            .expect("termination channel send failed");
+    })?;
+
+    let reload_rx = spawn_reload_on_sighup()?;
+
+    let ready_tx = spawn_notify_ready();
+
+    let Args {
+        command: _,
+        listen_address,
+        listen,
+        unix_socket_mode,
+        basic_auth_keys_file,
+        bearer_token_file,
+        watch_auth_files,
+        max_bind_retries,
+        tls_cert_file,
+        tls_key_file,
+        tls_client_ca_file,
+        allow_cidr,
+        trusted_proxy,
+        authenticate_health,
+        ready_max_age,
+        run_as,
+        zpool,
+        format,
+        failure_response,
+        poll_interval,
+        min_interval,
+        textfile_output: _,
+        push: _,
+    } = args;
+    let run_as = run_as.lookup(is_root)?;
+    app_context.set_zpool_command(zpool.into());
+    format.apply(&mut app_context)?;
+    app_context.set_failure_response(failure_response.into());
+    app_context.set_collect_mode(collect_mode(poll_interval, min_interval));
+    if let Some(seconds) = ready_max_age {
+        app_context.set_ready_max_age(Duration::from_secs(seconds));
+    }
+    let mut args = zpool_status_exporter::Args::basic_auth(basic_auth_keys_file, max_bind_retries)
+        .set_unix_socket_mode(unix_socket_mode)
+        .set_watch_auth_files(watch_auth_files)
+        .set_allow_cidrs(allow_cidr)
+        .set_trusted_proxies(trusted_proxy)
+        .set_authenticate_health(authenticate_health);
+    for listen_address in listen_address.into_iter().chain(listen) {
+        args = args.add_listen_address(listen_address);
+    }
+    if let Some(bearer_token_file) = bearer_token_file {
+        args = args.set_bearer_token_file(bearer_token_file);
+    }
+    if let Some(tls_files) = tls_files(tls_cert_file, tls_key_file, tls_client_ca_file) {
+        args = args.set_tls_files(tls_files);
+    }
+    let mut server_builder = app_context
+        .server_builder(&args)
+        .set_ready_sender(ready_tx)
+        .set_shutdown_receiver(shutdown_rx)
+        .set_reload_receiver(reload_rx)
+        .set_activated_listeners(activated_listeners);
+    if let Some(run_as) = run_as {
+        server_builder = server_builder.set_run_as(run_as);
+    }
+    let (heartbeat_tx, heartbeat_interval) = spawn_notify_heartbeats();
+    server_builder = server_builder.set_heartbeat_sender(heartbeat_tx, heartbeat_interval);
+    server_builder.serve()?;
+    Ok(())
+}
+
+/// Returns the sender for [`zpool_status_exporter::Ready`], forwarded as `sd_notify` `READY`
+fn spawn_notify_ready() -> std::sync::mpsc::Sender<zpool_status_exporter::Ready> {
     let (ready_tx, ready_rx) = std::sync::mpsc::channel();
     std::thread::spawn(move || {
         if let Ok(zpool_status_exporter::Ready) = ready_rx.recv() {
@@ -62,37 +695,77 @@ fn main() -> anyhow::Result<()> {
             }
         }
     });
+    ready_tx
+}
 
-    if is_oneshot_test_print() {
-        let metrics = app_context.get_metrics_now()?;
-        println!("{metrics}");
-        Ok(())
-    } else {
-        let Args {
-            listen_address,
-            basic_auth_keys_file,
-            max_bind_retries,
-        } = Args::parse();
-        let args = zpool_status_exporter::Args::listen_basic_auth(
-            listen_address,
-            basic_auth_keys_file,
-            max_bind_retries,
-        );
-        app_context
-            .server_builder(&args)
-            .set_ready_sender(ready_tx)
-            .set_shutdown_receiver(shutdown_rx)
-            .serve()?;
-        Ok(())
-    }
+/// Returns the receiver of a [`zpool_status_exporter::Reload`] for each SIGHUP
+fn spawn_reload_on_sighup()
+-> anyhow::Result<std::sync::mpsc::Receiver<zpool_status_exporter::Reload>> {
+    let (reload_tx, reload_rx) = std::sync::mpsc::channel();
+    let mut reload_signals = signal_hook::iterator::Signals::new([signal_hook::consts::SIGHUP])?;
+    std::thread::spawn(move || {
+        for _ in reload_signals.forever() {
+            if reload_tx.send(zpool_status_exporter::Reload).is_err() {
+                break;
+            }
+        }
+    });
+    Ok(reload_rx)
 }
 
-fn is_oneshot_test_print() -> bool {
-    let mut args = std::env::args();
-    if args.len() == 2 {
-        let arg = args.nth(1).expect("second arg exists, in list of length 2");
-        arg == "--oneshot-test-print"
-    } else {
-        false
+/// Returns the sender and interval for heartbeats, forwarded as `sd_notify` `STATUS` (and
+/// `WATCHDOG` keepalives if the service manager enabled the watchdog)
+fn spawn_notify_heartbeats() -> (
+    std::sync::mpsc::Sender<zpool_status_exporter::Heartbeat>,
+    Duration,
+) {
+    const STATUS_INTERVAL: Duration = Duration::from_secs(10);
+
+    let watchdog_timeout = sd_notify::watchdog_enabled();
+    // keepalive at half the timeout, as recommended by sd_watchdog_enabled(3)
+    let interval = watchdog_timeout.map_or(STATUS_INTERVAL, |timeout| timeout / 2);
+
+    let (heartbeat_tx, heartbeat_rx) =
+        std::sync::mpsc::channel::<zpool_status_exporter::Heartbeat>();
+    std::thread::spawn(move || {
+        for heartbeat in heartbeat_rx {
+            let status = sd_notify::NotifyState::Status(heartbeat.status());
+            let notify_result = if watchdog_timeout.is_some() && heartbeat.is_alive() {
+                sd_notify::notify(&[sd_notify::NotifyState::Watchdog, status])
+            } else {
+                sd_notify::notify(&[status])
+            };
+            if let Err(err) = notify_result {
+                eprintln!("error sending sd_notify heartbeat: {err}");
+            }
+        }
+    });
+    (heartbeat_tx, interval)
+}
+
+fn tls_files(
+    cert_file: Option<std::path::PathBuf>,
+    key_file: Option<std::path::PathBuf>,
+    client_ca_file: Option<std::path::PathBuf>,
+) -> Option<zpool_status_exporter::TlsFiles> {
+    let (Some(cert_file), Some(key_file)) = (cert_file, key_file) else {
+        return None;
+    };
+    let tls_files = zpool_status_exporter::TlsFiles::new(cert_file, key_file);
+    Some(match client_ca_file {
+        Some(client_ca_file) => tls_files.set_client_ca_file(client_ca_file),
+        None => tls_files,
+    })
+}
+
+fn collect_mode(poll_interval: Option<u64>, min_interval: Option<u64>) -> CollectMode {
+    match (poll_interval, min_interval) {
+        (Some(seconds), _) => CollectMode::Background(Duration::from_secs(seconds)),
+        (None, Some(seconds)) => CollectMode::MinInterval(Duration::from_secs(seconds)),
+        (None, None) => CollectMode::OnDemand,
     }
 }
+
+fn parse_octal_mode(input: &str) -> Result<u32, std::num::ParseIntError> {
+    u32::from_str_radix(input, 8)
+}
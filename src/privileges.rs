@@ -0,0 +1,173 @@
+//! Dropping super-user privileges after binding the listeners
+
+use nix::unistd::{Gid, Group, Uid, User};
+use std::{ffi::CString, path::Path};
+
+/// Target user and group to run as, after binding the listeners
+#[derive(Clone, Debug)]
+pub struct RunAs {
+    user_name: String,
+    uid: Uid,
+    gid: Gid,
+}
+impl RunAs {
+    /// Looks up the user and group (by name or numeric id), defaulting to the primary group of
+    /// the user
+    ///
+    /// # Errors
+    /// Returns an error if the user or group does not exist, or the user is the super-user
+    pub fn lookup(user: &str, group: Option<&str>) -> Result<Self, Error> {
+        let make_error = |kind| Error { kind };
+
+        let found_user = match user.parse() {
+            Ok(uid) => User::from_uid(Uid::from_raw(uid)),
+            Err(_) => User::from_name(user),
+        }
+        .map_err(|errno| ErrorKind::Lookup(errno.into()))
+        .map_err(make_error)?
+        .ok_or_else(|| make_error(ErrorKind::UnknownUser(user.to_owned())))?;
+        if found_user.uid.is_root() {
+            return Err(make_error(ErrorKind::SuperUser(user.to_owned())));
+        }
+
+        let gid = match group {
+            None => found_user.gid,
+            Some(group) => {
+                match group.parse() {
+                    Ok(gid) => Group::from_gid(Gid::from_raw(gid)),
+                    Err(_) => Group::from_name(group),
+                }
+                .map_err(|errno| ErrorKind::Lookup(errno.into()))
+                .map_err(make_error)?
+                .ok_or_else(|| make_error(ErrorKind::UnknownGroup(group.to_owned())))?
+                .gid
+            }
+        };
+
+        Ok(Self {
+            user_name: found_user.name,
+            uid: found_user.uid,
+            gid,
+        })
+    }
+
+    /// Changes the owner of a Unix domain socket file bound before dropping privileges, so the
+    /// file remains usable (and removable at shutdown)
+    pub(crate) fn chown(&self, path: &Path) -> Result<(), Error> {
+        nix::unistd::chown(path, Some(self.uid), Some(self.gid)).map_err(|errno| Error {
+            kind: ErrorKind::Chown {
+                path: path.to_owned(),
+                io_error: errno.into(),
+            },
+        })
+    }
+
+    /// Switches the process (all threads) to the user and group, with the supplementary groups of
+    /// the user (e.g. for group access to `/dev/zfs`)
+    ///
+    /// # Errors
+    /// Returns an error if switching fails, or the super-user privileges can be regained
+    pub fn apply(&self) -> Result<(), Error> {
+        let make_error = |kind| Error { kind };
+
+        // only the super-user may change the supplementary groups (not needed otherwise)
+        if Uid::effective().is_root() {
+            let user_name = CString::new(self.user_name.clone())
+                .map_err(|_| make_error(ErrorKind::UnknownUser(self.user_name.clone())))?;
+            nix::unistd::initgroups(&user_name, self.gid)
+                .map_err(|errno| make_error(ErrorKind::SetGroups(errno.into())))?;
+        }
+        nix::unistd::setgid(self.gid)
+            .map_err(|errno| make_error(ErrorKind::SetGid(errno.into())))?;
+        nix::unistd::setuid(self.uid)
+            .map_err(|errno| make_error(ErrorKind::SetUid(errno.into())))?;
+
+        // the saved set-user-id is also replaced, so privileges cannot be regained
+        if nix::unistd::setuid(Uid::from_raw(0)).is_ok() {
+            return Err(make_error(ErrorKind::Regained));
+        }
+        Ok(())
+    }
+
+    /// Prints the user and groups switched to by [`Self::apply`]
+    pub fn print_start_message(&self) {
+        let Self {
+            user_name,
+            uid,
+            gid,
+        } = self;
+        match nix::unistd::getgroups() {
+            Ok(mut groups) => {
+                groups.sort_by_key(|gid| gid.as_raw());
+                let groups = groups
+                    .iter()
+                    .map(Gid::to_string)
+                    .collect::<Vec<_>>()
+                    .join(",");
+                println!(
+                    "Dropped privileges to user {user_name} (uid {uid}, gid {gid}, groups {groups})"
+                );
+            }
+            Err(_) => println!("Dropped privileges to user {user_name} (uid {uid}, gid {gid})"),
+        }
+    }
+}
+
+/// Failure to look up the target user, or to drop privileges
+#[derive(Debug)]
+pub struct Error {
+    kind: ErrorKind,
+}
+#[derive(Debug)]
+enum ErrorKind {
+    Lookup(std::io::Error),
+    UnknownUser(String),
+    UnknownGroup(String),
+    SuperUser(String),
+    Chown {
+        path: std::path::PathBuf,
+        io_error: std::io::Error,
+    },
+    SetGroups(std::io::Error),
+    SetGid(std::io::Error),
+    SetUid(std::io::Error),
+    Regained,
+}
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match &self.kind {
+            ErrorKind::Lookup(io_error)
+            | ErrorKind::Chown { io_error, .. }
+            | ErrorKind::SetGroups(io_error)
+            | ErrorKind::SetGid(io_error)
+            | ErrorKind::SetUid(io_error) => Some(io_error),
+            ErrorKind::UnknownUser(_)
+            | ErrorKind::UnknownGroup(_)
+            | ErrorKind::SuperUser(_)
+            | ErrorKind::Regained => None,
+        }
+    }
+}
+impl std::fmt::Display for Error {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let Self { kind } = self;
+        match kind {
+            ErrorKind::Lookup(_) => write!(f, "failed to look up the user database"),
+            ErrorKind::UnknownUser(user) => write!(f, "unknown user {user:?}"),
+            ErrorKind::UnknownGroup(group) => write!(f, "unknown group {group:?}"),
+            ErrorKind::SuperUser(user) => {
+                write!(
+                    f,
+                    "user {user:?} is the super-user, try a non-privileged user"
+                )
+            }
+            ErrorKind::Chown { path, io_error: _ } => {
+                write!(f, "failed to change the owner of {}", path.display())
+            }
+            ErrorKind::SetGroups(_) => write!(f, "failed to set the supplementary groups"),
+            ErrorKind::SetGid(_) => write!(f, "failed to switch group"),
+            ErrorKind::SetUid(_) => write!(f, "failed to switch user"),
+            ErrorKind::Regained => write!(f, "super-user privileges remain after switching user"),
+        }
+    }
+}
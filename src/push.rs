@@ -0,0 +1,212 @@
+//! Pushing the metrics to a Prometheus Pushgateway, for hosts the Prometheus server cannot reach
+
+use crate::{AppContext, MetricsError, server::calculate_delay_seconds};
+use base64::Engine as _;
+use std::time::Duration;
+
+/// Time limit for each request to the Pushgateway
+const REQUEST_TIMEOUT_SECS: u64 = 10;
+
+/// Pushgateway URL and grouping key for the metrics
+#[must_use]
+pub struct Pushgateway {
+    /// URL of the metrics group, including the grouping key
+    url: String,
+    /// Value of the `Authorization` header (if any)
+    authorization: Option<String>,
+    max_retries: u32,
+}
+impl Pushgateway {
+    /// Creates the target for the Pushgateway base URL (e.g. `http://pushgateway:9091`), grouping
+    /// the metrics by the `job` and `instance` labels
+    pub fn new(base_url: &str, job: &str, instance: &str) -> Self {
+        let url = format!(
+            "{base_url}/metrics/{job}/{instance}",
+            base_url = base_url.trim_end_matches('/'),
+            job = grouping_key_segment("job", job),
+            instance = grouping_key_segment("instance", instance),
+        );
+        Self {
+            url,
+            authorization: None,
+            max_retries: 3,
+        }
+    }
+    /// Sets the credentials for basic authentication
+    pub fn set_basic_auth(mut self, username: &str, password: &str) -> Self {
+        let credentials = base64::prelude::BASE64_STANDARD.encode(format!("{username}:{password}"));
+        self.authorization = Some(format!("Basic {credentials}"));
+        self
+    }
+    /// Sets the maximum number of retries for each push (0 = no retries, just 1 attempt)
+    pub fn set_max_retries(mut self, max_retries: u32) -> Self {
+        self.max_retries = max_retries;
+        self
+    }
+
+    /// Replaces the metrics of the group, retrying with exponential backoff on connection errors
+    /// and server errors
+    fn put_with_retry(&self, metrics: &str) -> Result<(), PushError> {
+        let mut attempt = 1;
+        loop {
+            match self.put(metrics) {
+                Ok(()) => {
+                    if attempt > 1 {
+                        println!("Successfully pushed to {} on attempt {attempt}", self.url);
+                    }
+                    return Ok(());
+                }
+                Err(err) if err.is_retryable() && attempt <= self.max_retries => {
+                    let delay_secs = calculate_delay_seconds(attempt);
+                    eprintln!("Push attempt {attempt} failed: {err}. Retrying in {delay_secs}s...");
+                    std::thread::sleep(Duration::from_secs(delay_secs));
+
+                    attempt += 1;
+                }
+                Err(err) => return Err(err),
+            }
+        }
+    }
+    fn put(&self, metrics: &str) -> Result<(), PushError> {
+        let url = || self.url.clone();
+        let make_error = |kind| PushError { kind };
+
+        let mut request = minreq::put(&self.url)
+            .with_header("Content-Type", "text/plain; version=0.0.4")
+            .with_body(metrics)
+            .with_timeout(REQUEST_TIMEOUT_SECS);
+        if let Some(authorization) = &self.authorization {
+            request = request.with_header("Authorization", authorization);
+        }
+        let response = request.send().map_err(|error| {
+            make_error(ErrorKind::Request {
+                url: url(),
+                error: Box::new(error),
+            })
+        })?;
+
+        if (200..300).contains(&response.status_code) {
+            Ok(())
+        } else {
+            Err(make_error(ErrorKind::Status {
+                url: url(),
+                status_code: response.status_code,
+                body: response.as_str().unwrap_or_default().trim().to_owned(),
+            }))
+        }
+    }
+}
+
+/// Returns the URL path segment for the label, base64-encoded if the value is not URL-safe (or
+/// empty)
+fn grouping_key_segment(label: &str, value: &str) -> String {
+    let is_url_safe = !value.is_empty()
+        && value
+            .bytes()
+            .all(|byte| byte.is_ascii_alphanumeric() || b"-._~".contains(&byte));
+    if is_url_safe {
+        format!("{label}/{value}")
+    } else if value.is_empty() {
+        // the empty value is encoded as a single padding character
+        format!("{label}@base64/=")
+    } else {
+        let value = base64::prelude::BASE64_URL_SAFE.encode(value);
+        format!("{label}@base64/{value}")
+    }
+}
+
+impl AppContext {
+    /// Collects the metrics and pushes them to the Pushgateway, replacing the metrics of the group
+    ///
+    /// When the collection fails, pushes the error description and `up` = 0 (rather than leaving
+    /// the stale metrics of an earlier collection).
+    ///
+    /// # Errors
+    /// Returns an error if the collection fails (after pushing), or all push attempts fail
+    pub fn push_metrics(&self, pushgateway: &Pushgateway) -> Result<(), PushError> {
+        let (metrics, result) = self.get_metrics_document();
+
+        pushgateway.put_with_retry(&metrics)?;
+        result.map_err(|err| PushError {
+            kind: ErrorKind::Collect(err),
+        })
+    }
+}
+
+/// Failure to collect the metrics, or to push them to the Pushgateway
+#[derive(Debug)]
+pub struct PushError {
+    kind: ErrorKind,
+}
+#[derive(Debug)]
+enum ErrorKind {
+    Collect(MetricsError),
+    Request {
+        url: String,
+        /// Boxed, as the request error is large
+        error: Box<minreq::Error>,
+    },
+    Status {
+        url: String,
+        status_code: i32,
+        body: String,
+    },
+}
+impl PushError {
+    fn is_retryable(&self) -> bool {
+        match &self.kind {
+            ErrorKind::Request { .. } => true,
+            ErrorKind::Status { status_code, .. } => *status_code >= 500,
+            ErrorKind::Collect(_) => false,
+        }
+    }
+}
+impl std::error::Error for PushError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match &self.kind {
+            ErrorKind::Collect(error) => Some(error),
+            ErrorKind::Request { error, .. } => Some(&**error),
+            ErrorKind::Status { .. } => None,
+        }
+    }
+}
+impl std::fmt::Display for PushError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let Self { kind } = self;
+        match kind {
+            ErrorKind::Collect(_) => write!(f, "failed to create metrics (pushed as up = 0)"),
+            ErrorKind::Request { url, error: _ } => write!(f, "failed to push metrics to {url}"),
+            ErrorKind::Status {
+                url,
+                status_code,
+                body,
+            } => write!(
+                f,
+                "failed to push metrics to {url}, status {status_code}: {body:?}"
+            ),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Pushgateway;
+
+    #[test]
+    fn grouping_key_url() {
+        let url = |job, instance| Pushgateway::new("http://gateway:9091/", job, instance).url;
+
+        assert_eq!(
+            url("zpool", "nas-1.example"),
+            "http://gateway:9091/metrics/job/zpool/instance/nas-1.example"
+        );
+        assert_eq!(
+            url("zpool", "host/1"),
+            "http://gateway:9091/metrics/job/zpool/instance@base64/aG9zdC8x"
+        );
+        assert_eq!(
+            url("", "nas"),
+            "http://gateway:9091/metrics/job@base64/=/instance/nas"
+        );
+    }
+}
@@ -0,0 +1,110 @@
+//! Counters about the exporter itself, shared across requests
+
+use std::{
+    sync::{Mutex, PoisonError},
+    time::{Duration, Instant},
+};
+
+/// Exporter self-monitoring state
+#[derive(Default)]
+pub(crate) struct Stats(Mutex<Snapshot>);
+
+/// Point-in-time copy of the [`Stats`]
+#[derive(Clone, Default)]
+pub(crate) struct Snapshot {
+    /// Count of requests to the metrics endpoint
+    pub scrapes_total: u64,
+    /// Count of failed collections, indexed by the position in [`CollectorErrorKind::ALL`]
+    pub collector_errors_total: [u64; CollectorErrorKind::ALL.len()],
+    /// Duration of the most recent collection (successful or not)
+    pub collector_duration: Option<Duration>,
+    /// Time of the most recent request to the metrics endpoint
+    pub last_scrape: Option<Instant>,
+}
+
+impl Stats {
+    pub fn record_scrape(&self) {
+        let mut snapshot = self.lock();
+        snapshot.scrapes_total += 1;
+        snapshot.last_scrape = Some(Instant::now());
+    }
+    pub fn record_collection(&self, duration: Duration, error: Option<CollectorErrorKind>) {
+        let mut snapshot = self.lock();
+        snapshot.collector_duration = Some(duration);
+        if let Some(error) = error {
+            snapshot.collector_errors_total[error as usize] += 1;
+        }
+    }
+    pub fn snapshot(&self) -> Snapshot {
+        self.lock().clone()
+    }
+    fn lock(&self) -> std::sync::MutexGuard<'_, Snapshot> {
+        // counters remain valid even if a holder panicked
+        self.0.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+enum_all! {
+    /// Cause of a failed collection (running `zpool status` and parsing the output)
+    ///
+    /// `ALL` is in declaration order, matching the discriminant
+    #[derive(Clone, Copy, Debug)]
+    pub(crate) enum CollectorErrorKind {
+        ChildSpawn,
+        ChildStatus,
+        ChildOutput,
+        ChildTerminate,
+        Timeout,
+        NonUtf8Output,
+        ChildFailed,
+        EmptyOutput,
+        ZfsParse,
+    }
+}
+impl CollectorErrorKind {
+    /// Label value for the `kind` label
+    pub fn label(self) -> &'static str {
+        match self {
+            Self::ChildSpawn => "child_spawn",
+            Self::ChildStatus => "child_status",
+            Self::ChildOutput => "child_output",
+            Self::ChildTerminate => "child_terminate",
+            Self::Timeout => "timeout",
+            Self::NonUtf8Output => "non_utf8_output",
+            Self::ChildFailed => "child_failed",
+            Self::EmptyOutput => "empty_output",
+            Self::ZfsParse => "zfs_parse",
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{CollectorErrorKind, Stats};
+    use std::time::Duration;
+
+    #[test]
+    fn all_matches_discriminant() {
+        for (index, &kind) in CollectorErrorKind::ALL.iter().enumerate() {
+            assert_eq!(kind as usize, index, "{kind:?}");
+        }
+    }
+
+    #[test]
+    fn record_collection() {
+        let stats = Stats::default();
+        stats.record_scrape();
+        stats.record_collection(Duration::from_millis(5), None);
+        stats.record_collection(Duration::from_millis(7), Some(CollectorErrorKind::Timeout));
+
+        let snapshot = stats.snapshot();
+        assert_eq!(snapshot.scrapes_total, 1);
+        assert!(snapshot.last_scrape.is_some());
+        assert_eq!(snapshot.collector_duration, Some(Duration::from_millis(7)));
+        assert_eq!(
+            snapshot.collector_errors_total[CollectorErrorKind::Timeout as usize],
+            1
+        );
+        assert_eq!(snapshot.collector_errors_total.iter().sum::<u64>(), 1);
+    }
+}
@@ -0,0 +1,84 @@
+//! Writing the metrics into a `node_exporter` textfile-collector directory
+
+use crate::{AppContext, MetricsError};
+use std::{
+    io::Write as _,
+    path::{Path, PathBuf},
+};
+
+/// Name of the metrics file in the directory (the textfile collector reads only `*.prom`)
+const FILE_NAME: &str = "zpool.prom";
+/// Name of the file being written, renamed over [`FILE_NAME`] when complete
+const TEMP_FILE_NAME: &str = ".zpool.prom.tmp";
+
+impl AppContext {
+    /// Collects the metrics and replaces `zpool.prom` in the directory, atomically (the
+    /// collector never reads a partial file)
+    ///
+    /// When the collection fails, the file contains the error description and `up` = 0 (rather
+    /// than the stale metrics of an earlier collection).
+    ///
+    /// # Errors
+    /// Returns an error if the collection fails (after writing the file), or writing the file fails
+    pub fn write_textfile(&self, dir: &Path) -> Result<(), TextfileError> {
+        let (contents, result) = self.get_metrics_document();
+
+        write_atomic(dir, contents.as_bytes())?;
+        result.map_err(|err| TextfileError {
+            kind: ErrorKind::Collect(err),
+        })
+    }
+}
+
+/// Writes the temporary file, then renames it over the metrics file
+fn write_atomic(dir: &Path, contents: &[u8]) -> Result<(), TextfileError> {
+    let temp_path = dir.join(TEMP_FILE_NAME);
+    let make_error = |path: &Path| {
+        let path = path.to_owned();
+        move |io_error| TextfileError {
+            kind: ErrorKind::Write { path, io_error },
+        }
+    };
+
+    let mut file = std::fs::File::create(&temp_path).map_err(make_error(&temp_path))?;
+    file.write_all(contents)
+        .and_then(|()| file.sync_all())
+        .map_err(make_error(&temp_path))?;
+    drop(file);
+
+    let path = dir.join(FILE_NAME);
+    std::fs::rename(&temp_path, &path).map_err(make_error(&path))
+}
+
+/// Failure to collect the metrics, or to write the metrics file
+#[derive(Debug)]
+pub struct TextfileError {
+    kind: ErrorKind,
+}
+#[derive(Debug)]
+enum ErrorKind {
+    Collect(MetricsError),
+    Write {
+        path: PathBuf,
+        io_error: std::io::Error,
+    },
+}
+impl std::error::Error for TextfileError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match &self.kind {
+            ErrorKind::Collect(error) => Some(error),
+            ErrorKind::Write { io_error, .. } => Some(io_error),
+        }
+    }
+}
+impl std::fmt::Display for TextfileError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let Self { kind } = self;
+        match kind {
+            ErrorKind::Collect(_) => write!(f, "failed to create metrics (recorded as up = 0)"),
+            ErrorKind::Write { path, io_error: _ } => {
+                write!(f, "failed to write metrics file {}", path.display())
+            }
+        }
+    }
+}
@@ -0,0 +1,407 @@
+//! TLS termination for the HTTP listener
+//!
+//! The HTTP server does not support client certificates or reloading certificates, so TLS
+//! connections are decrypted here and forwarded to the HTTP server on an internal loopback address.
+//!
+//! Requests reaching the HTTP server are only accepted if forwarded by the [`Proxy`] (see
+//! [`Proxy::peer_addr`]), so local users cannot bypass the client certificate verification.
+
+pub(crate) use config::Error as ConfigError;
+use config::Reloader;
+use std::{
+    collections::HashMap,
+    io::{self, Read as _, Write as _},
+    net::{SocketAddr, TcpListener, TcpStream},
+    path::PathBuf,
+    sync::{
+        Arc, Mutex, PoisonError,
+        atomic::{AtomicBool, AtomicUsize, Ordering},
+    },
+    time::Duration,
+};
+
+/// Maximum number of concurrent TLS connections (additional connections are closed)
+const MAX_CONNECTIONS: usize = 64;
+/// Duration without traffic in either direction before closing a TLS connection
+const IDLE_TIMEOUT: Duration = Duration::from_mins(1);
+
+/// Certificate files for serving HTTPS
+///
+/// The files are re-read when modified, for new connections
+#[derive(Clone)]
+#[must_use]
+pub struct Files {
+    cert: PathBuf,
+    key: PathBuf,
+    client_ca: Option<PathBuf>,
+}
+impl Files {
+    /// PEM files for the server certificate chain and private key
+    pub fn new(cert_file: PathBuf, key_file: PathBuf) -> Self {
+        Self {
+            cert: cert_file,
+            key: key_file,
+            client_ca: None,
+        }
+    }
+    /// Requires clients to present a certificate signed by a CA in the specified PEM file
+    pub fn set_client_ca_file(mut self, client_ca_file: PathBuf) -> Self {
+        self.client_ca = Some(client_ca_file);
+        self
+    }
+}
+
+/// Listener for TLS connections, forwarding the decrypted traffic to the HTTP server
+pub(crate) struct Proxy {
+    listener: TcpListener,
+    stopping: AtomicBool,
+    shared: Arc<Shared>,
+}
+struct Shared {
+    upstream: SocketAddr,
+    reloader: Reloader,
+    /// Client address of each forwarded connection, by the local address of the upstream side
+    peers: Mutex<HashMap<SocketAddr, SocketAddr>>,
+    connections: AtomicUsize,
+}
+
+impl Proxy {
+    /// Loads the TLS files, for forwarding connections from `listener` to `upstream`
+    pub fn new(
+        listener: TcpListener,
+        upstream: SocketAddr,
+        files: Files,
+    ) -> Result<Self, ConfigError> {
+        let reloader = Reloader::new(files)?;
+        Ok(Self {
+            listener,
+            stopping: AtomicBool::new(false),
+            shared: Arc::new(Shared {
+                upstream,
+                reloader,
+                peers: Mutex::default(),
+                connections: AtomicUsize::new(0),
+            }),
+        })
+    }
+
+    /// Accepts connections in the current thread, until [`Self::stop`] is called
+    pub fn run(&self) {
+        for client in self.listener.incoming() {
+            if self.stopping.load(Ordering::SeqCst) {
+                break;
+            }
+            let client = match client {
+                Ok(client) => client,
+                Err(error) => {
+                    eprintln!("failed to accept TLS connection: {error}");
+                    continue;
+                }
+            };
+
+            let shared = Arc::clone(&self.shared);
+            if shared.connections.fetch_add(1, Ordering::SeqCst) >= MAX_CONNECTIONS {
+                shared.connections.fetch_sub(1, Ordering::SeqCst);
+                continue;
+            }
+            // not joined, connections end when idle (or at process exit)
+            std::thread::spawn(move || {
+                let peer = client.peer_addr();
+                if let Err(error) = shared.forward(client) {
+                    match peer {
+                        Ok(peer) => eprintln!("TLS connection from {peer} failed: {error}"),
+                        Err(_) => eprintln!("TLS connection failed: {error}"),
+                    }
+                }
+                shared.connections.fetch_sub(1, Ordering::SeqCst);
+            });
+        }
+    }
+
+    /// Signals [`Self::run`] to return
+    pub fn stop(&self) {
+        self.stopping.store(true, Ordering::SeqCst);
+        // wake the blocking accept
+        if let Ok(local_addr) = self.listener.local_addr() {
+            let _ = TcpStream::connect(local_addr);
+        }
+    }
+
+    /// Returns the TLS client address for a connection to the HTTP server
+    ///
+    /// Returns `None` if the connection was not forwarded by this proxy
+    pub fn peer_addr(&self, upstream_remote: SocketAddr) -> Option<SocketAddr> {
+        self.shared.lock_peers().get(&upstream_remote).copied()
+    }
+}
+
+impl Shared {
+    fn lock_peers(&self) -> std::sync::MutexGuard<'_, HashMap<SocketAddr, SocketAddr>> {
+        // entries remain valid even if a holder panicked
+        self.peers.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    fn forward(&self, mut client: TcpStream) -> io::Result<()> {
+        let peer = client.peer_addr()?;
+        client.set_write_timeout(Some(IDLE_TIMEOUT))?;
+
+        let mut tls =
+            rustls::ServerConnection::new(self.reloader.current()).map_err(io::Error::other)?;
+
+        let mut upstream = TcpStream::connect(self.upstream)?;
+        upstream.set_write_timeout(Some(IDLE_TIMEOUT))?;
+        let upstream_local = upstream.local_addr()?;
+
+        self.lock_peers().insert(upstream_local, peer);
+        let result = pump(&mut tls, &mut client, &mut upstream);
+        self.lock_peers().remove(&upstream_local);
+
+        result
+    }
+}
+
+/// Copies traffic between the TLS client and the upstream HTTP server, until either side closes
+fn pump(
+    tls: &mut rustls::ServerConnection,
+    client: &mut TcpStream,
+    upstream: &mut TcpStream,
+) -> io::Result<()> {
+    use nix::poll::{PollFd, PollFlags, PollTimeout, poll};
+    use std::os::fd::AsFd as _;
+
+    let timeout = PollTimeout::try_from(IDLE_TIMEOUT).map_err(io::Error::other)?;
+    let mut buf = vec![0; 16 * 1024];
+    loop {
+        // handshake, alerts, and forwarded responses
+        while tls.wants_write() {
+            tls.write_tls(client)?;
+        }
+
+        let readable = PollFlags::POLLIN | PollFlags::POLLHUP | PollFlags::POLLERR;
+        let mut fds = [
+            PollFd::new(client.as_fd(), PollFlags::POLLIN),
+            PollFd::new(upstream.as_fd(), PollFlags::POLLIN),
+        ];
+        match poll(&mut fds, timeout) {
+            Ok(0) => return Ok(()), // idle
+            Ok(_) => {}
+            Err(nix::errno::Errno::EINTR) => continue,
+            Err(errno) => return Err(errno.into()),
+        }
+        let is_ready = |fd: &PollFd| {
+            fd.revents()
+                .is_some_and(|events| events.intersects(readable))
+        };
+        let [client_ready, upstream_ready] = fds.each_ref().map(is_ready);
+
+        if client_ready {
+            if tls.read_tls(client)? == 0 {
+                return Ok(());
+            }
+            if let Err(error) = tls.process_new_packets() {
+                // send the alert (if any), ignoring errors as the connection is failed anyway
+                let _ = tls.write_tls(client);
+                return Err(io::Error::new(io::ErrorKind::InvalidData, error));
+            }
+            loop {
+                match tls.reader().read(&mut buf) {
+                    Ok(0) => return Ok(()), // close notify
+                    Ok(len) => upstream.write_all(&buf[..len])?,
+                    Err(error) if error.kind() == io::ErrorKind::WouldBlock => break,
+                    Err(error) if error.kind() == io::ErrorKind::UnexpectedEof => return Ok(()),
+                    Err(error) => return Err(error),
+                }
+            }
+        }
+
+        if upstream_ready {
+            let len = upstream.read(&mut buf)?;
+            if len == 0 {
+                tls.send_close_notify();
+                while tls.wants_write() {
+                    tls.write_tls(client)?;
+                }
+                return Ok(());
+            }
+            tls.writer().write_all(&buf[..len])?;
+        }
+    }
+}
+
+mod config {
+    use super::Files;
+    use rustls::{
+        RootCertStore, ServerConfig,
+        pki_types::{CertificateDer, PrivateKeyDer, pem::PemObject as _},
+        server::WebPkiClientVerifier,
+    };
+    use std::{
+        path::{Path, PathBuf},
+        sync::{Arc, Mutex, PoisonError},
+        time::SystemTime,
+    };
+
+    /// Server configuration, rebuilt when the files are modified
+    pub(super) struct Reloader {
+        files: Files,
+        state: Mutex<State>,
+    }
+    struct State {
+        modified: Vec<Option<SystemTime>>,
+        config: Arc<ServerConfig>,
+    }
+
+    impl Reloader {
+        /// Loads the files (initial load must succeed)
+        pub fn new(files: Files) -> Result<Self, Error> {
+            let modified = files.modified();
+            let config = files.load()?;
+            Ok(Self {
+                files,
+                state: Mutex::new(State { modified, config }),
+            })
+        }
+
+        /// Returns the configuration for a new connection, reloading if any file was modified
+        ///
+        /// Keeps the previous configuration if reloading fails
+        pub fn current(&self) -> Arc<ServerConfig> {
+            // configuration remains valid even if a holder panicked
+            let mut state = self.state.lock().unwrap_or_else(PoisonError::into_inner);
+
+            let modified = self.files.modified();
+            if modified != state.modified {
+                // retry only after the next modification (e.g. cert and key written separately)
+                state.modified = modified;
+                match self.files.load() {
+                    Ok(config) => {
+                        println!("Reloaded TLS certificate files");
+                        state.config = config;
+                    }
+                    Err(error) => {
+                        eprintln!("failed to reload TLS certificate files: {error}");
+                    }
+                }
+            }
+            Arc::clone(&state.config)
+        }
+    }
+
+    impl Files {
+        fn paths(&self) -> impl Iterator<Item = &Path> {
+            [&self.cert, &self.key]
+                .into_iter()
+                .chain(&self.client_ca)
+                .map(PathBuf::as_path)
+        }
+        fn modified(&self) -> Vec<Option<SystemTime>> {
+            self.paths()
+                .map(|path| {
+                    std::fs::metadata(path)
+                        .and_then(|meta| meta.modified())
+                        .ok()
+                })
+                .collect()
+        }
+        fn load(&self) -> Result<Arc<ServerConfig>, Error> {
+            let Self {
+                cert: cert_file,
+                key: key_file,
+                client_ca: client_ca_file,
+            } = self;
+
+            let provider = Arc::new(rustls::crypto::ring::default_provider());
+
+            let certs = read_certs(cert_file)?;
+            let key = PrivateKeyDer::from_pem_file(key_file)
+                .map_err(ErrorKind::Pem)
+                .map_err(Error::with_file(key_file))?;
+
+            let builder = ServerConfig::builder_with_provider(Arc::clone(&provider))
+                .with_safe_default_protocol_versions()
+                .expect("ring provider supports the default protocol versions");
+
+            let builder = if let Some(client_ca_file) = client_ca_file {
+                let make_error = Error::with_file(client_ca_file);
+                let mut roots = RootCertStore::empty();
+                for cert in read_certs(client_ca_file)? {
+                    roots
+                        .add(cert)
+                        .map_err(ErrorKind::Invalid)
+                        .map_err(make_error)?;
+                }
+                let verifier =
+                    WebPkiClientVerifier::builder_with_provider(Arc::new(roots), provider)
+                        .build()
+                        .map_err(ErrorKind::ClientVerifier)
+                        .map_err(make_error)?;
+                builder.with_client_cert_verifier(verifier)
+            } else {
+                builder.with_no_client_auth()
+            };
+
+            let config = builder
+                .with_single_cert(certs, key)
+                .map_err(ErrorKind::Invalid)
+                .map_err(Error::with_file(cert_file))?;
+            Ok(Arc::new(config))
+        }
+    }
+
+    fn read_certs(file: &Path) -> Result<Vec<CertificateDer<'static>>, Error> {
+        let make_error = Error::with_file(file);
+        let certs = CertificateDer::pem_file_iter(file)
+            .and_then(Iterator::collect::<Result<Vec<_>, _>>)
+            .map_err(ErrorKind::Pem)
+            .map_err(make_error)?;
+        if certs.is_empty() {
+            Err(make_error(ErrorKind::NoCertificates))
+        } else {
+            Ok(certs)
+        }
+    }
+
+    /// Error loading the TLS files
+    #[derive(Debug)]
+    pub(crate) struct Error {
+        file: PathBuf,
+        kind: ErrorKind,
+    }
+    #[derive(Debug)]
+    enum ErrorKind {
+        Pem(rustls::pki_types::pem::Error),
+        NoCertificates,
+        Invalid(rustls::Error),
+        ClientVerifier(rustls::server::VerifierBuilderError),
+    }
+    impl Error {
+        fn with_file(file: &Path) -> impl Fn(ErrorKind) -> Self + Copy {
+            move |kind| Self {
+                file: file.to_owned(),
+                kind,
+            }
+        }
+    }
+    impl std::error::Error for Error {
+        fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+            match &self.kind {
+                ErrorKind::Pem(error) => Some(error),
+                ErrorKind::NoCertificates => None,
+                ErrorKind::Invalid(error) => Some(error),
+                ErrorKind::ClientVerifier(error) => Some(error),
+            }
+        }
+    }
+    impl std::fmt::Display for Error {
+        fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+            let Self { file, kind } = self;
+            let description = match kind {
+                ErrorKind::Pem(_error) => "failed to read PEM",
+                ErrorKind::NoCertificates => "no certificates in",
+                ErrorKind::Invalid(_error) => "invalid certificate in",
+                ErrorKind::ClientVerifier(_error) => "invalid client CA certificates in",
+            };
+            write!(f, "{description} file {}", file.display())
+        }
+    }
+}
@@ -0,0 +1,156 @@
+//! Liveness of the serve loop and the collector, for the service manager watchdog
+
+use crate::{AppContext, stats::Snapshot as StatsSnapshot};
+use std::{
+    sync::{Mutex, PoisonError},
+    time::{Duration, Instant},
+};
+
+/// Signal that the server is alive (or stalled), with a summary of the last collection
+#[derive(Debug)]
+pub struct Heartbeat {
+    status: String,
+    alive: bool,
+}
+impl Heartbeat {
+    /// Summary of the last collection, e.g. "3 pools, last scrape 2s ago, 0 errors", or a
+    /// description of the stalled work
+    #[must_use]
+    pub fn status(&self) -> &str {
+        &self.status
+    }
+    /// Returns true if the serve loop and the collector are making progress (send the watchdog
+    /// keepalive)
+    #[must_use]
+    pub fn is_alive(&self) -> bool {
+        self.alive
+    }
+}
+
+/// In-progress work (requests or collections), to detect when all of it stops completing
+pub(crate) struct Progress {
+    state: Mutex<ProgressState>,
+    /// Number of concurrent units of work that make the progress stalled when all are running
+    capacity: usize,
+}
+#[derive(Default)]
+struct ProgressState {
+    running: usize,
+    /// Start of the oldest idle period or most recent completion, while running
+    since: Option<Instant>,
+}
+impl Progress {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            state: Mutex::default(),
+            capacity,
+        }
+    }
+    /// Marks the start of a unit of work, finished when the guard drops
+    pub fn start(&self) -> ProgressGuard<'_> {
+        let mut state = self.lock();
+        if state.running == 0 {
+            state.since = Some(Instant::now());
+        }
+        state.running += 1;
+        ProgressGuard(self)
+    }
+    /// Returns the duration without any completed work while at capacity (if any)
+    pub fn stalled_for(&self) -> Option<Duration> {
+        let state = self.lock();
+        let since = state.since.filter(|_| state.running >= self.capacity)?;
+        Some(since.elapsed())
+    }
+    fn lock(&self) -> std::sync::MutexGuard<'_, ProgressState> {
+        // counts remain valid even if a holder panicked
+        self.state.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+}
+pub(crate) struct ProgressGuard<'a>(&'a Progress);
+impl Drop for ProgressGuard<'_> {
+    fn drop(&mut self) {
+        let mut state = self.0.lock();
+        state.running -= 1;
+        state.since = (state.running > 0).then(Instant::now);
+    }
+}
+
+impl AppContext {
+    /// Returns the heartbeat for the current state of the collector and the request workers
+    pub(crate) fn heartbeat<'a>(
+        &self,
+        requests: impl IntoIterator<Item = &'a Progress>,
+    ) -> Heartbeat {
+        // twice the `zpool status` timeout, without progress
+        let stall_limit = self.zpool_command.timeout() * 2;
+        let stalled = |progress: &Progress| {
+            progress
+                .stalled_for()
+                .filter(|&duration| duration >= stall_limit)
+        };
+        let stall = if let Some(duration) = stalled(self.collector.progress()) {
+            Some(("collection", duration))
+        } else {
+            requests
+                .into_iter()
+                .find_map(stalled)
+                .map(|duration| ("all request workers", duration))
+        };
+        match stall {
+            Some((what, duration)) => Heartbeat {
+                status: format!("stalled, {what} busy for {}s", duration.as_secs()),
+                alive: false,
+            },
+            None => Heartbeat {
+                status: self.status_summary(),
+                alive: true,
+            },
+        }
+    }
+
+    /// Returns a summary of the last collection, e.g. "3 pools, last scrape 2s ago, 0 errors"
+    fn status_summary(&self) -> String {
+        let pools = self
+            .collector
+            .last_success()
+            .map_or(0, |snapshot| snapshot.pools.len());
+        let pools_plural = if pools == 1 { "pool" } else { "pools" };
+
+        let StatsSnapshot {
+            collector_errors_total,
+            last_scrape,
+            ..
+        } = self.stats.snapshot();
+        let last_scrape = match last_scrape {
+            Some(instant) => format!("last scrape {}s ago", instant.elapsed().as_secs()),
+            None => "no scrapes yet".to_owned(),
+        };
+        let errors: u64 = collector_errors_total.iter().sum();
+        let errors_plural = if errors == 1 { "error" } else { "errors" };
+
+        format!("{pools} {pools_plural}, {last_scrape}, {errors} {errors_plural}")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Progress;
+
+    #[test]
+    fn stalled_at_capacity() {
+        let progress = Progress::new(2);
+        assert_eq!(progress.stalled_for(), None);
+
+        let first = progress.start();
+        assert_eq!(progress.stalled_for(), None);
+        {
+            let _second = progress.start();
+            assert!(progress.stalled_for().is_some());
+        }
+        // below capacity
+        assert_eq!(progress.stalled_for(), None);
+
+        drop(first);
+        assert_eq!(progress.stalled_for(), None);
+    }
+}
@@ -20,6 +20,17 @@ pub(crate) struct PoolMetrics {
     pub scan_status: Option<(ScanStatus, Option<jiff::Zoned>)>,
     pub devices: Vec<DeviceMetrics>,
     pub error: Option<ErrorStatus>,
+    pub text: PoolText,
+}
+
+/// Original content of the header entries (e.g. to report `Unrecognized` values)
+#[allow(missing_docs)]
+#[derive(Default)]
+pub(crate) struct PoolText {
+    pub state: Option<String>,
+    pub pool_status: Option<String>,
+    pub scan_status: Option<String>,
+    pub error: Option<String>,
 }
 
 #[allow(missing_docs)]
@@ -61,6 +72,7 @@ pub(super) enum ScanStatus {
     Resilvered,
     // misc
     ScrubInProgress,
+    ResilverInProgress,
     ScrubCanceled,
     /// Pool has never been scanned (new pool, no scan line in zpool status)
     NeverScanned,
@@ -83,8 +95,12 @@ pub(super) struct DeviceMetrics {
     pub depth: usize,
     /// Device name
     pub name: String,
+    /// Device type, derived from the name and depth
+    pub vdev_type: VdevType,
     /// Device status
     pub state: DeviceStatus,
+    /// Original device status text
+    pub state_text: String,
     /// Count of Read errors
     pub errors_read: u32,
     /// Count of Write errors
@@ -93,6 +109,65 @@ pub(super) struct DeviceMetrics {
     pub errors_checksum: u32,
 }
 
+/// Type of a device in the device tree
+#[allow(missing_docs)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) enum VdevType {
+    /// Pool root device (depth=0)
+    Root,
+    Mirror,
+    Raidz1,
+    Raidz2,
+    Raidz3,
+    Draid,
+    Spare,
+    Replacing,
+    /// Leaf device which is a file (absolute path)
+    File,
+    /// Leaf device, or any unknown device type
+    Disk,
+}
+impl VdevType {
+    /// Label used in the metrics and JSON output
+    pub fn label(self) -> &'static str {
+        match self {
+            Self::Root => "root",
+            Self::Mirror => "mirror",
+            Self::Raidz1 => "raidz1",
+            Self::Raidz2 => "raidz2",
+            Self::Raidz3 => "raidz3",
+            Self::Draid => "draid",
+            Self::Spare => "spare",
+            Self::Replacing => "replacing",
+            Self::File => "file",
+            Self::Disk => "disk",
+        }
+    }
+    /// Derives the type from the device name, e.g. "mirror-0" or "raidz2-1"
+    fn new(depth: usize, name: &str) -> Self {
+        if depth == 0 {
+            return Self::Root;
+        }
+        // interior vdev names have a numeric suffix "-N"
+        let kind = name
+            .rsplit_once('-')
+            .filter(|(_, index)| !index.is_empty() && index.bytes().all(|b| b.is_ascii_digit()))
+            .map(|(kind, _)| kind);
+        match kind {
+            Some("mirror") => Self::Mirror,
+            Some("raidz" | "raidz1") => Self::Raidz1,
+            Some("raidz2") => Self::Raidz2,
+            Some("raidz3") => Self::Raidz3,
+            // e.g. "draid2:4d:1c:0s"
+            Some(kind) if kind.starts_with("draid") => Self::Draid,
+            Some("spare") => Self::Spare,
+            Some("replacing") => Self::Replacing,
+            _ if name.starts_with('/') => Self::File,
+            _ => Self::Disk,
+        }
+    }
+}
+
 #[derive(Clone, Copy, Default, Debug)]
 enum ZpoolStatusSection {
     #[default]
@@ -311,6 +386,7 @@ impl PoolMetrics {
             scan_status: None,
             devices: vec![],
             error: None,
+            text: PoolText::default(),
         }
     }
     fn parse_line_device(&mut self, line: &str) -> Result<(), device_metrics::Error> {
@@ -376,11 +452,13 @@ mod metrics_line_header {
                 "status" => {
                     // status - a short description of the state
                     let new_pool_status = content.into();
+                    self.text.pool_status = Some(content.to_owned());
                     err_if_previous(self.pool_status.replace(new_pool_status)).map_err(make_error)
                 }
                 "state" => {
                     // state - single token, e.g. DEGRADED, ONLINE
                     let new_state = content.into();
+                    self.text.state = Some(content.to_owned());
                     err_if_previous(self.state.replace(new_state)).map_err(make_error)
                 }
                 "scan" => {
@@ -388,6 +466,7 @@ mod metrics_line_header {
                         .parse_scan_content(content)
                         .map_err(ErrorKind::ScanContent)
                         .map_err(make_error)?;
+                    self.text.scan_status = Some(content.to_owned());
                     err_if_previous(self.scan_status.replace(new_scan_status)).map_err(make_error)
                 }
                 "config" => {
@@ -401,6 +480,7 @@ mod metrics_line_header {
                 }
                 "errors" => {
                     let new_error = content.into();
+                    self.text.error = Some(content.to_owned());
                     err_if_previous(self.error.replace(new_error)).map_err(make_error)
                 }
                 "action" | "see" => {
@@ -496,6 +576,7 @@ mod scan_content {
                 ScanStatus::Unrecognized
                 | ScanStatus::ScrubRepaired
                 | ScanStatus::ScrubInProgress
+                | ScanStatus::ResilverInProgress
                 | ScanStatus::Resilvered => self
                     .parse_timestamp(timestamp)
                     .map_err(|err| {
@@ -556,7 +637,7 @@ mod scan_content {
 }
 
 mod device_metrics {
-    use super::DeviceMetrics;
+    use super::{DeviceMetrics, VdevType};
     use crate::zfs::DeviceStatus;
     use std::str::FromStr;
 
@@ -600,6 +681,8 @@ mod device_metrics {
                 .ok_or(ErrorKind::MissingName)
                 .map_err(make_error)?;
 
+            let vdev_type = VdevType::new(depth, &name);
+
             let make_error = |kind| Error {
                 device_name: Some(name.clone()),
                 kind,
@@ -615,11 +698,12 @@ mod device_metrics {
                     .map_err(make_error)
             };
 
-            let state = cells
+            let state_text = cells
                 .next()
-                .map(DeviceStatus::from)
+                .map(String::from)
                 .ok_or(ErrorKind::MissingState)
                 .map_err(make_error)?;
+            let state = DeviceStatus::from(state_text.as_str());
             let errors_read = parse_count(cells.next(), ErrorKind::MissingReadErrorCount)?;
             let errors_write = parse_count(cells.next(), ErrorKind::MissingWriteErrorCount)?;
             let errors_checksum = parse_count(cells.next(), ErrorKind::MissingChecksumErrorCount)?;
@@ -627,7 +711,9 @@ mod device_metrics {
             Ok(Self {
                 depth,
                 name,
+                vdev_type,
                 state,
+                state_text,
                 errors_read,
                 errors_write,
                 errors_checksum,
@@ -784,6 +870,8 @@ impl From<&str> for ScanStatus {
             Self::Resilvered
         } else if scan_status.starts_with("scrub in progress") {
             Self::ScrubInProgress
+        } else if scan_status.starts_with("resilver in progress") {
+            Self::ResilverInProgress
         } else if scan_status.starts_with("scrub canceled") {
             Self::ScrubCanceled
         } else {
@@ -810,3 +898,36 @@ impl From<&str> for ErrorStatus {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::VdevType;
+
+    #[test]
+    fn vdev_type_root() {
+        assert_eq!(VdevType::new(0, "mirror-0"), VdevType::Root);
+    }
+
+    #[test]
+    fn vdev_type_interior() {
+        assert_eq!(VdevType::new(1, "mirror-0"), VdevType::Mirror);
+        assert_eq!(VdevType::new(1, "raidz-1"), VdevType::Raidz1);
+        assert_eq!(VdevType::new(1, "raidz1-2"), VdevType::Raidz1);
+        assert_eq!(VdevType::new(1, "raidz2-10"), VdevType::Raidz2);
+        assert_eq!(VdevType::new(1, "raidz3-0"), VdevType::Raidz3);
+        assert_eq!(VdevType::new(1, "draid2:4d:1c:0s-0"), VdevType::Draid);
+        assert_eq!(VdevType::new(2, "spare-1"), VdevType::Spare);
+        assert_eq!(VdevType::new(2, "replacing-3"), VdevType::Replacing);
+    }
+
+    #[test]
+    fn vdev_type_leaf() {
+        assert_eq!(VdevType::new(2, "loop0"), VdevType::Disk);
+        assert_eq!(
+            VdevType::new(1, "wwn-0x5000c500af8ec287-part2"),
+            VdevType::Disk
+        );
+        assert_eq!(VdevType::new(2, "mirror-"), VdevType::Disk);
+        assert_eq!(VdevType::new(2, "/var/lib/zfs/file-0"), VdevType::File);
+    }
+}
@@ -0,0 +1,78 @@
+use crate::{
+    HTTP_FORBIDDEN, HTTP_OK,
+    common::bin_cmd::{BinCommand, BinOutput},
+};
+use std::{net::SocketAddr, str::FromStr};
+
+fn get_metrics_status(
+    listen_address: SocketAddr,
+    forwarded_for: Option<&str>,
+) -> anyhow::Result<i32> {
+    let mut request = minreq::get(format!("http://{listen_address}/metrics"));
+    if let Some(forwarded_for) = forwarded_for {
+        request = request.with_header("X-Forwarded-For", forwarded_for);
+    }
+    Ok(request.send()?.status_code)
+}
+
+#[test]
+fn allow_cidr_forwarded_for() -> anyhow::Result<()> {
+    const LISTEN_ADDRESS: &str = crate::common::LISTEN_ADDRESS_ALLOW_CIDR;
+
+    let listen_address = SocketAddr::from_str(LISTEN_ADDRESS)?;
+
+    let (output, statuses) = BinCommand::new()
+        .arg(LISTEN_ADDRESS)
+        .arg("--allow-cidr")
+        .arg("192.0.2.0/24,2001:db8::/32")
+        .arg("--trusted-proxy")
+        .arg("127.0.0.1")
+        .spawn_cleanup_with(|| {
+            let direct = get_metrics_status(listen_address, None)?;
+            let allowed = get_metrics_status(listen_address, Some("192.0.2.7"))?;
+            let allowed_v6 = get_metrics_status(listen_address, Some("2001:db8::7"))?;
+            // nearest hop is not allowed (earlier hops may be forged)
+            let denied = get_metrics_status(listen_address, Some("192.0.2.7, 198.51.100.1"))?;
+            let invalid = get_metrics_status(listen_address, Some("unknown"))?;
+
+            anyhow::Ok([direct, allowed, allowed_v6, denied, invalid])
+        })?;
+
+    assert_eq!(
+        statuses?,
+        [
+            HTTP_FORBIDDEN,
+            HTTP_OK,
+            HTTP_OK,
+            HTTP_FORBIDDEN,
+            HTTP_FORBIDDEN
+        ]
+    );
+
+    let BinOutput {
+        status,
+        stdout,
+        stderr: _,
+    } = output;
+    assert!(
+        stdout.contains("Client allow-list configured with 2 networks\n"),
+        "stdout {stdout:?}"
+    );
+    assert!(
+        stdout.contains("denied access for client 127.0.0.1 to url \"/metrics\"\n"),
+        "stdout {stdout:?}"
+    );
+    assert!(
+        stdout.contains("denied access for client 198.51.100.1 to url \"/metrics\"\n"),
+        "stdout {stdout:?}"
+    );
+    assert!(
+        stdout.contains(
+            "denied access for client with X-Forwarded-For \"unknown\" to url \"/metrics\"\n"
+        ),
+        "stdout {stdout:?}"
+    );
+    assert!(status.success());
+
+    Ok(())
+}
@@ -0,0 +1,131 @@
+use crate::{
+    HTTP_FORBIDDEN, HTTP_OK,
+    common::bin_cmd::{BinCommand, BinOutput},
+};
+use base64::Engine as _;
+use nix::sys::signal::Signal;
+use std::{net::SocketAddr, path::Path, str::FromStr, time::Duration};
+
+/// Allow time for the reload after a signal (or for a distinct modification time)
+const RELOAD_DELAY: Duration = Duration::from_millis(100);
+
+fn get_metrics_status(listen_address: SocketAddr, userpass: &str) -> anyhow::Result<i32> {
+    let userpass_b64 = base64::prelude::BASE64_STANDARD.encode(userpass);
+    let response = minreq::get(format!("http://{listen_address}/metrics"))
+        .with_header("Authorization", format!("Basic {userpass_b64}"))
+        .send()?;
+    Ok(response.status_code)
+}
+
+fn spawn(listen_address: &'static str, auth_file: &Path, watch: bool) -> BinCommand {
+    let mut command = BinCommand::new()
+        .arg(listen_address)
+        .arg("--basic-auth-keys-file")
+        .arg_dynamic(auth_file.to_string_lossy().into_owned());
+    if watch {
+        command = command.arg("--watch-auth-files");
+    }
+    command
+}
+
+#[test]
+fn reload_on_sighup() -> anyhow::Result<()> {
+    const LISTEN_ADDRESS: &str = crate::common::LISTEN_ADDRESS_AUTH_RELOAD_SIGHUP;
+
+    let listen_address = SocketAddr::from_str(LISTEN_ADDRESS)?;
+    let dir = tempfile::tempdir()?;
+    let auth_file = dir.path().join("auth.txt");
+    std::fs::write(&auth_file, "user1:word1\n")?;
+
+    let mut child = spawn(LISTEN_ADDRESS, &auth_file, false).spawn()?;
+    let statuses = (|| -> anyhow::Result<_> {
+        let before = get_metrics_status(listen_address, "user2:word2")?;
+
+        // not reloaded without the signal
+        std::fs::write(&auth_file, "user2:word2\n")?;
+        std::thread::sleep(RELOAD_DELAY);
+        let modified = get_metrics_status(listen_address, "user2:word2")?;
+
+        child.signal(Signal::SIGHUP)?;
+        std::thread::sleep(RELOAD_DELAY);
+        let reloaded = get_metrics_status(listen_address, "user2:word2")?;
+
+        // invalid file keeps the previous rules
+        std::fs::write(&auth_file, "\n")?;
+        child.signal(Signal::SIGHUP)?;
+        std::thread::sleep(RELOAD_DELAY);
+        let invalid = get_metrics_status(listen_address, "user2:word2")?;
+
+        Ok([before, modified, reloaded, invalid])
+    })();
+    child.interrupt_wait()?;
+    let output = child.kill_await_output()?;
+
+    assert_eq!(
+        statuses?,
+        [HTTP_FORBIDDEN, HTTP_FORBIDDEN, HTTP_OK, HTTP_OK]
+    );
+
+    let BinOutput {
+        status,
+        stdout,
+        stderr,
+    } = output;
+    let auth_file = auth_file.display();
+    assert!(
+        stdout.contains(&format!("Reloaded auth file {auth_file} with 1 entry\n")),
+        "stdout {stdout:?}"
+    );
+    assert!(
+        stderr.contains(&format!(
+            "failed to reload auth file, keeping the previous rules: no entries in file {auth_file}\n"
+        )),
+        "stderr {stderr:?}"
+    );
+    assert!(
+        stderr.ends_with("user requested shutdown...\n"),
+        "stderr {stderr:?}"
+    );
+    assert!(status.success());
+
+    Ok(())
+}
+
+#[test]
+fn reload_on_modified() -> anyhow::Result<()> {
+    const LISTEN_ADDRESS: &str = crate::common::LISTEN_ADDRESS_AUTH_RELOAD_WATCH;
+
+    let listen_address = SocketAddr::from_str(LISTEN_ADDRESS)?;
+    let dir = tempfile::tempdir()?;
+    let auth_file = dir.path().join("auth.txt");
+    std::fs::write(&auth_file, "user1:word1\n")?;
+
+    let (output, statuses) = spawn(LISTEN_ADDRESS, &auth_file, true).spawn_cleanup_with(|| {
+        let before = get_metrics_status(listen_address, "user2:word2")?;
+
+        std::thread::sleep(RELOAD_DELAY);
+        std::fs::write(&auth_file, "user2:word2\n")?;
+        let modified = get_metrics_status(listen_address, "user2:word2")?;
+        let previous = get_metrics_status(listen_address, "user1:word1")?;
+
+        anyhow::Ok([before, modified, previous])
+    })?;
+
+    assert_eq!(statuses?, [HTTP_FORBIDDEN, HTTP_OK, HTTP_FORBIDDEN]);
+
+    let BinOutput {
+        status,
+        stdout,
+        stderr: _,
+    } = output;
+    assert!(
+        stdout.contains(&format!(
+            "Reloaded auth file {} with 1 entry\n",
+            auth_file.display()
+        )),
+        "stdout {stdout:?}"
+    );
+    assert!(status.success());
+
+    Ok(())
+}
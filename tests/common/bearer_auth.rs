@@ -0,0 +1,76 @@
+use crate::{
+    HTTP_OK, HTTP_UNAUTHORIZED,
+    common::bin_cmd::{BinCommand, BinOutput},
+};
+use std::{net::SocketAddr, str::FromStr};
+
+const TOKEN: &str = "secret-token-1";
+
+fn get_metrics(
+    listen_address: SocketAddr,
+    authorization: Option<&str>,
+) -> anyhow::Result<(i32, Option<String>)> {
+    let mut request = minreq::get(format!("http://{listen_address}/metrics"));
+    if let Some(authorization) = authorization {
+        request = request.with_header("Authorization", authorization);
+    }
+    let response = request.send()?;
+    let www_authenticate = response.headers.get("www-authenticate").cloned();
+    Ok((response.status_code, www_authenticate))
+}
+
+#[test]
+fn bearer_token() -> anyhow::Result<()> {
+    const LISTEN_ADDRESS: &str = crate::common::LISTEN_ADDRESS_BEARER_AUTH;
+
+    let listen_address = SocketAddr::from_str(LISTEN_ADDRESS)?;
+    let dir = tempfile::tempdir()?;
+    let token_file = dir.path().join("tokens.txt");
+    std::fs::write(&token_file, format!("{TOKEN}\n\nsecret-token-2\n"))?;
+
+    let (output, responses) = BinCommand::new()
+        .arg(LISTEN_ADDRESS)
+        .arg("--bearer-token-file")
+        .arg_dynamic(token_file.to_string_lossy().into_owned())
+        .spawn_cleanup_with(|| {
+            let none = get_metrics(listen_address, None)?;
+            let pass = get_metrics(listen_address, Some(&format!("Bearer {TOKEN}")))?;
+            let fail = get_metrics(listen_address, Some("Bearer wrong-token"))?;
+            // basic authentication is not configured
+            let basic = get_metrics(listen_address, Some("Basic dXNlcjE6d29yZDE="))?;
+
+            anyhow::Ok([none, pass, fail, basic])
+        })?;
+
+    let bearer = || Some("Bearer".to_string());
+    assert_eq!(
+        responses?,
+        [
+            (HTTP_UNAUTHORIZED, bearer()),
+            (HTTP_OK, None),
+            (
+                HTTP_UNAUTHORIZED,
+                Some(r#"Bearer error="invalid_token""#.to_string())
+            ),
+            (HTTP_UNAUTHORIZED, bearer()),
+        ]
+    );
+
+    let BinOutput {
+        status,
+        stdout,
+        stderr: _,
+    } = output;
+    assert!(
+        stdout.contains("Bearer token list configured with 2 entries\n"),
+        "stdout {stdout:?}"
+    );
+    assert!(
+        stdout.contains("denied access for bearer token (len 11) to url \"/metrics\"\n"),
+        "stdout {stdout:?}"
+    );
+    assert!(!stdout.contains("wrong-token"), "stdout {stdout:?}");
+    assert!(status.success());
+
+    Ok(())
+}
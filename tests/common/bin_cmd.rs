@@ -3,12 +3,14 @@ use nix::{
     unistd::Pid,
 };
 use std::{
-    path::Path,
+    ffi::OsString,
+    os::fd::OwnedFd,
+    path::{Path, PathBuf},
     process::{Child, Command, ExitStatus, Output, Stdio},
     time::Duration,
 };
 
-#[derive(Clone, Copy)]
+#[derive(Clone)]
 pub enum FakeZpoolMode {
     NoPools,
     DevsMissing,
@@ -16,11 +18,18 @@ pub enum FakeZpoolMode {
     SleepForever,
     ExitCode1,
     ExitCode2,
+    /// Succeeds once (creating the marker file), then fails
+    FailAfterFirst(PathBuf),
+    /// Succeeds quickly once (creating the marker file), then succeeds slowly
+    SlowAfterFirst(PathBuf),
 }
 #[derive(Default)]
 pub struct BinCommand {
     mode: Option<FakeZpoolMode>,
     args: Vec<String>,
+    envs: Vec<(&'static str, OsString)>,
+    activated_listener: Option<(OwnedFd, &'static str)>,
+    stdin: Option<Stdio>,
 }
 impl BinCommand {
     pub fn new() -> Self {
@@ -38,6 +47,19 @@ impl BinCommand {
         self.args.push(arg);
         self
     }
+    pub fn env(mut self, key: &'static str, value: impl Into<OsString>) -> Self {
+        self.envs.push((key, value.into()));
+        self
+    }
+    pub fn stdin(mut self, stdin: impl Into<Stdio>) -> Self {
+        self.stdin = Some(stdin.into());
+        self
+    }
+    /// Passes the listener as if by socket activation (as stdin, to avoid `unsafe` fd setup)
+    pub fn activated_listener(mut self, listener: impl Into<OwnedFd>, name: &'static str) -> Self {
+        self.activated_listener = Some((listener.into(), name));
+        self
+    }
     fn build(self) -> Command {
         const BIN_EXE: &str = env!("CARGO_BIN_EXE_zpool-status-exporter");
         const BIN_EXE_ZPOOL: &str = env!("CARGO_BIN_EXE_zpool");
@@ -55,6 +77,11 @@ impl BinCommand {
         }
 
         if let Some(mode) = self.mode {
+            if let FakeZpoolMode::FailAfterFirst(marker) | FakeZpoolMode::SlowAfterFirst(marker) =
+                &mode
+            {
+                command.env("FAKE_ZPOOL_MARKER", marker);
+            }
             let mode_str = match mode {
                 FakeZpoolMode::NoPools => "no-pools",
                 FakeZpoolMode::DevsMissing => "devs-missing",
@@ -62,6 +89,8 @@ impl BinCommand {
                 FakeZpoolMode::SleepForever => "sleep-forever",
                 FakeZpoolMode::ExitCode1 => "exit-code1",
                 FakeZpoolMode::ExitCode2 => "exit-code2",
+                FakeZpoolMode::FailAfterFirst(_) => "fail-after-first",
+                FakeZpoolMode::SlowAfterFirst(_) => "slow-after-first",
             };
             command.env("FAKE_ZPOOL_MODE", mode_str);
         }
@@ -70,6 +99,20 @@ impl BinCommand {
             command.args(self.args);
         }
 
+        command.envs(self.envs);
+
+        if let Some(stdin) = self.stdin {
+            command.stdin(stdin);
+        }
+
+        if let Some((listener, name)) = self.activated_listener {
+            command
+                .stdin(listener)
+                .env("LISTEN_FDS", "1")
+                .env("LISTEN_FDS_FIRST_FD", "0")
+                .env("LISTEN_FDNAMES", name);
+        }
+
         command
     }
     pub fn spawn(self) -> anyhow::Result<BinChild> {
@@ -99,6 +142,10 @@ pub struct BinChild {
     subcommand: Child,
 }
 impl BinChild {
+    pub fn signal(&self, signal: Signal) -> anyhow::Result<()> {
+        signal::kill(Pid::from_raw(self.subcommand.id().try_into()?), signal)?;
+        Ok(())
+    }
     pub fn interrupt_wait(&mut self) -> anyhow::Result<()> {
         // SIGINT - request clean exit
         signal::kill(
@@ -0,0 +1,87 @@
+use crate::common::bin_cmd::{BinCommand, BinOutput, FakeZpoolMode};
+
+const EXIT_WARNING: i32 = 1;
+const EXIT_CRITICAL: i32 = 2;
+const EXIT_UNKNOWN: i32 = 3;
+
+#[test]
+fn check_default_thresholds() -> anyhow::Result<()> {
+    let (output, ()) = BinCommand::new().arg("check").spawn_cleanup_with(|| {})?;
+
+    {
+        let BinOutput {
+            status,
+            stdout,
+            stderr: _,
+        } = output;
+
+        assert!(
+            stdout.starts_with("ZPOOL WARNING - alpha-centauri: 279 device errors, last scan "),
+            "{stdout}"
+        );
+        assert!(
+            stdout.contains(
+                " | 'alpha-centauri_state'=10;~:49;~:59 'alpha-centauri_device_errors'=279;~:0; "
+            ),
+            "{stdout}"
+        );
+        assert_eq!(stdout.lines().count(), 1, "{stdout}");
+        assert_eq!(status.code(), Some(EXIT_WARNING));
+    }
+
+    Ok(())
+}
+
+#[test]
+fn check_critical_threshold() -> anyhow::Result<()> {
+    let (output, ()) = BinCommand::new()
+        .arg("check")
+        .arg("--errors-critical=100")
+        .arg("--scan-age-warning=100000")
+        .spawn_cleanup_with(|| {})?;
+
+    {
+        let BinOutput {
+            status,
+            stdout,
+            stderr: _,
+        } = output;
+
+        assert!(
+            stdout.starts_with("ZPOOL CRITICAL - alpha-centauri: 279 device errors | "),
+            "{stdout}"
+        );
+        assert!(
+            stdout.contains(" 'alpha-centauri_device_errors'=279;~:0;~:99 "),
+            "{stdout}"
+        );
+        assert_eq!(status.code(), Some(EXIT_CRITICAL));
+    }
+
+    Ok(())
+}
+
+#[test]
+fn check_collection_failure() -> anyhow::Result<()> {
+    let (output, ()) = BinCommand::new()
+        .arg("check")
+        .fake_zpool_mode(FakeZpoolMode::ExitCode1)
+        .spawn_cleanup_with(|| {})?;
+
+    {
+        let BinOutput {
+            status,
+            stdout,
+            stderr,
+        } = output;
+
+        assert!(
+            stdout.starts_with("ZPOOL UNKNOWN - failed to execute zpool command: "),
+            "{stdout}"
+        );
+        assert_eq!(stderr, "");
+        assert_eq!(status.code(), Some(EXIT_UNKNOWN));
+    }
+
+    Ok(())
+}
@@ -52,7 +52,14 @@ fn stderr_no_pools() -> anyhow::Result<()> {
         let line = lines.next().expect("has line 4");
         assert!(line.starts_with("zpool_lookup"), "line 4 {line:?}");
 
-        assert_eq!(lines.next(), None, "no extra lines");
+        // followed only by the exporter self-metrics
+        let lines_rest: Vec<_> = lines.collect();
+        assert_eq!(
+            lines_rest.first(),
+            Some(&"# HELP zpool_exporter_build_info Exporter version, always 1 (version label)"),
+            "exporter metrics start"
+        );
+        assert_eq!(lines_rest.last(), Some(&"zpool_up 1"), "last line");
     }
 
     Ok(())
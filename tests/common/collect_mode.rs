@@ -0,0 +1,118 @@
+use crate::{
+    HTTP_OK,
+    common::bin_cmd::{BinCommand, BinOutput, FakeZpoolMode},
+};
+use std::{net::SocketAddr, str::FromStr, time::Duration};
+
+/// Runs the server with a `zpool` that only succeeds for the startup check, returning the
+/// stderr and the `/metrics` responses
+fn run_failing_after_first(
+    listen_address: &'static str,
+    args: &[&'static str],
+    request_delays: &[Duration],
+) -> anyhow::Result<(String, Vec<String>)> {
+    let marker_dir = tempfile::tempdir()?;
+    let marker = marker_dir.path().join("first-run-done");
+
+    let listen_address_parsed = SocketAddr::from_str(listen_address)?;
+
+    let mut command = BinCommand::new()
+        .arg(listen_address)
+        .fake_zpool_mode(FakeZpoolMode::FailAfterFirst(marker));
+    for &arg in args {
+        command = command.arg(arg);
+    }
+    let (output, responses) = command.spawn_cleanup_with(|| {
+        request_delays
+            .iter()
+            .map(|&delay| {
+                std::thread::sleep(delay);
+                minreq::get(format!("http://{listen_address_parsed}/metrics")).send()
+            })
+            .collect::<Vec<_>>()
+    })?;
+
+    let BinOutput {
+        status,
+        stdout: _,
+        stderr,
+    } = output;
+    assert!(status.success());
+
+    let mut contents = vec![];
+    for response in responses {
+        let response = response?;
+        assert_eq!(response.status_code, HTTP_OK);
+        contents.push(response.as_str()?.to_owned());
+    }
+    Ok((stderr, contents))
+}
+
+#[test]
+fn min_interval_uses_cache() -> anyhow::Result<()> {
+    const LISTEN_ADDRESS: &str = crate::common::LISTEN_ADDRESS_COLLECT_MIN_INTERVAL;
+
+    let (stderr, contents) = run_failing_after_first(
+        LISTEN_ADDRESS,
+        &["--min-interval", "60"],
+        &[Duration::ZERO, Duration::ZERO],
+    )?;
+
+    assert_eq!(
+        stderr,
+        concat!(
+            "Unrecognized DeviceStatus: \"NOTSURE?\"\n",
+            "user requested shutdown...\n",
+        ),
+        "stderr"
+    );
+    for content in contents {
+        let lines: Vec<_> = content.lines().collect();
+        assert!(
+            lines.contains(&r#"zpool_exporter_collector_errors_total{kind="child_failed"} 0"#),
+            "{content}"
+        );
+        assert!(lines.contains(&"zpool_collection_stale 0"), "{content}");
+        assert_eq!(lines.last(), Some(&"zpool_up 1"), "{content}");
+    }
+
+    Ok(())
+}
+
+#[test]
+fn poll_interval_updates_in_background() -> anyhow::Result<()> {
+    const LISTEN_ADDRESS: &str = crate::common::LISTEN_ADDRESS_COLLECT_POLL_INTERVAL;
+
+    let (stderr, contents) = run_failing_after_first(
+        LISTEN_ADDRESS,
+        &["--poll-interval", "1"],
+        &[Duration::ZERO, Duration::from_millis(1500)],
+    )?;
+
+    assert_eq!(
+        stderr,
+        concat!(
+            "Unrecognized DeviceStatus: \"NOTSURE?\"\n",
+            "failed to get metrics: failed to execute zpool command\n",
+            "user requested shutdown...\n",
+        ),
+        "stderr"
+    );
+    let [first, second] = contents.as_slice() else {
+        anyhow::bail!("expected two responses, got {}", contents.len());
+    };
+
+    // served from the startup collection
+    assert_eq!(first.lines().last(), Some("zpool_up 1"), "{first}");
+
+    // background poll failed, stale metrics from the startup collection
+    let lines: Vec<_> = second.lines().collect();
+    assert!(
+        lines.contains(&r#"zpool_pool_state{pool="alpha-centauri"} 10"#),
+        "{second}"
+    );
+    assert!(lines.contains(&"zpool_collection_stale 1"), "{second}");
+    assert_eq!(lines.last(), Some(&"zpool_up 0"), "{second}");
+
+    Ok(())
+}
@@ -0,0 +1,73 @@
+use crate::{
+    HTTP_OK,
+    common::bin_cmd::{BinCommand, BinOutput, FakeZpoolMode},
+};
+use std::{
+    net::SocketAddr,
+    str::FromStr,
+    time::{Duration, Instant},
+};
+
+#[test]
+fn slow_scrape_does_not_block_root() -> anyhow::Result<()> {
+    const LISTEN_ADDRESS: &str = crate::common::LISTEN_ADDRESS_CONCURRENT;
+
+    let marker_dir = tempfile::tempdir()?;
+    let marker = marker_dir.path().join("first-run-done");
+
+    let listen_address = SocketAddr::from_str(LISTEN_ADDRESS)?;
+
+    let (output, (response_metrics, response_root, root_elapsed)) = BinCommand::new()
+        .arg(LISTEN_ADDRESS)
+        .fake_zpool_mode(FakeZpoolMode::SlowAfterFirst(marker))
+        .spawn_cleanup_with(|| {
+            std::thread::scope(|scope| {
+                let metrics = scope.spawn(|| {
+                    minreq::get(format!("http://{listen_address}/metrics")).send() //
+                });
+
+                // allow the slow scrape to start
+                std::thread::sleep(Duration::from_millis(200));
+
+                let start = Instant::now();
+                let root = minreq::get(format!("http://{listen_address}/")).send();
+                let root_elapsed = start.elapsed();
+
+                let metrics = metrics.join().expect("metrics request thread");
+                (metrics, root, root_elapsed)
+            })
+        })?;
+
+    {
+        let BinOutput {
+            status,
+            stdout: _,
+            stderr,
+        } = output;
+
+        assert_eq!(
+            stderr,
+            concat!(
+                // startup check, then the slow scrape
+                "Unrecognized DeviceStatus: \"NOTSURE?\"\n",
+                "Unrecognized DeviceStatus: \"NOTSURE?\"\n",
+                "user requested shutdown...\n",
+            ),
+            "stderr"
+        );
+        assert!(status.success());
+    }
+
+    assert_eq!(response_root?.status_code, HTTP_OK);
+    assert!(
+        root_elapsed < Duration::from_secs(1),
+        "root page took {root_elapsed:?}"
+    );
+
+    let response_metrics = response_metrics?;
+    assert_eq!(response_metrics.status_code, HTTP_OK);
+    let content = response_metrics.as_str()?;
+    assert_eq!(content.lines().last(), Some("zpool_up 1"), "{content}");
+
+    Ok(())
+}
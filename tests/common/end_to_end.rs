@@ -7,6 +7,7 @@ use std::{net::SocketAddr, str::FromStr};
 
 struct Responses {
     metrics: MiniReqResult,
+    pools_json: MiniReqResult,
     root: MiniReqResult,
     unknown: MiniReqResult,
 }
@@ -25,6 +26,9 @@ fn run_bin() -> anyhow::Result<()> {
             // request from `/metrics` endpoint
             let metrics = minreq::get(format!("http://{listen_address}/metrics")).send();
 
+            // request from `/api/v1/pools` endpoint
+            let pools_json = minreq::get(format!("http://{listen_address}/api/v1/pools")).send();
+
             // request root `/`
             let root = minreq::get(format!("http://{listen_address}/")).send();
 
@@ -33,6 +37,7 @@ fn run_bin() -> anyhow::Result<()> {
 
             Responses {
                 metrics,
+                pools_json,
                 root,
                 unknown,
             }
@@ -47,12 +52,14 @@ fn run_bin() -> anyhow::Result<()> {
 
         // no fatal errors
         //
-        // "NOTSURE?" is mentioned twice:
-        // 1. once for fail-fast startup run, and
-        // 2. again for the "/metrics" request
+        // "NOTSURE?" is mentioned three times:
+        // 1. once for fail-fast startup run,
+        // 2. again for the "/metrics" request, and
+        // 3. again for the "/api/v1/pools" request
         assert_eq!(
             stderr,
             concat!(
+                "Unrecognized DeviceStatus: \"NOTSURE?\"\n",
                 "Unrecognized DeviceStatus: \"NOTSURE?\"\n",
                 "Unrecognized DeviceStatus: \"NOTSURE?\"\n",
                 "user requested shutdown...\n",
@@ -73,6 +80,7 @@ fn run_bin() -> anyhow::Result<()> {
     {
         let Responses {
             metrics,
+            pools_json,
             root,
             unknown,
         } = responses;
@@ -90,6 +98,17 @@ fn run_bin() -> anyhow::Result<()> {
             assert_matches_template(content, EXPECTED_METRICS_OUTPUT);
             true
         });
+
+        let pools_json = pools_json?;
+        assert_eq!(
+            pools_json.headers.get("content-type").map(String::as_str),
+            Some("application/json"),
+            "pools_json content type"
+        );
+        assert_response("pools_json", &pools_json, HTTP_OK, |content| {
+            content.contains(r#""name": "alpha-centauri""#)
+                && content.contains(r#""text": "NOTSURE?""#)
+        });
     }
 
     Ok(())
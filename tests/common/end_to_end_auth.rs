@@ -26,7 +26,7 @@ fn run_bin() -> anyhow::Result<()> {
     let listen_address = SocketAddr::from_str(LISTEN_ADDRESS)?;
 
     let mut auth_file = tempfile::NamedTempFile::new().context("tempfile creation")?;
-    writeln!(auth_file, "user1:word1")?;
+    writeln!(auth_file, "user1:{}", bcrypt::hash("word1", 4)?)?;
     writeln!(auth_file, "user2:phrase2")?;
     let auth_file_name = auth_file
         .path()
@@ -108,6 +108,7 @@ fn assert_bin_output(listen_address: &SocketAddr, output: BinOutput) {
             "Listening at http://{listen_address}\n{}",
             concat!(
                 "Allow-list configured with 2 entries\n",
+                "WARNING: 1 plaintext password entry in the allow-list, deprecated in favor of bcrypt or argon2 hashes\n",
                 "!!!!!! WARNING: HTTP transmits authentication in plaintext, use a HTTPS-proxy on the local machine!!!!!!!\n",
                 "denied access for \"user2:phrase1\" to url \"/metrics\"\n",
             )
@@ -0,0 +1,119 @@
+use crate::{
+    HTTP_OK,
+    common::bin_cmd::{BinCommand, BinOutput, FakeZpoolMode},
+};
+use std::{net::SocketAddr, str::FromStr};
+
+const HTTP_INTERNAL_SERVER_ERROR: i32 = 500;
+const HTTP_SERVICE_UNAVAILABLE: i32 = 503;
+
+struct Responses {
+    metrics: minreq::Response,
+    pools_json: minreq::Response,
+}
+
+/// Runs the server with a `zpool` that only succeeds for the startup check
+fn run_failing(listen_address: &'static str, args: &[&'static str]) -> anyhow::Result<Responses> {
+    let marker_dir = tempfile::tempdir()?;
+    let marker = marker_dir.path().join("first-run-done");
+
+    let listen_address_parsed = SocketAddr::from_str(listen_address)?;
+
+    let mut command = BinCommand::new()
+        .arg(listen_address)
+        .fake_zpool_mode(FakeZpoolMode::FailAfterFirst(marker));
+    for &arg in args {
+        command = command.arg(arg);
+    }
+    let (output, (metrics, pools_json)) = command.spawn_cleanup_with(|| {
+        let metrics = minreq::get(format!("http://{listen_address_parsed}/metrics")).send();
+        let pools_json = minreq::get(format!("http://{listen_address_parsed}/api/v1/pools")).send();
+        (metrics, pools_json)
+    })?;
+
+    {
+        let BinOutput {
+            status,
+            stdout: _,
+            stderr,
+        } = output;
+
+        assert_eq!(
+            stderr,
+            concat!(
+                "Unrecognized DeviceStatus: \"NOTSURE?\"\n",
+                "failed to get metrics: failed to execute zpool command\n",
+                "failed to get pools json: failed to execute zpool command\n",
+                "user requested shutdown...\n",
+            ),
+            "stderr"
+        );
+        assert!(status.success());
+    }
+
+    Ok(Responses {
+        metrics: metrics?,
+        pools_json: pools_json?,
+    })
+}
+
+fn assert_pools_json_error(pools_json: &minreq::Response) -> anyhow::Result<()> {
+    assert_eq!(pools_json.status_code, HTTP_INTERNAL_SERVER_ERROR);
+    let error: serde_json::Value = serde_json::from_str(pools_json.as_str()?)?;
+    assert_eq!(error["code"], "child_failed", "{error}");
+    Ok(())
+}
+
+#[test]
+fn up_zero() -> anyhow::Result<()> {
+    const LISTEN_ADDRESS: &str = crate::common::LISTEN_ADDRESS_FAILURE_UP_ZERO;
+
+    let Responses {
+        metrics,
+        pools_json,
+    } = run_failing(LISTEN_ADDRESS, &[])?;
+
+    assert_eq!(metrics.status_code, HTTP_OK);
+    let content = metrics.as_str()?;
+    let lines: Vec<_> = content.lines().collect();
+
+    assert_eq!(lines.first(), Some(&"# ERROR:"), "{content}");
+    assert!(lines.contains(&"# ERROR_CODE: child_failed"), "{content}");
+    // stale data from the startup run
+    assert!(
+        lines.contains(&"# STALE: metrics below are from the last successful collection"),
+        "{content}"
+    );
+    assert!(
+        lines.contains(&r#"zpool_pool_state{pool="alpha-centauri"} 10"#),
+        "{content}"
+    );
+    assert!(
+        lines.contains(&r#"zpool_exporter_collector_errors_total{kind="child_failed"} 1"#),
+        "{content}"
+    );
+    assert!(lines.contains(&"zpool_collection_stale 1"), "{content}");
+    assert_eq!(lines.last(), Some(&"zpool_up 0"), "{content}");
+
+    assert_pools_json_error(&pools_json)
+}
+
+#[test]
+fn unavailable() -> anyhow::Result<()> {
+    const LISTEN_ADDRESS: &str = crate::common::LISTEN_ADDRESS_FAILURE_UNAVAILABLE;
+
+    let Responses {
+        metrics,
+        pools_json,
+    } = run_failing(LISTEN_ADDRESS, &["--failure-response", "unavailable"])?;
+
+    assert_eq!(metrics.status_code, HTTP_SERVICE_UNAVAILABLE);
+    insta::assert_snapshot!(metrics.as_str()?, @r#"
+    # ERROR:
+    # failed to execute zpool command
+    # command "zpool" (args ["status", "-p"]) failed with exit code exit status: 1,  stdout: "", stderr: "fail-after-first stderr contents\n"
+    # ERROR_CODE: child_failed
+    "#);
+
+    assert_pools_json_error(&pools_json)
+}
@@ -0,0 +1,43 @@
+use crate::common::bin_cmd::{BinCommand, BinOutput};
+
+#[test]
+fn generate_rules() -> anyhow::Result<()> {
+    let (output, ()) = BinCommand::new()
+        .arg("generate-rules")
+        .arg("--metric-prefix=zfs")
+        .arg("--const-label=host=nas-1")
+        .arg("--state-warning=faulted")
+        .arg("--scan-age=7")
+        .spawn_cleanup_with(|| {})?;
+
+    {
+        let BinOutput {
+            status,
+            stdout,
+            stderr,
+        } = output;
+
+        assert!(stdout.contains("\ngroups:\n"), "{stdout}");
+        assert!(
+            stdout.contains(
+                "      - alert: ZpoolPoolFaulted\n        expr: \"zfs_pool_state{host=\\\"nas-1\\\"} == 60\"\n        for: 15m\n        labels:\n          severity: critical\n"
+            ),
+            "{stdout}"
+        );
+        assert!(
+            !stdout.contains("alert: ZpoolPoolDegraded"),
+            "degraded below the warning state, got: {stdout}"
+        );
+        assert!(
+            stdout.contains(
+                "        expr: \"zfs_scan_age{host=\\\"nas-1\\\"} > 168 unless zfs_scan_state{host=\\\"nas-1\\\"} == 40\"\n"
+            ),
+            "{stdout}"
+        );
+        assert!(stdout.contains("alert: ZpoolDataErrors\n"), "{stdout}");
+        assert_eq!(stderr, "");
+        assert!(status.success());
+    }
+
+    Ok(())
+}
@@ -0,0 +1,129 @@
+use crate::{
+    HTTP_OK, HTTP_UNAUTHORIZED,
+    common::bin_cmd::{BinCommand, BinOutput, FakeZpoolMode},
+};
+use std::{net::SocketAddr, path::Path, str::FromStr, time::Duration};
+
+const HTTP_SERVICE_UNAVAILABLE: i32 = 503;
+
+const TOKEN: &str = "secret-token";
+
+fn get(
+    listen_address: SocketAddr,
+    path: &str,
+    authorization: Option<&str>,
+) -> anyhow::Result<(i32, String)> {
+    let mut request = minreq::get(format!("http://{listen_address}{path}"));
+    if let Some(authorization) = authorization {
+        request = request.with_header("Authorization", authorization);
+    }
+    let response = request.send()?;
+    Ok((response.status_code, response.as_str()?.to_owned()))
+}
+
+fn write_token_file(dir: &Path) -> anyhow::Result<String> {
+    let token_file = dir.join("tokens.txt");
+    std::fs::write(&token_file, format!("{TOKEN}\n"))?;
+    Ok(token_file.to_string_lossy().into_owned())
+}
+
+#[test]
+fn public_until_collection_fails() -> anyhow::Result<()> {
+    const LISTEN_ADDRESS: &str = crate::common::LISTEN_ADDRESS_HEALTH_PUBLIC;
+
+    let listen_address = SocketAddr::from_str(LISTEN_ADDRESS)?;
+    let dir = tempfile::tempdir()?;
+    let marker = dir.path().join("first-run-done");
+
+    let (output, responses) = BinCommand::new()
+        .arg(LISTEN_ADDRESS)
+        .arg("--bearer-token-file")
+        .arg_dynamic(write_token_file(dir.path())?)
+        .fake_zpool_mode(FakeZpoolMode::FailAfterFirst(marker))
+        .spawn_cleanup_with(|| {
+            let healthy = get(listen_address, "/-/healthy", None)?;
+            let ready = get(listen_address, "/-/ready", None)?;
+            // metrics still require authentication
+            let (metrics_unauthenticated, _) = get(listen_address, "/metrics", None)?;
+            let (metrics_failed, _) =
+                get(listen_address, "/metrics", Some(&format!("Bearer {TOKEN}")))?;
+            let healthy_after = get(listen_address, "/-/healthy", None)?;
+            let ready_after = get(listen_address, "/-/ready", None)?;
+
+            anyhow::Ok((
+                [healthy, ready, healthy_after],
+                [metrics_unauthenticated, metrics_failed],
+                ready_after,
+            ))
+        })?;
+
+    let (health, metrics, (ready_after_status, ready_after_body)) = responses?;
+    let ok = || (HTTP_OK, "OK".to_owned());
+    assert_eq!(health, [ok(), ok(), ok()]);
+    assert_eq!(metrics, [HTTP_UNAUTHORIZED, HTTP_OK]);
+    assert_eq!(ready_after_status, HTTP_SERVICE_UNAVAILABLE);
+    assert!(
+        ready_after_body.starts_with("last collection failed 0s ago: "),
+        "{ready_after_body:?}"
+    );
+
+    let BinOutput {
+        status,
+        stdout: _,
+        stderr: _,
+    } = output;
+    assert!(status.success());
+
+    Ok(())
+}
+
+#[test]
+fn authenticated_with_max_age() -> anyhow::Result<()> {
+    const LISTEN_ADDRESS: &str = crate::common::LISTEN_ADDRESS_HEALTH_AUTHENTICATED;
+
+    let listen_address = SocketAddr::from_str(LISTEN_ADDRESS)?;
+    let dir = tempfile::tempdir()?;
+
+    let (output, responses) = BinCommand::new()
+        .arg(LISTEN_ADDRESS)
+        .arg("--bearer-token-file")
+        .arg_dynamic(write_token_file(dir.path())?)
+        .arg("--authenticate-health")
+        .arg("--ready-max-age")
+        .arg("1")
+        .spawn_cleanup_with(|| {
+            let authorization = format!("Bearer {TOKEN}");
+            let (unauthenticated, _) = get(listen_address, "/-/ready", None)?;
+            let ready = get(listen_address, "/-/ready", Some(&authorization))?;
+
+            // only the startup collection, no scrapes
+            std::thread::sleep(Duration::from_millis(1100));
+            let expired = get(listen_address, "/-/ready", Some(&authorization))?;
+            let healthy = get(listen_address, "/-/healthy", Some(&authorization))?;
+
+            anyhow::Ok((unauthenticated, [ready, expired, healthy]))
+        })?;
+
+    let (unauthenticated, responses) = responses?;
+    assert_eq!(unauthenticated, HTTP_UNAUTHORIZED);
+    assert_eq!(
+        responses,
+        [
+            (HTTP_OK, "OK".to_owned()),
+            (
+                HTTP_SERVICE_UNAVAILABLE,
+                "last collection 1s ago, exceeds the maximum age 1s".to_owned()
+            ),
+            (HTTP_OK, "OK".to_owned()),
+        ]
+    );
+
+    let BinOutput {
+        status,
+        stdout: _,
+        stderr: _,
+    } = output;
+    assert!(status.success());
+
+    Ok(())
+}
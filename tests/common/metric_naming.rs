@@ -0,0 +1,89 @@
+use crate::{
+    HTTP_OK, assert_response,
+    common::bin_cmd::{BinCommand, BinOutput},
+};
+use std::{net::SocketAddr, str::FromStr};
+
+#[test]
+fn prefix_and_const_labels() -> anyhow::Result<()> {
+    const LISTEN_ADDRESS: &str = crate::common::LISTEN_ADDRESS_METRIC_NAMING;
+
+    let listen_address = SocketAddr::from_str(LISTEN_ADDRESS)?;
+
+    let (output, response_metrics) = BinCommand::new()
+        .arg(LISTEN_ADDRESS)
+        .arg("--metric-prefix")
+        .arg("zfs")
+        .arg("--const-label")
+        .arg("host=alpha")
+        .arg("--const-label")
+        .arg("site=lab \"west\"")
+        .spawn_cleanup_with(|| {
+            minreq::get(format!("http://{listen_address}/metrics")).send() //
+        })?;
+
+    assert!(output.status.success());
+
+    assert_response("metrics", &response_metrics?, HTTP_OK, |content| {
+        const CONST_LABELS: &str = r#"host="alpha",site="lab \"west\"""#;
+
+        let expected_lines = [
+            format!(r#"zfs_pool_state{{pool="alpha-centauri",{CONST_LABELS}}} 10"#),
+            format!(r#"zfs_dev_state{{pool="alpha-centauri",dev="__root__",{CONST_LABELS}}} 10"#),
+        ];
+        for expected in &expected_lines {
+            assert!(
+                content.lines().any(|line| line == expected),
+                "missing line {expected:?}"
+            );
+        }
+
+        let lookup_prefix = format!("zfs_lookup{{{CONST_LABELS}}} ");
+        assert!(
+            content.lines().any(|line| line.starts_with(&lookup_prefix)),
+            "missing lookup line {lookup_prefix:?}"
+        );
+
+        for line in content.lines() {
+            if let Some(comment) = line.strip_prefix("# ") {
+                let (_kind, metric) = comment.split_once(' ').expect("HELP/TYPE comment");
+                assert!(metric.starts_with("zfs_"), "comment line {line:?}");
+            } else {
+                assert!(line.starts_with("zfs_"), "metric line {line:?}");
+                assert!(line.contains(CONST_LABELS), "metric line {line:?}");
+            }
+        }
+        true
+    });
+
+    Ok(())
+}
+
+#[test]
+fn const_label_conflict() -> anyhow::Result<()> {
+    const LISTEN_ADDRESS: &str = crate::common::LISTEN_ADDRESS_METRIC_NAMING;
+
+    let (output, ()) = BinCommand::new()
+        .arg(LISTEN_ADDRESS)
+        .arg("--const-label")
+        .arg("pool=shadowed")
+        .spawn_cleanup_with(|| {})?;
+
+    {
+        let BinOutput {
+            status,
+            stdout,
+            stderr,
+        } = output;
+
+        assert_eq!(stdout, "");
+        insta::assert_snapshot!(stderr, @r#"
+        error: invalid value 'pool=shadowed' for '--const-label <CONST_LABEL>': label name in "pool=shadowed" conflicts with a built-in label (one of ["pool", "dev", "vdev_type", "parent", "depth", "state", "version", "kind"])
+
+        For more information, try '--help'.
+        "#);
+        assert!(!status.success());
+    }
+
+    Ok(())
+}
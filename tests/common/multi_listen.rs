@@ -0,0 +1,83 @@
+use crate::{
+    HTTP_OK,
+    common::bin_cmd::{BinCommand, BinOutput},
+};
+use std::{
+    io::{Read as _, Write as _},
+    net::SocketAddr,
+    os::unix::{fs::PermissionsExt as _, net::UnixStream},
+    path::Path,
+    str::FromStr,
+};
+
+fn get_metrics_unix(socket: &Path) -> anyhow::Result<String> {
+    let mut stream = UnixStream::connect(socket)?;
+    stream.write_all(b"GET /metrics HTTP/1.0\r\n\r\n")?;
+    let mut response = String::new();
+    stream.read_to_string(&mut response)?;
+    Ok(response)
+}
+
+#[test]
+fn tcp_and_unix() -> anyhow::Result<()> {
+    const LISTEN_ADDRESS: &str = crate::common::LISTEN_ADDRESS_MULTI_LISTEN_1;
+    const LISTEN_ADDRESS_2: &str = crate::common::LISTEN_ADDRESS_MULTI_LISTEN_2;
+
+    let listen_addresses = [
+        SocketAddr::from_str(LISTEN_ADDRESS)?,
+        SocketAddr::from_str(LISTEN_ADDRESS_2)?,
+    ];
+    let dir = tempfile::tempdir()?;
+    let socket = dir.path().join("exporter.sock");
+    let socket_arg = format!("unix:{}", socket.display());
+
+    let (output, results) = BinCommand::new()
+        .arg(LISTEN_ADDRESS)
+        .arg("--listen")
+        .arg(LISTEN_ADDRESS_2)
+        .arg("--listen")
+        .arg_dynamic(socket_arg.clone())
+        .arg("--unix-socket-mode")
+        .arg("600")
+        .spawn_cleanup_with(|| {
+            let tcp_statuses = listen_addresses.map(|listen_address| {
+                minreq::get(format!("http://{listen_address}/metrics"))
+                    .send()
+                    .map(|response| response.status_code)
+                    .ok()
+            });
+            let socket_mode = std::fs::metadata(&socket)?.permissions().mode() & 0o777;
+            let unix_response = get_metrics_unix(&socket)?;
+
+            anyhow::Ok((tcp_statuses, socket_mode, unix_response))
+        })?;
+
+    let (tcp_statuses, socket_mode, unix_response) = results?;
+    assert_eq!(tcp_statuses, [Some(HTTP_OK), Some(HTTP_OK)]);
+    assert_eq!(socket_mode, 0o600);
+    assert!(
+        unix_response.starts_with("HTTP/1.0 200 OK\r\n"),
+        "unix_response {unix_response:?}"
+    );
+    assert!(
+        unix_response.contains("zpool_up 1\n"),
+        "unix_response {unix_response:?}"
+    );
+
+    let BinOutput {
+        status,
+        stdout,
+        stderr: _,
+    } = output;
+    assert!(
+        stdout.starts_with(&format!(
+            "Listening at http://{LISTEN_ADDRESS}\nListening at http://{LISTEN_ADDRESS_2}\nListening at {socket_arg}\n"
+        )),
+        "stdout {stdout:?}"
+    );
+    assert!(status.success());
+    // removed on shutdown
+    assert!(!socket.exists());
+
+    Ok(())
+}
@@ -0,0 +1,75 @@
+use crate::{
+    assert_matches_template,
+    common::bin_cmd::{BinCommand, BinOutput},
+};
+
+const INPUT: &str = include_str!("../input/input-03-resilvered.txt");
+const EXPECTED_OUTPUT: &str = include_str!("../input/output-03-resilvered.txt");
+
+/// Writes the input without the `TEST_TIMESTAMP` line, as captured from `zpool status -p`
+fn write_input(dir: &tempfile::TempDir) -> anyhow::Result<std::path::PathBuf> {
+    let (_timestamp_line, zpool_output) = INPUT.split_once('\n').expect("timestamp line");
+    let path = dir.path().join("zpool-status.txt");
+    std::fs::write(&path, zpool_output)?;
+    Ok(path)
+}
+
+#[test]
+fn parse_file_at_timestamp() -> anyhow::Result<()> {
+    let dir = tempfile::tempdir()?;
+    let input = write_input(&dir)?;
+
+    let (output, ()) = BinCommand::new()
+        .arg("parse")
+        .arg("--now=0")
+        .arg_dynamic(input.display().to_string())
+        .env("TZ", "UTC")
+        .spawn_cleanup_with(|| {})?;
+
+    {
+        let BinOutput {
+            status,
+            stdout,
+            stderr,
+        } = output;
+
+        assert_matches_template(&stdout, EXPECTED_OUTPUT);
+        assert_eq!(stderr, "");
+        assert!(status.success());
+    }
+
+    Ok(())
+}
+
+#[test]
+fn parse_stdin() -> anyhow::Result<()> {
+    let dir = tempfile::tempdir()?;
+    let input = write_input(&dir)?;
+
+    let (output, ()) = BinCommand::new()
+        .arg("parse")
+        .arg("--metric-prefix=zfs")
+        .stdin(std::fs::File::open(input)?)
+        .spawn_cleanup_with(|| {})?;
+
+    {
+        let BinOutput {
+            status,
+            stdout,
+            stderr,
+        } = output;
+
+        assert!(
+            stdout.starts_with("# HELP zfs_pool_state "),
+            "stdout did not start with the pool state, got: {stdout:?}"
+        );
+        assert!(
+            stdout.contains("\nzfs_lookup "),
+            "stdout missing the lookup duration, got: {stdout:?}"
+        );
+        assert_eq!(stderr, "");
+        assert!(status.success());
+    }
+
+    Ok(())
+}
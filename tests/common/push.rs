@@ -0,0 +1,180 @@
+use crate::common::bin_cmd::{BinCommand, BinOutput, FakeZpoolMode};
+use std::{
+    sync::mpsc,
+    time::{Duration, Instant},
+};
+
+/// Request received by the Pushgateway stand-in
+struct RecordedRequest {
+    method: String,
+    url: String,
+    authorization: Option<String>,
+    body: String,
+}
+
+/// Starts a Pushgateway stand-in, responding to each request with the next status code
+fn spawn_pushgateway(
+    listen_address: &'static str,
+    status_codes: Vec<u16>,
+) -> anyhow::Result<mpsc::Receiver<RecordedRequest>> {
+    let server = tiny_http::Server::http(listen_address)
+        .map_err(|err| anyhow::anyhow!("failed to bind {listen_address}: {err}"))?;
+    let (request_tx, request_rx) = mpsc::channel();
+    std::thread::spawn(move || {
+        for status_code in status_codes {
+            let Ok(mut request) = server.recv() else {
+                return;
+            };
+            let mut body = String::new();
+            request
+                .as_reader()
+                .read_to_string(&mut body)
+                .expect("utf8 request body");
+            let authorization = request
+                .headers()
+                .iter()
+                .find(|header| header.field.equiv("Authorization"))
+                .map(|header| header.value.to_string());
+            let recorded = RecordedRequest {
+                method: request.method().to_string(),
+                url: request.url().to_owned(),
+                authorization,
+                body,
+            };
+            let response = tiny_http::Response::from_string("").with_status_code(status_code);
+            request.respond(response).expect("respond to request");
+            if request_tx.send(recorded).is_err() {
+                return;
+            }
+        }
+    });
+    Ok(request_rx)
+}
+
+fn recv_request(requests: &mpsc::Receiver<RecordedRequest>) -> anyhow::Result<RecordedRequest> {
+    Ok(requests.recv_timeout(Duration::from_secs(5))?)
+}
+
+#[test]
+fn push_retries_server_error() -> anyhow::Result<()> {
+    const LISTEN_ADDRESS: &str = crate::common::LISTEN_ADDRESS_PUSHGATEWAY;
+
+    let dir = tempfile::tempdir()?;
+    let basic_auth_file = dir.path().join("push-credentials");
+    // CRLF line ending is not part of the password
+    std::fs::write(&basic_auth_file, "pusher:secret\r\n")?;
+
+    let requests = spawn_pushgateway(LISTEN_ADDRESS, vec![503, 200])?;
+
+    let start_time = Instant::now();
+    let (output, ()) = BinCommand::new()
+        .arg_dynamic(format!("--push-url=http://{LISTEN_ADDRESS}/"))
+        .arg("--push-job=zpool")
+        .arg("--push-instance=host/1")
+        .arg("--push-basic-auth-file")
+        .arg_dynamic(basic_auth_file.display().to_string())
+        .fake_zpool_mode(FakeZpoolMode::NoPools)
+        .spawn_cleanup_with(|| std::thread::sleep(Duration::from_millis(1500)))?;
+
+    {
+        let BinOutput {
+            status,
+            stdout,
+            stderr,
+        } = output;
+        assert!(
+            stderr.starts_with("Push attempt 1 failed: failed to push metrics to "),
+            "{stderr}"
+        );
+        assert!(stderr.contains(", status 503: "), "{stderr}");
+        assert!(stdout.contains("on attempt 2"), "{stdout}");
+        assert!(status.success());
+    }
+
+    for _ in 0..2 {
+        let RecordedRequest {
+            method,
+            url,
+            authorization,
+            body,
+        } = recv_request(&requests)?;
+        assert_eq!(method, "PUT");
+        assert_eq!(url, "/metrics/job/zpool/instance@base64/aG9zdC8x");
+        // base64 of "pusher:secret"
+        assert_eq!(authorization.as_deref(), Some("Basic cHVzaGVyOnNlY3JldA=="));
+        assert!(body.starts_with("# no pools reported\n"), "{body}");
+        assert_eq!(body.lines().last(), Some("zpool_up 1"), "{body}");
+    }
+    assert!(
+        start_time.elapsed() >= Duration::from_secs(1),
+        "backoff delay"
+    );
+
+    Ok(())
+}
+
+#[test]
+fn push_collection_failure() -> anyhow::Result<()> {
+    const LISTEN_ADDRESS: &str = crate::common::LISTEN_ADDRESS_PUSHGATEWAY_FAILURE;
+
+    let requests = spawn_pushgateway(LISTEN_ADDRESS, vec![200])?;
+
+    let (output, ()) = BinCommand::new()
+        .arg_dynamic(format!("--push-url=http://{LISTEN_ADDRESS}"))
+        .arg("--push-instance=nas")
+        .fake_zpool_mode(FakeZpoolMode::ExitCode1)
+        .spawn_cleanup_with(|| {})?;
+
+    {
+        let BinOutput {
+            status,
+            stdout: _,
+            stderr,
+        } = output;
+        assert!(
+            stderr.starts_with("Error: failed to create metrics (pushed as up = 0)"),
+            "{stderr}"
+        );
+        assert!(!status.success());
+    }
+
+    let RecordedRequest {
+        method,
+        url,
+        authorization,
+        body,
+    } = recv_request(&requests)?;
+    assert_eq!(method, "PUT");
+    assert_eq!(url, "/metrics/job/zpool-status-exporter/instance/nas");
+    assert_eq!(authorization, None);
+    let lines: Vec<_> = body.lines().collect();
+    assert_eq!(lines.first(), Some(&"# ERROR:"), "{body}");
+    assert!(lines.contains(&"# ERROR_CODE: child_failed"), "{body}");
+    assert_eq!(lines.last(), Some(&"zpool_up 0"), "{body}");
+
+    Ok(())
+}
+
+#[test]
+fn push_args_require_push_url() -> anyhow::Result<()> {
+    let (output, ()) = BinCommand::new()
+        .arg("127.0.0.1:1")
+        .arg("--push-max-retries=5")
+        .spawn_cleanup_with(|| {})?;
+
+    let BinOutput {
+        status,
+        stdout,
+        stderr,
+    } = output;
+    assert_eq!(stdout, "");
+    assert!(
+        stderr.starts_with(
+            "error: the argument '--push-max-retries <MAX_RETRIES>' cannot be used with"
+        ),
+        "{stderr}"
+    );
+    assert!(!status.success());
+
+    Ok(())
+}
@@ -0,0 +1,165 @@
+use crate::{
+    HTTP_OK,
+    common::bin_cmd::{BinCommand, BinOutput},
+};
+use nix::unistd::{Gid, Uid, User};
+use std::{
+    io::{Read as _, Write as _},
+    os::unix::{
+        fs::{MetadataExt as _, PermissionsExt as _},
+        net::UnixStream,
+    },
+    str::FromStr,
+};
+
+/// Switching to the current (unprivileged) user, as the super-user cannot be tested
+#[test]
+fn current_user() -> anyhow::Result<()> {
+    const LISTEN_ADDRESS: &str = crate::common::LISTEN_ADDRESS_RUN_AS;
+
+    let user = User::from_uid(Uid::current())?.expect("current user exists");
+    let dir = tempfile::tempdir()?;
+    let socket = dir.path().join("exporter.sock");
+
+    let (output, responses) = BinCommand::new()
+        .arg(LISTEN_ADDRESS)
+        .arg("--listen")
+        .arg_dynamic(format!("unix:{}", socket.display()))
+        .arg("--user")
+        .arg_dynamic(user.name.clone())
+        .spawn_cleanup_with(|| {
+            let listen_address = std::net::SocketAddr::from_str(LISTEN_ADDRESS)?;
+            let tcp = minreq::get(format!("http://{listen_address}/metrics")).send()?;
+
+            let socket_uid = std::fs::metadata(&socket)?.uid();
+            let mut stream = UnixStream::connect(&socket)?;
+            stream.write_all(b"GET /metrics HTTP/1.0\r\n\r\n")?;
+            let mut unix = String::new();
+            stream.read_to_string(&mut unix)?;
+
+            anyhow::Ok((tcp.status_code, socket_uid, unix))
+        })?;
+
+    let (tcp_status, socket_uid, unix_response) = responses?;
+    assert_eq!(tcp_status, HTTP_OK);
+    assert_eq!(socket_uid, user.uid.as_raw());
+    assert!(
+        unix_response.starts_with("HTTP/1.0 200 OK\r\n"),
+        "response {unix_response:?}"
+    );
+
+    let BinOutput {
+        status,
+        stdout,
+        stderr: _,
+    } = output;
+    // unprivileged, so the supplementary groups are unchanged
+    let expected = format!(
+        "Dropped privileges to user {name} (uid {uid}, gid {gid}, groups {groups})\n",
+        name = user.name,
+        uid = user.uid,
+        gid = user.gid,
+        groups = format_groups(&nix::unistd::getgroups()?),
+    );
+    assert!(stdout.contains(&expected), "stdout {stdout:?}");
+    assert!(status.success());
+
+    Ok(())
+}
+
+/// Switching from the super-user keeps the supplementary groups of the target user
+///
+/// NOTE: Only runs as the super-user, with a user listed as a member in `/etc/group`
+#[test]
+fn supplementary_groups() -> anyhow::Result<()> {
+    const LISTEN_ADDRESS: &str = crate::common::LISTEN_ADDRESS_RUN_AS_GROUPS;
+    const BIN_EXE_ZPOOL: &str = env!("CARGO_BIN_EXE_zpool");
+
+    if !Uid::effective().is_root() {
+        eprintln!("skipping, requires the super-user");
+        return Ok(());
+    }
+    let group_file = std::fs::read_to_string("/etc/group")?;
+    let member = group_file
+        .lines()
+        .filter_map(|line| line.split(':').nth(3))
+        .flat_map(|members| members.split(','))
+        .filter(|member| !member.is_empty())
+        .find_map(|member| User::from_name(member).ok().flatten())
+        .filter(|user| !user.uid.is_root());
+    let Some(user) = member else {
+        eprintln!("skipping, no user with supplementary groups");
+        return Ok(());
+    };
+    let name = std::ffi::CString::new(user.name.clone())?;
+    let groups = nix::unistd::getgrouplist(&name, user.gid)?;
+    assert!(groups.len() > 1, "groups {groups:?}");
+
+    // the target user may not have access to the build directory
+    let dir = tempfile::tempdir()?;
+    let zpool = dir.path().join("zpool");
+    std::fs::copy(BIN_EXE_ZPOOL, &zpool)?;
+    std::fs::set_permissions(dir.path(), std::fs::Permissions::from_mode(0o755))?;
+
+    let (output, ()) = BinCommand::new()
+        .arg(LISTEN_ADDRESS)
+        .arg("--user")
+        .arg_dynamic(user.name.clone())
+        .arg("--zpool-path")
+        .arg_dynamic(zpool.display().to_string())
+        .spawn_cleanup_with(|| {})?;
+
+    let BinOutput {
+        status: _,
+        stdout,
+        stderr,
+    } = output;
+    let expected = format!(
+        "Dropped privileges to user {name} (uid {uid}, gid {gid}, groups {groups})\n",
+        name = user.name,
+        uid = user.uid,
+        gid = user.gid,
+        groups = format_groups(&groups),
+    );
+    assert!(
+        stdout.contains(&expected),
+        "stdout {stdout:?} stderr {stderr:?}"
+    );
+
+    Ok(())
+}
+
+fn format_groups(groups: &[Gid]) -> String {
+    let mut groups = groups.to_vec();
+    groups.sort_by_key(|gid| gid.as_raw());
+    groups
+        .iter()
+        .map(Gid::to_string)
+        .collect::<Vec<_>>()
+        .join(",")
+}
+
+#[test]
+fn unknown_user() -> anyhow::Result<()> {
+    const LISTEN_ADDRESS: &str = crate::common::LISTEN_ADDRESS_RUN_AS_UNKNOWN;
+
+    let (output, ()) = BinCommand::new()
+        .arg(LISTEN_ADDRESS)
+        .arg("--user")
+        .arg("no-such-user-zpool-status-exporter")
+        .spawn_cleanup_with(|| {})?;
+
+    let BinOutput {
+        status,
+        stdout,
+        stderr,
+    } = output;
+    assert_eq!(stdout, "");
+    assert_eq!(
+        stderr,
+        "Error: unknown user \"no-such-user-zpool-status-exporter\"\n"
+    );
+    assert!(!status.success());
+
+    Ok(())
+}
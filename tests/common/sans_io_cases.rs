@@ -73,4 +73,7 @@ test_cases! {
     case09 {09-scrub-cancel}
     case10 {10-new-pool}
     case11 {11-degraded-no-scan}
+    case12 {12-vdev-types}
+    case13 {13-resilver-progress}
+    case14 {14-leaf-unrecognized}
 }
@@ -0,0 +1,306 @@
+//! Snapshots of the JSON pool tree (`/api/v1/pools`) for selected inputs
+//!
+//! NOTE: The JSON field names are a public API, review any snapshot changes carefully
+
+use anyhow::Context as _;
+
+/// Compute the JSON string from the input string (ignoring the prepended timestamp line)
+fn run_test(full_input: &str) -> anyhow::Result<String> {
+    let (_timestamp_line, input) = full_input
+        .split_once('\n')
+        .context("missing timestamp line in input")?;
+
+    let json = zpool_status_exporter::AppContext::new_assume_local_is_utc()
+        .get_pools_json_for_output(input)?;
+
+    Ok(json)
+}
+
+#[test]
+fn new_pool() -> anyhow::Result<()> {
+    let json = run_test(include_str!("../input/input-10-new-pool.txt"))?;
+    insta::assert_snapshot!(json, @r#"
+    {
+      "pools": [
+        {
+          "name": "milton",
+          "state": {
+            "kind": "Online",
+            "value": 10,
+            "text": "ONLINE"
+          },
+          "status": null,
+          "scan": {
+            "kind": "NeverScanned",
+            "value": 40,
+            "text": null,
+            "time": null,
+            "time_unix": null
+          },
+          "errors": {
+            "kind": "Ok",
+            "value": 10,
+            "text": "No known data errors"
+          },
+          "devices": [
+            {
+              "name": "milton",
+              "vdev_type": "root",
+              "state": {
+                "kind": "Online",
+                "value": 10,
+                "text": "ONLINE"
+              },
+              "errors": {
+                "read": 0,
+                "write": 0,
+                "checksum": 0
+              },
+              "children": [
+                {
+                  "name": "mirror-0",
+                  "vdev_type": "mirror",
+                  "state": {
+                    "kind": "Online",
+                    "value": 10,
+                    "text": "ONLINE"
+                  },
+                  "errors": {
+                    "read": 0,
+                    "write": 0,
+                    "checksum": 0
+                  },
+                  "children": [
+                    {
+                      "name": "ata-ST8000VN004-2M2101_WSD4EYEW",
+                      "vdev_type": "disk",
+                      "state": {
+                        "kind": "Online",
+                        "value": 10,
+                        "text": "ONLINE"
+                      },
+                      "errors": {
+                        "read": 0,
+                        "write": 0,
+                        "checksum": 0
+                      },
+                      "children": []
+                    },
+                    {
+                      "name": "ata-ST8000VN004-2M2101_WSD49ZDC",
+                      "vdev_type": "disk",
+                      "state": {
+                        "kind": "Online",
+                        "value": 10,
+                        "text": "ONLINE"
+                      },
+                      "errors": {
+                        "read": 0,
+                        "write": 0,
+                        "checksum": 0
+                      },
+                      "children": []
+                    }
+                  ]
+                }
+              ]
+            }
+          ]
+        }
+      ]
+    }
+    "#);
+    Ok(())
+}
+
+#[test]
+fn scrub_progress() -> anyhow::Result<()> {
+    let json = run_test(include_str!("../input/input-04-scrub-progress.txt"))?;
+    insta::assert_snapshot!(json, @r#"
+    {
+      "pools": [
+        {
+          "name": "neptune",
+          "state": {
+            "kind": "Online",
+            "value": 10,
+            "text": "ONLINE"
+          },
+          "status": null,
+          "scan": {
+            "kind": "ScrubInProgress",
+            "value": 30,
+            "text": "scrub in progress since Sun Feb 11 11:54:20 2024\n704G / 1.66T scanned at 5.67G/s, 0B / 1.66T issued\n0B repaired, 0.00% done, no estimated completion time",
+            "time": "2024-02-11T11:54:20+00:00",
+            "time_unix": 1707652460
+          },
+          "errors": {
+            "kind": "Ok",
+            "value": 10,
+            "text": "No known data errors"
+          },
+          "devices": [
+            {
+              "name": "neptune",
+              "vdev_type": "root",
+              "state": {
+                "kind": "Online",
+                "value": 10,
+                "text": "ONLINE"
+              },
+              "errors": {
+                "read": 0,
+                "write": 0,
+                "checksum": 0
+              },
+              "children": [
+                {
+                  "name": "mirror-0",
+                  "vdev_type": "mirror",
+                  "state": {
+                    "kind": "Online",
+                    "value": 10,
+                    "text": "ONLINE"
+                  },
+                  "errors": {
+                    "read": 0,
+                    "write": 0,
+                    "checksum": 0
+                  },
+                  "children": [
+                    {
+                      "name": "wwn-0x5000c500af8ec287-part2",
+                      "vdev_type": "disk",
+                      "state": {
+                        "kind": "Online",
+                        "value": 10,
+                        "text": "ONLINE"
+                      },
+                      "errors": {
+                        "read": 0,
+                        "write": 0,
+                        "checksum": 0
+                      },
+                      "children": []
+                    },
+                    {
+                      "name": "ata-TOSHIBA_HDWQ140_50SRK3F3FBJG-part1",
+                      "vdev_type": "disk",
+                      "state": {
+                        "kind": "Online",
+                        "value": 10,
+                        "text": "ONLINE"
+                      },
+                      "errors": {
+                        "read": 0,
+                        "write": 0,
+                        "checksum": 0
+                      },
+                      "children": []
+                    }
+                  ]
+                }
+              ]
+            }
+          ]
+        }
+      ]
+    }
+    "#);
+    Ok(())
+}
+
+#[test]
+fn degraded_no_scan() -> anyhow::Result<()> {
+    let json = run_test(include_str!("../input/input-11-degraded-no-scan.txt"))?;
+    insta::assert_snapshot!(json, @r#"
+    {
+      "pools": [
+        {
+          "name": "broken",
+          "state": {
+            "kind": "Degraded",
+            "value": 50,
+            "text": "DEGRADED"
+          },
+          "status": {
+            "kind": "SufficientReplicasForMissing",
+            "value": 10,
+            "text": "One or more devices could not be used because the label is missing or\ninvalid.  Sufficient replicas exist for the pool to continue\nfunctioning in a degraded state."
+          },
+          "scan": null,
+          "errors": {
+            "kind": "Ok",
+            "value": 10,
+            "text": "No known data errors"
+          },
+          "devices": [
+            {
+              "name": "broken",
+              "vdev_type": "root",
+              "state": {
+                "kind": "Degraded",
+                "value": 50,
+                "text": "DEGRADED"
+              },
+              "errors": {
+                "read": 0,
+                "write": 0,
+                "checksum": 0
+              },
+              "children": [
+                {
+                  "name": "mirror-0",
+                  "vdev_type": "mirror",
+                  "state": {
+                    "kind": "Degraded",
+                    "value": 50,
+                    "text": "DEGRADED"
+                  },
+                  "errors": {
+                    "read": 0,
+                    "write": 0,
+                    "checksum": 0
+                  },
+                  "children": [
+                    {
+                      "name": "loop0",
+                      "vdev_type": "disk",
+                      "state": {
+                        "kind": "Online",
+                        "value": 10,
+                        "text": "ONLINE"
+                      },
+                      "errors": {
+                        "read": 0,
+                        "write": 0,
+                        "checksum": 0
+                      },
+                      "children": []
+                    },
+                    {
+                      "name": "loop1",
+                      "vdev_type": "disk",
+                      "state": {
+                        "kind": "Unavail",
+                        "value": 100,
+                        "text": "UNAVAIL"
+                      },
+                      "errors": {
+                        "read": 0,
+                        "write": 0,
+                        "checksum": 0
+                      },
+                      "children": []
+                    }
+                  ]
+                }
+              ]
+            }
+          ]
+        }
+      ]
+    }
+    "#);
+    Ok(())
+}
@@ -0,0 +1,67 @@
+use crate::{
+    HTTP_OK,
+    common::bin_cmd::{BinCommand, BinOutput},
+};
+use std::{os::unix::net::UnixDatagram, time::Duration};
+
+/// Returns the messages received on the socket, until none arrive within the read timeout
+fn receive_all(socket: &UnixDatagram) -> Vec<String> {
+    let mut messages = vec![];
+    let mut buf = [0; 1024];
+    while let Ok(len) = socket.recv(&mut buf) {
+        messages.push(String::from_utf8_lossy(&buf[..len]).into_owned());
+    }
+    messages
+}
+
+#[test]
+fn watchdog_status_stopping() -> anyhow::Result<()> {
+    const LISTEN_ADDRESS: &str = crate::common::LISTEN_ADDRESS_SD_NOTIFY;
+
+    let dir = tempfile::tempdir()?;
+    let notify_path = dir.path().join("notify.sock");
+    let notify_socket = UnixDatagram::bind(&notify_path)?;
+    notify_socket.set_read_timeout(Some(Duration::from_millis(500)))?;
+
+    let (output, status_code) = BinCommand::new()
+        .arg(LISTEN_ADDRESS)
+        .env("NOTIFY_SOCKET", &notify_path)
+        // keepalive every 100ms
+        .env("WATCHDOG_USEC", "200000")
+        .spawn_cleanup_with(|| {
+            let response = minreq::get(format!("http://{LISTEN_ADDRESS}/metrics")).send()?;
+            // next heartbeat after the scrape
+            std::thread::sleep(Duration::from_millis(250));
+            anyhow::Ok(response.status_code)
+        })?;
+
+    assert_eq!(status_code?, HTTP_OK);
+
+    let messages = receive_all(&notify_socket);
+    // ready and heartbeats are sent from separate threads, in any order
+    assert!(
+        messages.contains(&"READY=1\n".to_owned()),
+        "messages {messages:?}"
+    );
+    assert!(
+        messages.contains(&"WATCHDOG=1\nSTATUS=1 pool, no scrapes yet, 0 errors\n".to_owned()),
+        "messages {messages:?}"
+    );
+    assert!(
+        messages.contains(&"WATCHDOG=1\nSTATUS=1 pool, last scrape 0s ago, 0 errors\n".to_owned()),
+        "messages {messages:?}"
+    );
+    assert!(
+        messages.contains(&"STOPPING=1\n".to_owned()),
+        "messages {messages:?}"
+    );
+
+    let BinOutput {
+        status,
+        stdout: _,
+        stderr: _,
+    } = output;
+    assert!(status.success());
+
+    Ok(())
+}
@@ -0,0 +1,83 @@
+use crate::{
+    HTTP_OK,
+    common::bin_cmd::{BinCommand, BinOutput},
+};
+use std::{
+    io::{Read as _, Write as _},
+    net::{SocketAddr, TcpListener},
+    os::unix::net::{UnixListener, UnixStream},
+    str::FromStr,
+};
+
+#[test]
+fn activated_tcp() -> anyhow::Result<()> {
+    const LISTEN_ADDRESS: &str = crate::common::LISTEN_ADDRESS_SOCKET_ACTIVATION;
+
+    let listen_address = SocketAddr::from_str(LISTEN_ADDRESS)?;
+    let listener = TcpListener::bind(listen_address)?;
+
+    let (output, status_code) = BinCommand::new()
+        .activated_listener(listener, "metrics")
+        .spawn_cleanup_with(|| {
+            let response = minreq::get(format!("http://{listen_address}/metrics")).send()?;
+            anyhow::Ok(response.status_code)
+        })?;
+
+    assert_eq!(status_code?, HTTP_OK);
+
+    let BinOutput {
+        status,
+        stdout,
+        stderr: _,
+    } = output;
+    assert!(
+        stdout.starts_with(&format!(
+            "Listening at http://{LISTEN_ADDRESS} (socket activated, \"metrics\")\n"
+        )),
+        "stdout {stdout:?}"
+    );
+    assert!(status.success());
+
+    Ok(())
+}
+
+#[test]
+fn activated_unix() -> anyhow::Result<()> {
+    let dir = tempfile::tempdir()?;
+    let socket = dir.path().join("activated.sock");
+    let listener = UnixListener::bind(&socket)?;
+
+    let (output, response) = BinCommand::new()
+        .activated_listener(listener, "")
+        .spawn_cleanup_with(|| {
+            let mut stream = UnixStream::connect(&socket)?;
+            stream.write_all(b"GET /metrics HTTP/1.0\r\n\r\n")?;
+            let mut response = String::new();
+            stream.read_to_string(&mut response)?;
+            anyhow::Ok(response)
+        })?;
+
+    let response = response?;
+    assert!(
+        response.starts_with("HTTP/1.0 200 OK\r\n"),
+        "response {response:?}"
+    );
+
+    let BinOutput {
+        status,
+        stdout,
+        stderr: _,
+    } = output;
+    assert!(
+        stdout.starts_with(&format!(
+            "Listening at unix:{} (socket activated)\n",
+            socket.display()
+        )),
+        "stdout {stdout:?}"
+    );
+    assert!(status.success());
+    // owned by the service manager, not removed on shutdown
+    assert!(socket.exists());
+
+    Ok(())
+}
@@ -0,0 +1,109 @@
+use crate::common::bin_cmd::{BinCommand, BinOutput, FakeZpoolMode};
+use std::time::Duration;
+
+const FILE_NAME: &str = "zpool.prom";
+
+#[test]
+fn textfile_once() -> anyhow::Result<()> {
+    let dir = tempfile::tempdir()?;
+
+    let (output, ()) = BinCommand::new()
+        .arg("--textfile-output")
+        .arg_dynamic(dir.path().display().to_string())
+        .fake_zpool_mode(FakeZpoolMode::NoPools)
+        .spawn_cleanup_with(|| {})?;
+
+    {
+        let BinOutput {
+            status,
+            stdout,
+            stderr,
+        } = output;
+        assert_eq!(stdout, "");
+        assert_eq!(stderr, "");
+        assert!(status.success());
+    }
+
+    let contents = std::fs::read_to_string(dir.path().join(FILE_NAME))?;
+    assert!(contents.starts_with("# no pools reported\n"), "{contents}");
+    assert_eq!(contents.lines().last(), Some("zpool_up 1"), "{contents}");
+
+    // only the renamed file remains
+    let file_names = std::fs::read_dir(dir.path())?
+        .map(|entry| Ok(entry?.file_name()))
+        .collect::<anyhow::Result<Vec<_>>>()?;
+    assert_eq!(file_names, [FILE_NAME]);
+
+    Ok(())
+}
+
+#[test]
+fn textfile_interval_failure() -> anyhow::Result<()> {
+    let dir = tempfile::tempdir()?;
+    let marker_dir = tempfile::tempdir()?;
+    let marker = marker_dir.path().join("first-run-done");
+    let path = dir.path().join(FILE_NAME);
+
+    let (output, contents) = BinCommand::new()
+        .arg("--textfile-output")
+        .arg_dynamic(dir.path().display().to_string())
+        .arg("--poll-interval=1")
+        .fake_zpool_mode(FakeZpoolMode::FailAfterFirst(marker))
+        .spawn_cleanup_with(|| {
+            let first = std::fs::read_to_string(&path);
+            std::thread::sleep(Duration::from_millis(1500));
+            let second = std::fs::read_to_string(&path);
+            (first, second)
+        })?;
+
+    {
+        let BinOutput {
+            status,
+            stdout: _,
+            stderr,
+        } = output;
+        assert!(
+            stderr.contains("error: failed to create metrics (recorded as up = 0)"),
+            "{stderr}"
+        );
+        assert!(stderr.ends_with("user requested shutdown...\n"), "{stderr}");
+        assert!(status.success());
+    }
+
+    let (first, second) = contents;
+    let first = first?;
+    assert_eq!(first.lines().last(), Some("zpool_up 1"), "{first}");
+    let second = second?;
+    let lines: Vec<_> = second.lines().collect();
+    assert_eq!(lines.first(), Some(&"# ERROR:"), "{second}");
+    assert!(lines.contains(&"# ERROR_CODE: child_failed"), "{second}");
+    assert_eq!(lines.last(), Some(&"zpool_up 0"), "{second}");
+
+    Ok(())
+}
+
+#[test]
+fn textfile_rejects_server_args() -> anyhow::Result<()> {
+    let dir = tempfile::tempdir()?;
+
+    let (output, ()) = BinCommand::new()
+        .arg("--textfile-output")
+        .arg_dynamic(dir.path().display().to_string())
+        .arg("--basic-auth-keys-file=/nonexistent")
+        .spawn_cleanup_with(|| {})?;
+
+    let BinOutput {
+        status,
+        stdout,
+        stderr,
+    } = output;
+    assert_eq!(stdout, "");
+    assert!(
+        stderr.starts_with("error: the argument '--textfile-output <DIR>' cannot be used with"),
+        "{stderr}"
+    );
+    assert!(stderr.contains("--basic-auth-keys-file"), "{stderr}");
+    assert!(!status.success());
+
+    Ok(())
+}
@@ -0,0 +1,187 @@
+use crate::common::bin_cmd::{BinCommand, BinOutput};
+use rcgen::{
+    BasicConstraints, CertificateParams, CertifiedKey, DnType, ExtendedKeyUsagePurpose, IsCa,
+    KeyPair,
+};
+use rustls::pki_types::{CertificateDer, PrivatePkcs8KeyDer, ServerName};
+use std::{
+    io::{Read as _, Write as _},
+    net::{SocketAddr, TcpStream},
+    path::Path,
+    str::FromStr,
+    sync::Arc,
+    time::Duration,
+};
+
+/// Self-signed CA, for signing the server and client certificates
+struct TestCa {
+    certified: CertifiedKey,
+}
+impl TestCa {
+    fn new() -> anyhow::Result<Self> {
+        let key_pair = KeyPair::generate()?;
+        let mut params = CertificateParams::default();
+        params.is_ca = IsCa::Ca(BasicConstraints::Unconstrained);
+        params
+            .distinguished_name
+            .push(DnType::CommonName, "zpool-status-exporter test CA");
+        let cert = params.self_signed(&key_pair)?;
+        Ok(Self {
+            certified: CertifiedKey { cert, key_pair },
+        })
+    }
+    fn sign(&self, usage: ExtendedKeyUsagePurpose) -> anyhow::Result<CertifiedKey> {
+        let key_pair = KeyPair::generate()?;
+        let mut params = CertificateParams::new(vec!["localhost".to_string()])?;
+        params.extended_key_usages = vec![usage];
+        let CertifiedKey {
+            cert: ca_cert,
+            key_pair: ca_key,
+        } = &self.certified;
+        let cert = params.signed_by(&key_pair, ca_cert, ca_key)?;
+        Ok(CertifiedKey { cert, key_pair })
+    }
+}
+
+fn write_server_files(dir: &Path, server: &CertifiedKey) -> anyhow::Result<()> {
+    std::fs::write(dir.join("cert.pem"), server.cert.pem())?;
+    std::fs::write(dir.join("key.pem"), server.key_pair.serialize_pem())?;
+    Ok(())
+}
+
+struct HttpsResponse {
+    /// First certificate presented by the server
+    server_cert: CertificateDer<'static>,
+    content: String,
+}
+
+fn https_get(
+    address: SocketAddr,
+    ca: &TestCa,
+    client: Option<&CertifiedKey>,
+    path: &str,
+) -> anyhow::Result<HttpsResponse> {
+    let mut roots = rustls::RootCertStore::empty();
+    roots.add(ca.certified.cert.der().clone())?;
+
+    let provider = Arc::new(rustls::crypto::ring::default_provider());
+    let builder = rustls::ClientConfig::builder_with_provider(provider)
+        .with_safe_default_protocol_versions()?
+        .with_root_certificates(roots);
+    let config = if let Some(client) = client {
+        let key = PrivatePkcs8KeyDer::from(client.key_pair.serialize_der());
+        builder.with_client_auth_cert(vec![client.cert.der().clone()], key.into())?
+    } else {
+        builder.with_no_client_auth()
+    };
+
+    let server_name = ServerName::try_from("localhost")?;
+    let connection = rustls::ClientConnection::new(Arc::new(config), server_name)?;
+    let socket = TcpStream::connect(address)?;
+    socket.set_read_timeout(Some(Duration::from_secs(5)))?;
+    let mut stream = rustls::StreamOwned::new(connection, socket);
+
+    write!(
+        stream,
+        "GET {path} HTTP/1.1\r\nHost: localhost\r\nConnection: close\r\n\r\n"
+    )?;
+    let mut content = String::new();
+    stream.read_to_string(&mut content)?;
+
+    let server_cert = stream
+        .conn
+        .peer_certificates()
+        .and_then(<[_]>::first)
+        .ok_or_else(|| anyhow::anyhow!("no server certificate"))?
+        .clone();
+    Ok(HttpsResponse {
+        server_cert,
+        content,
+    })
+}
+
+#[test]
+fn client_cert_and_reload() -> anyhow::Result<()> {
+    const LISTEN_ADDRESS: &str = crate::common::LISTEN_ADDRESS_TLS;
+
+    let listen_address = SocketAddr::from_str(LISTEN_ADDRESS)?;
+
+    let ca = TestCa::new()?;
+    let server_first = ca.sign(ExtendedKeyUsagePurpose::ServerAuth)?;
+    let server_second = ca.sign(ExtendedKeyUsagePurpose::ServerAuth)?;
+    let client = ca.sign(ExtendedKeyUsagePurpose::ClientAuth)?;
+
+    let dir = tempfile::tempdir()?;
+    write_server_files(dir.path(), &server_first)?;
+    std::fs::write(dir.path().join("ca.pem"), ca.certified.cert.pem())?;
+    let dir_str = |name: &str| dir.path().join(name).to_string_lossy().into_owned();
+
+    let (output, (with_client_cert, without_client_cert, after_reload)) = BinCommand::new()
+        .arg(LISTEN_ADDRESS)
+        .arg("--tls-cert-file")
+        .arg_dynamic(dir_str("cert.pem"))
+        .arg("--tls-key-file")
+        .arg_dynamic(dir_str("key.pem"))
+        .arg("--tls-client-ca-file")
+        .arg_dynamic(dir_str("ca.pem"))
+        .spawn_cleanup_with(|| {
+            let with_client_cert = https_get(listen_address, &ca, Some(&client), "/metrics");
+            let without_client_cert = https_get(listen_address, &ca, None, "/metrics");
+
+            let after_reload = write_server_files(dir.path(), &server_second)
+                .and_then(|()| https_get(listen_address, &ca, Some(&client), "/"));
+
+            (with_client_cert, without_client_cert, after_reload)
+        })?;
+
+    {
+        let BinOutput {
+            status,
+            stdout,
+            stderr,
+        } = output;
+
+        assert_eq!(
+            stdout,
+            format!("Listening at https://{listen_address}\nReloaded TLS certificate files\n"),
+            "stdout"
+        );
+        let stderr_lines: Vec<_> = stderr.lines().collect();
+        assert_eq!(stderr_lines.len(), 4, "stderr {stderr:?}");
+        assert!(
+            stderr_lines[2].starts_with("TLS connection from 127.0.0.1:"),
+            "stderr {stderr:?}"
+        );
+        assert!(
+            stderr_lines[2].contains("peer sent no certificates"),
+            "stderr {stderr:?}"
+        );
+        assert_eq!(stderr_lines[3], "user requested shutdown...");
+        assert!(status.success());
+    }
+
+    let with_client_cert = with_client_cert?;
+    assert!(
+        with_client_cert.content.starts_with("HTTP/1.1 200 OK\r\n"),
+        "{}",
+        with_client_cert.content
+    );
+    assert!(
+        with_client_cert.content.ends_with("zpool_up 1\n"),
+        "{}",
+        with_client_cert.content
+    );
+    assert_eq!(with_client_cert.server_cert, *server_first.cert.der());
+
+    assert!(without_client_cert.is_err(), "client certificate required");
+
+    let after_reload = after_reload?;
+    assert!(
+        after_reload.content.starts_with("HTTP/1.1 200 OK\r\n"),
+        "{}",
+        after_reload.content
+    );
+    assert_eq!(after_reload.server_cert, *server_second.cert.der());
+
+    Ok(())
+}
@@ -0,0 +1,72 @@
+use crate::common::bin_cmd::{BinCommand, BinOutput};
+use std::time::{Duration, Instant};
+
+#[test]
+fn zpool_path_not_found() -> anyhow::Result<()> {
+    const LISTEN_ADDRESS: &str = crate::common::LISTEN_ADDRESS_ZPOOL_PATH;
+
+    let (output, ()) = BinCommand::new()
+        .arg(LISTEN_ADDRESS)
+        .arg("--zpool-path")
+        .arg("/nonexistent/zpool")
+        .spawn_cleanup_with(|| {})?;
+
+    let BinOutput {
+        status,
+        stdout,
+        stderr,
+    } = output;
+    insta::assert_snapshot!(stdout, @"");
+    insta::assert_snapshot!(stderr, @r###"
+    Error: failed to create metrics
+
+    Caused by:
+        0: failed to execute zpool command
+        1: command "/nonexistent/zpool" (args ["status", "-p"]) should spawn a process
+        2: No such file or directory (os error 2)
+    "###);
+    assert!(!status.success());
+
+    Ok(())
+}
+
+#[test]
+fn zpool_wrapper_timeout() -> anyhow::Result<()> {
+    const LISTEN_ADDRESS: &str = crate::common::LISTEN_ADDRESS_ZPOOL_WRAPPER;
+    const BIN_EXE_ZPOOL: &str = env!("CARGO_BIN_EXE_zpool");
+
+    // the wrapper selects the fake zpool mode
+    let mut child = BinCommand::new()
+        .arg(LISTEN_ADDRESS)
+        .arg("--zpool-path")
+        .arg(BIN_EXE_ZPOOL)
+        .arg("--zpool-wrapper")
+        .arg("/usr/bin/env FAKE_ZPOOL_MODE=sleep-forever")
+        .arg("--zpool-timeout")
+        .arg("1")
+        .spawn()?;
+
+    let start_time = Instant::now();
+    while start_time.elapsed() < Duration::from_secs(10) && !child.is_finished()? {
+        std::thread::sleep(Duration::from_millis(100));
+    }
+    assert!(child.is_finished()?, "child should timeout on time");
+
+    let BinOutput {
+        status,
+        stdout,
+        stderr,
+    } = child.kill_await_output()?;
+    assert_eq!(stdout, "");
+    let expected = format!(
+        concat!(
+            "1: command \"/usr/bin/env\" (args [\"FAKE_ZPOOL_MODE=sleep-forever\", {zpool:?}, ",
+            "\"status\", \"-p\"]) should complete within the timeout of 1s\n",
+        ),
+        zpool = BIN_EXE_ZPOOL,
+    );
+    assert!(stderr.contains(&expected), "stderr {stderr:?}");
+    assert!(!status.success());
+
+    Ok(())
+}
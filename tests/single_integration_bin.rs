@@ -19,17 +19,60 @@ mod common {
     const LISTEN_ADDRESS_CHILD_SILENT_2: &str = "127.0.0.1:9587";
     const LISTEN_ADDRESS_CHILD_EXITCODE_1: &str = "127.0.0.1:9588";
     const LISTEN_ADDRESS_CHILD_EXITCODE_2: &str = "127.0.0.1:9589";
+    const LISTEN_ADDRESS_METRIC_NAMING: &str = "127.0.0.1:9590";
+    const LISTEN_ADDRESS_FAILURE_UP_ZERO: &str = "127.0.0.1:9591";
+    const LISTEN_ADDRESS_FAILURE_UNAVAILABLE: &str = "127.0.0.1:9592";
+    const LISTEN_ADDRESS_COLLECT_MIN_INTERVAL: &str = "127.0.0.1:9593";
+    const LISTEN_ADDRESS_COLLECT_POLL_INTERVAL: &str = "127.0.0.1:9594";
+    const LISTEN_ADDRESS_CONCURRENT: &str = "127.0.0.1:9595";
+    const LISTEN_ADDRESS_TLS: &str = "127.0.0.1:9596";
+    const LISTEN_ADDRESS_AUTH_RELOAD_SIGHUP: &str = "127.0.0.1:9597";
+    const LISTEN_ADDRESS_AUTH_RELOAD_WATCH: &str = "127.0.0.1:9598";
+    const LISTEN_ADDRESS_BEARER_AUTH: &str = "127.0.0.1:9599";
+    const LISTEN_ADDRESS_ALLOW_CIDR: &str = "127.0.0.1:9600";
+    const LISTEN_ADDRESS_MULTI_LISTEN_1: &str = "127.0.0.1:9601";
+    const LISTEN_ADDRESS_MULTI_LISTEN_2: &str = "127.0.0.1:9602";
+    const LISTEN_ADDRESS_SOCKET_ACTIVATION: &str = "127.0.0.1:9603";
+    const LISTEN_ADDRESS_SD_NOTIFY: &str = "127.0.0.1:9604";
+    const LISTEN_ADDRESS_HEALTH_PUBLIC: &str = "127.0.0.1:9605";
+    const LISTEN_ADDRESS_HEALTH_AUTHENTICATED: &str = "127.0.0.1:9606";
+    const LISTEN_ADDRESS_RUN_AS: &str = "127.0.0.1:9607";
+    const LISTEN_ADDRESS_RUN_AS_UNKNOWN: &str = "127.0.0.1:9608";
+    const LISTEN_ADDRESS_ZPOOL_PATH: &str = "127.0.0.1:9609";
+    const LISTEN_ADDRESS_ZPOOL_WRAPPER: &str = "127.0.0.1:9610";
+    const LISTEN_ADDRESS_PUSHGATEWAY: &str = "127.0.0.1:9611";
+    const LISTEN_ADDRESS_PUSHGATEWAY_FAILURE: &str = "127.0.0.1:9612";
+    const LISTEN_ADDRESS_RUN_AS_GROUPS: &str = "127.0.0.1:9613";
 
     type MiniReqResult = Result<minreq::Response, minreq::Error>;
 
+    mod allow_cidr;
+    mod auth_reload;
+    mod bearer_auth;
+    mod check;
     mod child_exitcode;
     mod child_silent;
     mod child_stderr;
+    mod collect_mode;
+    mod concurrent;
     mod end_to_end;
     mod end_to_end_auth;
-    mod oneshot;
+    mod failure_response;
+    mod generate_rules;
+    mod health;
+    mod metric_naming;
+    mod multi_listen;
+    mod parse;
+    mod push;
+    mod run_as;
+    mod sd_notify;
+    mod socket_activation;
+    mod textfile;
+    mod tls;
+    mod zpool_command;
 
     mod sans_io_cases;
+    mod sans_io_json;
 
     mod bin_cmd;
 }
@@ -46,6 +89,9 @@ const HTTP_NOT_FOUND_STRING: &str = "Not Found";
 /// line-by-line comparison, to filter out timestamp-sensitive items
 fn assert_matches_template(response: &str, expected: &str) {
     const IGNORE_MARKER: &str = "<IGNORE>";
+    const VERSION_MARKER: &str = "<VERSION>";
+
+    let expected = &expected.replace(VERSION_MARKER, env!("CARGO_PKG_VERSION"));
 
     println!("response:\n{response}\n--------------------------------------------------");
     println!("expected:\n{expected}\n--------------------------------------------------");